@@ -3,13 +3,20 @@ use crate::values_linux::*;
 #[cfg(target_os = "windows")]
 use crate::values_windows::*;
 use clap::{Args, Parser, Subcommand};
+use collector_core::diff::DiffFormat;
+use collector_core::known::KnownAction;
+use collector_core::manifest::ManifestFormat;
+use collector_core::sink::ArchiveFormat;
+use serde::Serialize;
+use std::path::PathBuf;
 
 /// This is the best and fast artifact collector.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Serialize)]
 #[command(version, about, long_about = None)]
 pub struct ArgsCollector {
     #[command(subcommand)]
-    pub command: Option<ResourcesCommand>,
+    #[serde(skip)]
+    pub command: Option<Command>,
 
     /// The source path of collecting artifact.
     #[arg(short,long, default_value=SOURCE_PATH)]
@@ -29,14 +36,135 @@ pub struct ArgsCollector {
     #[arg(short,long,default_value=PATH_RESOURCE)]
     pub path_resources: String,
 
-    /// Zip the output directory.
+    /// Archive the output directory. (ZIP unless --format is set)
     #[arg(long)]
     pub zip: bool,
 
+    /// Archive format: zip, tar, tar.gz or tar.zst.
+    #[arg(long)]
+    pub format: Option<ArchiveFormat>,
+
+    /// Split the archive into volumes of this size. Example: 700M, 4G
+    #[arg(long)]
+    pub volume_size: Option<String>,
+
     /// Set zip password.
     #[arg(long)]
+    #[serde(skip)]
     pub pass: Option<String>,
 
+    /// Encrypt the archive to this age public key (age1...). Repeatable.
+    /// Needs --direct, or --zip which stages a plaintext copy first.
+    #[arg(long = "recipient", value_name = "KEY")]
+    pub recipients: Vec<String>,
+
+    /// Sign the manifest seal with this Ed25519 key (64 hex characters or a file containing them).
+    #[arg(long, value_name = "HEX|FILE")]
+    #[serde(skip)]
+    pub signing_key: Option<String>,
+
+    /// Previous collection (folder, archive or Collector_copy.csv): files whose size, mtime and inode did not change are listed but not copied.
+    #[arg(long, value_name = "PATH")]
+    pub previous: Option<String>,
+
+    /// Indicator list (md5:, sha1:, sha256:, filename: or path: lines) to sweep collected files with. Repeatable.
+    #[arg(long = "ioc", value_name = "PATH")]
+    pub iocs: Vec<String>,
+
+    /// YARA-style rule file, or a folder of .yar/.yara files, to scan collected content with. Repeatable.
+    #[arg(long = "rules", value_name = "PATH")]
+    pub rules: Vec<String>,
+
+    /// Only collect files larger than this when they match a rule. Example: 50M
+    #[arg(long, value_name = "SIZE", requires = "rules")]
+    pub rules_only_above: Option<String>,
+
+    /// Known-good hash set: a SHA-1 list or an NSRL RDS SQLite database. Repeatable.
+    #[arg(long = "known-hashes", value_name = "PATH")]
+    pub known_hashes: Vec<String>,
+
+    /// What to do with files found in a known-good hash set: skip or mark.
+    /// With --direct archive output, skip reads every file twice (hash, then copy).
+    #[arg(long, value_name = "ACTION")]
+    pub known_action: Option<KnownAction>,
+
+    /// Inventory mode: list every file with its metadata in the manifest, without copying content.
+    #[arg(long)]
+    pub inventory: bool,
+
+    /// Folder to walk recursively in inventory mode, instead of the resource patterns. Repeatable.
+    #[arg(long = "inventory-root", value_name = "PATH", requires = "inventory")]
+    pub inventory_roots: Vec<String>,
+
+    /// Hash every file listed in inventory mode.
+    #[arg(long, requires = "inventory")]
+    pub inventory_hash: bool,
+
+    /// Write a TSK bodyfile and a Timesketch JSONL timeline of the source metadata next to the manifest.
+    #[arg(long)]
+    pub timeline: bool,
+
+    /// Write a DFXML description of the collection (creator, environment and one file object per file) next to the manifest.
+    #[arg(long)]
+    pub dfxml: bool,
+
+    /// Write a CASE/UCO JSON-LD provenance of the collection (action, tool, examiner, host and files) next to the manifest.
+    #[arg(long)]
+    pub case_uco: bool,
+
+    /// Also write the manifest as jsonl or sqlite (the CSV manifest is always written). Repeatable.
+    #[arg(long = "manifest-format", value_name = "FORMAT")]
+    pub manifest_formats: Vec<ManifestFormat>,
+
+    /// Case number recorded in the case metadata.
+    #[arg(long)]
+    pub case_number: Option<String>,
+
+    /// Examiner name recorded in the case metadata.
+    #[arg(long)]
+    pub examiner: Option<String>,
+
+    /// Free-text notes recorded in the case metadata.
+    #[arg(long)]
+    pub case_notes: Option<String>,
+
+    /// Write files straight into the archive, without a staging copy. (Implies --zip)
+    #[arg(long)]
+    pub direct: bool,
+
+    /// Write a JSON summary of the run (parameters, resources, stats, outputs, warnings and failures) to this file, or to stdout with "-".
+    #[arg(long, value_name = "PATH|-")]
+    pub summary_json: Option<String>,
+
+    /// Write every collection event (plan, artifact and file progress, failures, archive progress) to this file as JSON lines.
+    #[arg(long, value_name = "PATH")]
+    pub events_json: Option<String>,
+
+    /// Limit reads (copies and hashing) to this many bytes per second. Example: 20M
+    #[arg(long, value_name = "SIZE")]
+    pub max_bytes_per_sec: Option<String>,
+
+    /// Limit the collection to this many files per second. Example: 50, 0.5
+    #[arg(long, value_name = "RATE")]
+    pub max_files_per_sec: Option<f64>,
+
+    /// Run with the lowest I/O priority (ioprio on Linux, background mode on Windows).
+    #[arg(long)]
+    pub low_io_priority: bool,
+
+    /// CPU niceness of the collection, from -20 (highest priority) to 19 (lowest).
+    #[arg(
+        long,
+        value_name = "N",
+        allow_negative_numbers = true,
+        value_parser = clap::value_parser!(i32).range(-20..=19)
+    )]
+    pub nice: Option<i32>,
+
+    /// Continue an interrupted collection in the destination folder. Files already copied are checked by hash and kept.
+    #[arg(long, conflicts_with = "direct")]
+    pub resume: bool,
+
     /// Collect from vss. (Take more time)
     #[cfg(target_os = "windows")]
     #[arg(long)]
@@ -56,9 +184,13 @@ pub struct ArgsCollector {
 }
 
 #[derive(Subcommand, Debug)]
-pub enum ResourcesCommand {
+pub enum Command {
     /// Resource list options
     Resources(ResourcesArgs),
+    /// Re-check a collection folder or archive against its manifest
+    Verify(VerifyArgs),
+    /// Compare two collections of the same host by source path and hash
+    Diff(DiffArgs),
 }
 
 #[derive(Debug, Args)]
@@ -67,6 +199,33 @@ pub struct ResourcesArgs {
     pub command: ListResources,
 }
 
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Collection folder (holding Collector_copy.csv) or archive (.zip, .tar, .tar.gz, .tar.zst).
+    pub path: PathBuf,
+
+    /// Require the manifest seal to be signed by this Ed25519 public key (64 hex characters or a file containing them).
+    #[arg(long, value_name = "HEX|FILE")]
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Older collection: folder, archive or Collector_copy.csv.
+    pub old: PathBuf,
+
+    /// Newer collection: folder, archive or Collector_copy.csv.
+    pub new: PathBuf,
+
+    /// Output format: csv or json.
+    #[arg(long, default_value = "csv")]
+    pub format: DiffFormat,
+
+    /// Write the diff to this file instead of the standard output.
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum ListResources {
     /// List all target names
@@ -1,4 +1,7 @@
 use crate::args::ArgsCollector;
+use collector_core::known::KnownAction;
+use collector_core::manifest::ManifestFormat;
+use collector_core::sink::ArchiveFormat;
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -12,6 +15,33 @@ pub(crate) struct Config {
     verbose: Option<bool>,
     zip: Option<bool>,
     zip_pass: Option<String>,
+    recipients: Option<Vec<String>>,
+    format: Option<ArchiveFormat>,
+    volume_size: Option<String>,
+    direct: Option<bool>,
+    signing_key: Option<String>,
+    previous: Option<String>,
+    known_hashes: Option<Vec<String>>,
+    iocs: Option<Vec<String>>,
+    rules: Option<Vec<String>>,
+    rules_only_above: Option<String>,
+    known_action: Option<KnownAction>,
+    timeline: Option<bool>,
+    dfxml: Option<bool>,
+    case_uco: Option<bool>,
+    manifest_formats: Option<Vec<ManifestFormat>>,
+    inventory: Option<bool>,
+    inventory_roots: Option<Vec<String>>,
+    inventory_hash: Option<bool>,
+    case_number: Option<String>,
+    examiner: Option<String>,
+    case_notes: Option<String>,
+    summary_json: Option<String>,
+    events_json: Option<String>,
+    max_bytes_per_sec: Option<String>,
+    max_files_per_sec: Option<f64>,
+    low_io_priority: Option<bool>,
+    nice: Option<i32>,
     #[cfg(target_os = "windows")]
     vss: Option<bool>,
     log: Option<bool>,
@@ -51,6 +81,114 @@ impl Config {
             args.pass = self.zip_pass;
         }
 
+        if args.recipients.is_empty() {
+            args.recipients = self.recipients.unwrap_or_default();
+        }
+
+        if args.format.is_none() {
+            args.format = self.format;
+        }
+
+        if args.volume_size.is_none() {
+            args.volume_size = self.volume_size;
+        }
+
+        if !args.direct {
+            args.direct = self.direct.unwrap_or(false);
+        }
+
+        if args.signing_key.is_none() {
+            args.signing_key = self.signing_key;
+        }
+
+        if args.previous.is_none() {
+            args.previous = self.previous;
+        }
+
+        if args.iocs.is_empty() {
+            args.iocs = self.iocs.unwrap_or_default();
+        }
+
+        if args.rules.is_empty() {
+            args.rules = self.rules.unwrap_or_default();
+        }
+
+        if args.rules_only_above.is_none() {
+            args.rules_only_above = self.rules_only_above;
+        }
+
+        if args.known_hashes.is_empty() {
+            args.known_hashes = self.known_hashes.unwrap_or_default();
+        }
+
+        if args.known_action.is_none() {
+            args.known_action = self.known_action;
+        }
+
+        if !args.timeline {
+            args.timeline = self.timeline.unwrap_or(false);
+        }
+
+        if !args.dfxml {
+            args.dfxml = self.dfxml.unwrap_or(false);
+        }
+
+        if !args.case_uco {
+            args.case_uco = self.case_uco.unwrap_or(false);
+        }
+
+        if args.manifest_formats.is_empty() {
+            args.manifest_formats = self.manifest_formats.unwrap_or_default();
+        }
+
+        if !args.inventory {
+            args.inventory = self.inventory.unwrap_or(false);
+        }
+
+        if args.inventory_roots.is_empty() {
+            args.inventory_roots = self.inventory_roots.unwrap_or_default();
+        }
+
+        if !args.inventory_hash {
+            args.inventory_hash = self.inventory_hash.unwrap_or(false);
+        }
+
+        if args.case_number.is_none() {
+            args.case_number = self.case_number;
+        }
+
+        if args.examiner.is_none() {
+            args.examiner = self.examiner;
+        }
+
+        if args.case_notes.is_none() {
+            args.case_notes = self.case_notes;
+        }
+
+        if args.summary_json.is_none() {
+            args.summary_json = self.summary_json;
+        }
+
+        if args.events_json.is_none() {
+            args.events_json = self.events_json;
+        }
+
+        if args.max_bytes_per_sec.is_none() {
+            args.max_bytes_per_sec = self.max_bytes_per_sec;
+        }
+
+        if args.max_files_per_sec.is_none() {
+            args.max_files_per_sec = self.max_files_per_sec;
+        }
+
+        if !args.low_io_priority {
+            args.low_io_priority = self.low_io_priority.unwrap_or(false);
+        }
+
+        if args.nice.is_none() {
+            args.nice = self.nice;
+        }
+
         #[cfg(target_os = "windows")]
         if !args.vss {
             args.vss = self.vss.unwrap_or(false);
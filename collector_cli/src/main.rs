@@ -6,19 +6,42 @@ mod values_linux;
 #[cfg(target_os = "windows")]
 mod values_windows;
 
-use args::{ArgsCollector, ListResources, ResourcesCommand};
+use args::{ArgsCollector, Command, DiffArgs, ListResources, VerifyArgs};
 use clap::Parser;
+use collector_core::csv::seal::{SignatureStatus, parse_signing_key, parse_verifying_key};
+use collector_core::diff::{ChangeKind, diff_collections};
+use collector_core::error::{EXIT_CANCELLED, EXIT_MISMATCH, EXIT_SUCCESS};
+use collector_core::event::JsonEventLog;
+use collector_core::failure::{FailureReport, summarize};
 use collector_core::prelude::*;
+use collector_core::summary::{OutputFile, RunSummary};
+use collector_core::utils::parse_size;
+use collector_core::verify::verify_collection;
 use config::Config;
 use log::LevelFilter;
 use simplelog::{
     ColorChoice, CombinedLogger, ConfigBuilder, TermLogger, TerminalMode, WriteLogger,
 };
 use std::fs::File;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Set when the JSON summary is written to stdout.
+static SUMMARY_TO_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for progress output, moved to stderr when stdout carries the JSON summary.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if SUMMARY_TO_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
 fn setup_logging(args: &ArgsCollector, log_filename: &str) -> Result<()> {
     let config = if args.verbose {
         ConfigBuilder::new().set_time_format_rfc3339().build()
@@ -34,12 +57,17 @@ fn setup_logging(args: &ArgsCollector, log_filename: &str) -> Result<()> {
         source: e,
     })?;
 
+    let terminal_mode = if SUMMARY_TO_STDOUT.load(Ordering::Relaxed) {
+        TerminalMode::Stderr
+    } else {
+        TerminalMode::Mixed
+    };
     let loggers: Vec<Box<dyn simplelog::SharedLogger>> = if args.log {
         vec![
             TermLogger::new(
                 LevelFilter::Info,
                 config.clone(),
-                TerminalMode::Mixed,
+                terminal_mode,
                 ColorChoice::Auto,
             ),
             WriteLogger::new(LevelFilter::Info, config, file),
@@ -54,16 +82,16 @@ fn setup_logging(args: &ArgsCollector, log_filename: &str) -> Result<()> {
 }
 
 fn print_header() {
-    println!();
-    println!("╔══════════════════════════════════════════════════╗");
-    println!("║            COLLECTOR CLI v{}                 ║", VERSION);
-    println!("║       Fast Artifact Collector for DFIR           ║");
-    println!("╚══════════════════════════════════════════════════╝");
-    println!();
+    say!();
+    say!("╔══════════════════════════════════════════════════╗");
+    say!("║            COLLECTOR CLI v{}                 ║", VERSION);
+    say!("║       Fast Artifact Collector for DFIR           ║");
+    say!("╚══════════════════════════════════════════════════╝");
+    say!();
 }
 
 fn print_separator() {
-    println!("{}", "─".repeat(52));
+    say!("{}", "─".repeat(52));
 }
 
 async fn handle_resources_command(args: &ArgsCollector, cmd: &ListResources) -> Result<()> {
@@ -121,7 +149,115 @@ async fn handle_resources_command(args: &ArgsCollector, cmd: &ListResources) ->
     Ok(())
 }
 
-async fn run_collection(args: ArgsCollector) -> Result<()> {
+/// Verify a collection, returning whether it matches its manifest.
+async fn handle_verify_command(args: &VerifyArgs) -> Result<bool> {
+    let trusted_key = args
+        .public_key
+        .as_deref()
+        .map(parse_verifying_key)
+        .transpose()?;
+
+    print_header();
+    println!("  Verifying:    {}", args.path.display());
+    print_separator();
+
+    let report = verify_collection(&args.path, trusted_key.as_ref()).await?;
+
+    println!("\n  Files checked: {}", report.files_checked);
+    println!("  Matched:       {}", report.matched);
+    println!("  Reports:       {}", report.reports_matched);
+    if report.skipped > 0 {
+        println!("  Not copied:    {}", report.skipped);
+    }
+
+    for file in &report.modified {
+        println!(
+            "  MODIFIED  {} (expected {}, found {})",
+            file.path, file.expected_sha1, file.actual_sha1
+        );
+    }
+    for path in &report.missing {
+        println!("  MISSING   {}", path);
+    }
+    for path in &report.extra {
+        println!("  EXTRA     {}", path);
+    }
+    for path in &report.modified_reports {
+        println!("  MODIFIED  {} (does not match its sealed digest)", path);
+    }
+    for path in &report.unverified {
+        println!("  UNVERIFIED {} (no sealed digest)", path);
+    }
+
+    match report.manifest {
+        Some(ref manifest) => {
+            if let Some(row) = manifest.broken_row {
+                println!("  Manifest hash chain broken at row {}", row);
+            }
+            if !manifest.rows_match || !manifest.chain_matches || !manifest.digest_matches {
+                println!("  Manifest does not match its seal");
+            }
+            let signature = match manifest.signature {
+                SignatureStatus::Unsigned => "not signed",
+                SignatureStatus::Missing => "MISSING (a public key was given)",
+                SignatureStatus::Valid => "valid",
+                SignatureStatus::Invalid => "INVALID",
+                SignatureStatus::UntrustedKey => "valid but from an UNTRUSTED key",
+            };
+            println!("  Seal signature: {}", signature);
+            if manifest.cancelled {
+                println!("  Collection was cancelled: the manifest lists a partial collection");
+            }
+        }
+        None => println!("  No manifest seal found"),
+    }
+
+    print_separator();
+    if report.is_ok() {
+        println!("\n  Collection verified: no missing, extra or modified files\n");
+    } else {
+        println!(
+            "\n  Verification FAILED: {} modified, {} missing, {} extra\n",
+            report.modified.len() + report.modified_reports.len(),
+            report.missing.len(),
+            report.extra.len()
+        );
+    }
+
+    Ok(report.is_ok())
+}
+
+/// Diff two collections, to a file with a summary or straight to stdout.
+async fn handle_diff_command(args: &DiffArgs) -> Result<()> {
+    let diff = diff_collections(&args.old, &args.new).await?;
+    let rendered = diff.render(args.format).await?;
+
+    let Some(ref output) = args.output else {
+        std::io::Write::write_all(&mut std::io::stdout(), &rendered)?;
+        return Ok(());
+    };
+
+    std::fs::write(output, &rendered).map_err(|e| CollectorError::FileWrite {
+        path: output.clone(),
+        source: e,
+    })?;
+
+    print_header();
+    println!("  Old:        {}", args.old.display());
+    println!("  New:        {}", args.new.display());
+    print_separator();
+    println!("\n  Added:      {}", diff.count(ChangeKind::Added));
+    println!("  Removed:    {}", diff.count(ChangeKind::Removed));
+    println!("  Modified:   {}", diff.count(ChangeKind::Modified));
+    println!("  Failed:     {}", diff.count(ChangeKind::Failed));
+    println!("  Unchanged:  {}", diff.unchanged);
+    println!("\n  Diff written to {}\n", output.display());
+
+    Ok(())
+}
+
+/// Collect, filling `summary` as the run goes.
+async fn run_collection(args: ArgsCollector, summary: &mut RunSummary) -> Result<()> {
     let hostname = sysinfo::System::host_name().unwrap_or_else(|| "unknown".into());
     let timestamp = chrono::Utc::now().timestamp();
     let log_filename = format!("collector_{}_{}.log", hostname, timestamp);
@@ -129,6 +265,12 @@ async fn run_collection(args: ArgsCollector) -> Result<()> {
 
     setup_logging(&args, &log_filename)?;
 
+    let case = CaseInfo::new()
+        .with_case_number(args.case_number.clone())
+        .with_examiner(args.examiner.clone())
+        .with_notes(args.case_notes.clone());
+    let case_metadata = CaseMetadata::new(case.clone());
+
     print_header();
 
     log::info!("{}", "=".repeat(50));
@@ -137,93 +279,332 @@ async fn run_collection(args: ArgsCollector) -> Result<()> {
     log::info!("Resources: {:?}", args.resources);
     log::info!("Resources path: {}", args.path_resources);
     log::info!("Log file: {}", log_filename);
+    if let Some(ref case_number) = case.case_number {
+        log::info!("Case: {}", case_number);
+    }
+    if let Some(ref examiner) = case.examiner {
+        log::info!("Examiner: {}", examiner);
+    }
     log::info!("{}", "=".repeat(50));
 
-    println!("  Source:       {}", args.source);
-    println!("  Destination:  {}", args.destination);
-    println!("  Resources:    {:?}", args.resources);
+    say!("  Source:       {}", args.source);
+    say!("  Destination:  {}", args.destination);
+    say!("  Resources:    {:?}", args.resources);
     if verbose {
-        println!("  Resources path: {}", args.path_resources);
+        say!("  Resources path: {}", args.path_resources);
+    }
+    say!("  Log file:     {}", log_filename);
+    if let Some(ref case_number) = case.case_number {
+        say!("  Case:         {}", case_number);
+    }
+    if let Some(ref examiner) = case.examiner {
+        say!("  Examiner:     {}", examiner);
     }
-    println!("  Log file:     {}", log_filename);
     print_separator();
 
     // Parse resources
-    println!("\n[1/4] Parsing resource files...");
+    say!("\n[1/4] Parsing resource files...");
     log::info!("Parsing resource files");
 
     let mut parser = ResourcesParser::new(&args.path_resources)?;
     let artifacts = parser.get_doc_struct().await?;
     let patterns = parser.select_artifact(args.resources.clone(), &artifacts)?;
-
-    println!("      Found {} artifact patterns", patterns.len());
+    let resource_set_hash = parser.resource_set_hash().await?;
+    let resources = parser.selected_artifacts().to_vec();
+    summary.resources = resources.clone();
+    summary.resource_set_sha256 = Some(resource_set_hash.clone());
+    summary.patterns = patterns.len();
+    let case_metadata = case_metadata.with_resources(resources, Some(resource_set_hash));
+
+    say!("      Found {} artifact patterns", patterns.len());
     log::info!("Found {} artifact patterns", patterns.len());
 
     if verbose {
-        println!("      Patterns:");
+        say!("      Patterns:");
         for pattern in &patterns {
-            println!("        • {}", pattern);
+            say!("        • {}", pattern);
         }
     }
 
     // Create collector
-    println!("\n[2/4] Initializing collector...");
+    say!("\n[2/4] Initializing collector...");
     log::info!("Initializing collector");
 
-    let mut collector =
-        ArtifactCollector::new(&args.source, &args.destination, patterns.clone()).await?;
+    let volume_size = args.volume_size.as_deref().map(parse_size).transpose()?;
+    let archive_options = ArchiveOptions::default()
+        .with_format(args.format.unwrap_or_default())
+        .with_password(args.pass.clone())
+        .with_volume_size(volume_size)
+        .with_recipients(args.recipients.clone());
+
+    if archive_options.is_encrypted() {
+        if !args.zip && !args.direct {
+            return Err(CollectorError::Config(
+                "--recipient encrypts an archive: add --direct (or --zip)".into(),
+            ));
+        }
+        if !args.direct {
+            let warning = "--zip stages a plaintext copy, deleted but not wiped once archived; \
+                           use --direct to keep plaintext off the disk";
+            say!("      Warning: {}", warning);
+            log::warn!("{}", warning);
+            summary.warnings.push(warning.to_string());
+        }
+    }
+
+    let signing_key = args
+        .signing_key
+        .as_deref()
+        .map(parse_signing_key)
+        .transpose()?;
+    if signing_key.is_some() {
+        say!("      Manifest seal will be signed");
+        log::info!("Manifest seal will be signed");
+    }
+
+    let previous = match args.previous {
+        Some(ref path) => {
+            let previous = PreviousCollection::load(path).await?;
+            say!(
+                "      Incremental: {} files known from {}",
+                previous.len(),
+                path
+            );
+            log::info!("Incremental from {} ({} files)", path, previous.len());
+            Some(previous)
+        }
+        None => None,
+    };
+
+    let known = if args.known_hashes.is_empty() {
+        None
+    } else {
+        let known = KnownFiles::load(&args.known_hashes)?
+            .with_action(args.known_action.unwrap_or_default());
+        say!(
+            "      Known-good hash sets: {} ({})",
+            known.sets().len(),
+            known.action()
+        );
+        Some(known)
+    };
+
+    let iocs = if args.iocs.is_empty() {
+        None
+    } else {
+        let iocs = IocSet::load(&args.iocs)?;
+        say!("      Indicators of compromise: {}", iocs.len());
+        Some(iocs)
+    };
+
+    let rules = if args.rules.is_empty() {
+        None
+    } else {
+        let size_filter = args
+            .rules_only_above
+            .as_deref()
+            .map(parse_size)
+            .transpose()?;
+        let rules = RuleSet::load(&args.rules)?.with_size_filter(size_filter);
+        say!("      Scan rules: {}", rules.len());
+        if let Some(size) = size_filter {
+            say!(
+                "      Files over {} collected only on a rule match",
+                format_bytes(size)
+            );
+        }
+        Some(rules)
+    };
+
+    let inventory = if args.inventory {
+        let inventory = InventoryOptions::new()
+            .with_roots(args.inventory_roots.clone())
+            .with_hash(args.inventory_hash);
+        if inventory.roots.is_empty() {
+            say!("      Inventory of the resource patterns (no content copied)");
+        } else {
+            say!(
+                "      Inventory of {} (no content copied)",
+                args.inventory_roots.join(", ")
+            );
+        }
+        Some(inventory)
+    } else {
+        None
+    };
+
+    let max_bytes = args
+        .max_bytes_per_sec
+        .as_deref()
+        .map(parse_size)
+        .transpose()?;
+    let throttle = ThrottleOptions::new()
+        .with_bytes_per_sec(max_bytes)
+        .with_files_per_sec(args.max_files_per_sec)
+        .with_low_io_priority(args.low_io_priority)
+        .with_nice(args.nice);
+    if let Some(rate) = throttle.bytes_per_sec {
+        say!("      Reads limited to {}/s", format_bytes(rate));
+    }
+    if let Some(rate) = throttle.files_per_sec {
+        say!("      Files limited to {}/s", rate);
+    }
+    if throttle.low_io_priority {
+        say!("      Low I/O priority");
+    }
+    if let Some(nice) = throttle.nice {
+        say!("      CPU niceness: {}", nice);
+    }
+
+    if args.resume && args.direct {
+        return Err(CollectorError::Config(
+            "--resume needs a staging folder and cannot be used with direct archiving".into(),
+        ));
+    }
+
+    let collector = if args.direct {
+        ArtifactCollector::new_archive(
+            &args.source,
+            &args.destination,
+            patterns.clone(),
+            archive_options.clone(),
+        )
+        .await?
+    } else if args.resume {
+        ArtifactCollector::resume(&args.source, &args.destination, patterns.clone()).await?
+    } else {
+        ArtifactCollector::new(&args.source, &args.destination, patterns.clone()).await?
+    };
+    let mut collector = collector
+        .with_signing_key(signing_key)
+        .with_case_metadata(case_metadata)
+        .with_previous_collection(previous)
+        .with_known_files(known)
+        .with_ioc_set(iocs)
+        .with_rules(rules)
+        .with_timeline(args.timeline)
+        .with_dfxml(args.dfxml)
+        .with_case_uco(args.case_uco)
+        .with_manifest_formats(args.manifest_formats.clone())
+        .with_inventory(inventory)
+        .with_throttle(throttle)
+        .with_cancel_token(cancel_on_ctrl_c())
+        .with_event_handler(move |event: &CollectionEvent| print_event(event, verbose));
+    if let Some(ref path) = args.events_json {
+        let file = File::create(path).map_err(|e| CollectorError::FileWrite {
+            path: path.into(),
+            source: e,
+        })?;
+        collector = collector.with_event_handler(JsonEventLog::new(file));
+    }
 
     let total_files = collector.count_files();
-    println!("      Found {} files to collect", total_files);
+    say!("      Found {} files to collect", total_files);
     log::info!("Found {} files to collect", total_files);
 
     // Collect
-    println!("\n[3/4] Collecting artifacts...");
+    say!("\n[3/4] Collecting artifacts...");
     log::info!("Starting collection");
 
     let timer = Instant::now();
     let stats = collector.collect().await?;
     let elapsed = timer.elapsed();
+    summary.stats = stats.clone();
+    summary.cancelled = collector.is_cancelled();
+    if collector.is_cancelled() {
+        say!("      Cancelled: the files done so far are sealed as a partial collection");
+        summary
+            .warnings
+            .push("Collection cancelled before its last file".to_string());
+    }
 
-    println!(
-        "      Collected {} files ({})",
-        stats.files_collected,
-        format_bytes(stats.bytes_collected)
+    if args.inventory {
+        say!(
+            "      Inventoried {} files ({})",
+            stats.files_inventoried,
+            format_bytes(stats.bytes_inventoried)
+        );
+        if args.inventory_hash {
+            say!("      Hashed {}", format_bytes(stats.bytes_hashed));
+        }
+    } else {
+        say!(
+            "      Collected {} files ({})",
+            stats.files_collected,
+            format_bytes(stats.bytes_collected)
+        );
+    }
+    let (files_per_second, bytes_per_second) = stats.throughput();
+    say!(
+        "      Throughput: {:.0} files/s, {}/s",
+        files_per_second,
+        format_bytes(bytes_per_second as u64)
     );
     if verbose {
-        println!(
+        say!(
             "      Filesystem extractions: {}",
             stats.filesystem_extractions
         );
-        println!("      NTFS extractions: {}", stats.ntfs_extractions);
-        println!("      Failed extractions: {}", stats.failed_extractions);
+        say!("      NTFS extractions: {}", stats.ntfs_extractions);
+        say!("      Failed extractions: {}", stats.failed_extractions);
     } else {
-        println!(
+        say!(
             "      Filesystem: {} | NTFS: {} | Failed: {}",
-            stats.filesystem_extractions, stats.ntfs_extractions, stats.failed_extractions
+            stats.filesystem_extractions,
+            stats.ntfs_extractions,
+            stats.failed_extractions
+        );
+    }
+    if stats.files_resumed > 0 {
+        say!("      Kept from interrupted run: {}", stats.files_resumed);
+    }
+    if stats.files_known > 0 {
+        say!("      Known-good files: {}", stats.files_known);
+    }
+    if stats.files_filtered > 0 {
+        say!("      Left out (no rule match): {}", stats.files_filtered);
+    }
+    if stats.files_unchanged > 0 {
+        say!(
+            "      Unchanged since previous collection: {}",
+            stats.files_unchanged
+        );
+    }
+    if !collector.failures().is_empty() {
+        say!(
+            "      Not collected ({} files, see Collector_failures.json):",
+            collector.failures().len()
         );
+        for summary in summarize(collector.failures()) {
+            say!("        {} x {}", summary.count, summary.reason);
+        }
     }
     log::info!("Collection complete: {} files", stats.files_collected);
 
     // VSS collection (Windows only)
     #[cfg(target_os = "windows")]
-    if args.vss {
-        println!("\n[3b/4] Collecting from VSS snapshots...");
+    if args.vss && !collector.is_cancelled() {
+        say!("\n[3b/4] Collecting from VSS snapshots...");
         log::info!("Starting VSS collection");
 
         let mut vss_collector = VssCollector::new(&args.source, &args.destination, patterns);
+        let vss_result = if args.direct {
+            vss_collector.collect_into(&mut collector).await
+        } else {
+            vss_collector.collect_from_snapshots().await
+        };
 
-        match vss_collector.collect_from_snapshots().await {
+        match vss_result {
             Ok(vss_stats) => {
-                println!(
+                say!(
                     "      VSS: {} files collected ({})",
                     vss_stats.files_collected,
                     format_bytes(vss_stats.bytes_collected)
                 );
                 if verbose {
-                    println!("      VSS Filesystem: {}", vss_stats.filesystem_extractions);
-                    println!("      VSS NTFS: {}", vss_stats.ntfs_extractions);
-                    println!("      VSS Failed: {}", vss_stats.failed_extractions);
+                    say!("      VSS Filesystem: {}", vss_stats.filesystem_extractions);
+                    say!("      VSS NTFS: {}", vss_stats.ntfs_extractions);
+                    say!("      VSS Failed: {}", vss_stats.failed_extractions);
                 }
                 log::info!(
                     "VSS collection complete: {} files",
@@ -231,36 +612,164 @@ async fn run_collection(args: ArgsCollector) -> Result<()> {
                 );
             }
             Err(e) => {
-                println!("      VSS collection failed: {}", e);
+                say!("      VSS collection failed: {}", e);
                 log::error!("VSS collection failed: {}", e);
+                summary
+                    .warnings
+                    .push(format!("VSS collection failed: {}", e));
             }
         }
     }
 
-    // ZIP
-    if args.zip {
-        println!("\n[4/4] Creating ZIP archive...");
-        log::info!("Creating ZIP archive");
+    if !collector.ioc_findings().is_empty() {
+        say!(
+            "\n  !! {} files match indicators of compromise:",
+            collector.ioc_findings().len()
+        );
+        for item in collector.ioc_findings() {
+            say!("     {} ({})", item.source_file, item.ioc);
+        }
+        log::warn!(
+            "{} files match indicators of compromise",
+            collector.ioc_findings().len()
+        );
+        summary.warnings.push(format!(
+            "{} files match indicators of compromise",
+            collector.ioc_findings().len()
+        ));
+    }
+
+    if !collector.scan_findings().is_empty() {
+        say!(
+            "\n  !! {} files match scan rules:",
+            collector.scan_findings().len()
+        );
+        for finding in collector.scan_findings() {
+            let rules: Vec<&str> = finding.matches.iter().map(|m| m.rule.as_str()).collect();
+            say!("     {} ({})", finding.source_file, rules.join(", "));
+        }
+        log::warn!("{} files match scan rules", collector.scan_findings().len());
+        summary.warnings.push(format!(
+            "{} files match scan rules",
+            collector.scan_findings().len()
+        ));
+    }
+
+    summary.failures = FailureReport::new(collector.failures());
+
+    // Archive
+    let outputs = if args.direct {
+        say!("\n[4/4] Finalizing {} archive...", archive_options.format);
+        log::info!("Finalizing {} archive", archive_options.format);
 
-        collector.create_archive(args.pass).await?;
-        println!("      Archive created successfully");
+        let outputs = collector.finish().await?;
+        for output in &outputs {
+            say!("      Archive created: {}", output.display());
+        }
         log::info!("Archive created");
+        report_encryption(&archive_options);
+        outputs
+    } else if args.zip {
+        say!("\n[4/4] Creating {} archive...", archive_options.format);
+        log::info!("Creating {} archive", archive_options.format);
+
+        let outputs = collector.create_archive(&archive_options).await?;
+        say!("      Archive created successfully");
+        log::info!("Archive created");
+        report_encryption(&archive_options);
+        outputs
     } else {
-        println!("\n[4/4] Skipping ZIP (not requested)");
+        say!("\n[4/4] Skipping archive (not requested)");
+        collector.finish().await?
+    };
+    if args.summary_json.is_some() {
+        summary.outputs = outputs.iter().map(OutputFile::new).collect::<Result<_>>()?;
     }
 
     // Summary
     print_separator();
-    println!("\n  Collection completed in {:.2}s", elapsed.as_secs_f64());
-    println!("  Total files: {}", stats.files_collected);
-    println!("  Total size:  {}", format_bytes(stats.bytes_collected));
-    println!();
+    say!("\n  Collection completed in {:.2}s", elapsed.as_secs_f64());
+    if args.inventory {
+        say!("  Files listed: {}", stats.files_inventoried);
+        say!("  Total size:   {}", format_bytes(stats.bytes_inventoried));
+    } else {
+        say!("  Total files: {}", stats.files_collected);
+        say!("  Total size:  {}", format_bytes(stats.bytes_collected));
+    }
+    say!();
 
     log::info!("Execution took {:.2}s", elapsed.as_secs_f64());
 
     Ok(())
 }
 
+/// Note the recipients of an archive once it is written
+fn report_encryption(options: &ArchiveOptions) {
+    if options.is_encrypted() {
+        say!(
+            "      Archive encrypted to {} recipient(s)",
+            options.recipients.len()
+        );
+        log::info!(
+            "Archive encrypted to {} recipient(s)",
+            options.recipients.len()
+        );
+    }
+}
+
+/// Token cancelled by the first Ctrl-C. A second Ctrl-C exits at once.
+fn cancel_on_ctrl_c() -> CancelToken {
+    let token = CancelToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\n  Cancelling, sealing the files done so far (Ctrl-C again to abort)...");
+        log::warn!("Collection cancelled by the user");
+        cancel.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::error!("Collection aborted by the user");
+            std::process::exit(EXIT_CANCELLED);
+        }
+    });
+    token
+}
+
+/// Progress output of a collection event
+fn print_event(event: &CollectionEvent, verbose: bool) {
+    match event {
+        CollectionEvent::ArtifactStarted { artifact, files } if verbose => {
+            say!("      • {} ({} files)", artifact, files);
+        }
+        CollectionEvent::FileSkipped { path, reason } if verbose => {
+            say!("        skipped {} ({})", path, reason);
+        }
+        CollectionEvent::FileFailed { failure } => {
+            say!("      ! {}: {}", failure.source_file, failure.reason());
+        }
+        CollectionEvent::Cancelled { files_done, total } => {
+            say!("      Stopped after {} of {} files", files_done, total);
+        }
+        _ => {}
+    }
+}
+
+/// Write the run summary to a file, or to stdout for `-`.
+fn write_summary(target: &str, summary: &RunSummary) -> Result<()> {
+    let mut json = summary.to_json();
+    json.push(b'\n');
+    if target == "-" {
+        std::io::Write::write_all(&mut std::io::stdout(), &json)?;
+        return Ok(());
+    }
+    std::fs::write(target, &json).map_err(|e| CollectorError::FileWrite {
+        path: target.into(),
+        source: e,
+    })
+}
+
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -282,26 +791,58 @@ async fn main() {
     let mut args = ArgsCollector::parse();
 
     // Load config file if specified
-    if let Some(ref config_path) = args.config.clone() {
-        if let Err(e) = Config::parse_config_file(config_path.clone(), &mut args) {
-            eprintln!("Error loading config: {}", e);
-            std::process::exit(0);
-        }
+    if let Some(ref config_path) = args.config.clone()
+        && let Err(e) = Config::parse_config_file(config_path.clone(), &mut args)
+    {
+        eprintln!("Error loading config: {}", e);
+        std::process::exit(ErrorCategory::Config.exit_code());
     }
 
     // Handle subcommands
-    if let Some(ResourcesCommand::Resources(ref listing)) = args.command {
-        if let Err(e) = handle_resources_command(&args, &listing.command).await {
-            eprintln!("Error: {}", e);
-            std::process::exit(0);
+    match args.command {
+        Some(Command::Resources(ref listing)) => {
+            if let Err(e) = handle_resources_command(&args, &listing.command).await {
+                eprintln!("Error [{}]: {}", e.code(), e);
+                std::process::exit(e.exit_code());
+            }
+            return;
         }
-        return;
+        Some(Command::Verify(ref verify)) => match handle_verify_command(verify).await {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(EXIT_MISMATCH),
+            Err(e) => {
+                eprintln!("Error [{}]: {}", e.code(), e);
+                std::process::exit(e.exit_code());
+            }
+        },
+        Some(Command::Diff(ref diff)) => {
+            if let Err(e) = handle_diff_command(diff).await {
+                eprintln!("Error [{}]: {}", e.code(), e);
+                std::process::exit(e.exit_code());
+            }
+            return;
+        }
+        None => {}
     }
 
     // Run collection
-    if let Err(e) = run_collection(args).await {
-        eprintln!("\nError: {}", e);
-        log::error!("Collection failed: {}", e);
-        std::process::exit(0);
+    let summary_json = args.summary_json.clone();
+    SUMMARY_TO_STDOUT.store(summary_json.as_deref() == Some("-"), Ordering::Relaxed);
+    let mut summary = RunSummary::new(serde_json::to_value(&args).unwrap_or_default());
+
+    let result = run_collection(args, &mut summary).await;
+    if let Err(ref e) = result {
+        eprintln!("\nError [{}]: {}", e.code(), e);
+        log::error!("Collection failed ({} {}): {}", e.category(), e.code(), e);
+    }
+    let exit_code = summary.finish(result);
+
+    if let Some(ref target) = summary_json
+        && let Err(e) = write_summary(target, &summary)
+    {
+        eprintln!("Error [{}]: {}", e.code(), e);
+    }
+    if exit_code != EXIT_SUCCESS {
+        std::process::exit(exit_code);
     }
 }
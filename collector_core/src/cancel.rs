@@ -0,0 +1,68 @@
+//! Cooperative cancellation of a running collection.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Asks a collection to stop. Clones share the same flag, so one can be kept
+/// by the UI while the collector holds another.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Writer failing once its token is cancelled, so a copy stops mid-file.
+pub(crate) struct CancelWriter<W> {
+    inner: W,
+    token: CancelToken,
+}
+
+impl<W> CancelWriter<W> {
+    pub(crate) fn new(inner: W, token: CancelToken) -> Self {
+        Self { inner, token }
+    }
+}
+
+impl<W: Write> Write for CancelWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.token.is_cancelled() {
+            return Err(io::Error::other("collection cancelled"));
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_writer() {
+        let token = CancelToken::new();
+        let mut writer = CancelWriter::new(Vec::new(), token.clone());
+        writer.write_all(b"abc").unwrap();
+
+        token.cancel();
+        assert!(token.clone().is_cancelled());
+        assert!(writer.write_all(b"def").is_err());
+        assert_eq!(writer.inner, b"abc");
+    }
+}
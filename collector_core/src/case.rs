@@ -0,0 +1,227 @@
+//! Chain-of-custody metadata written as `Collector_case.json` in every
+//! collection: who collected, with what, from which host and when.
+
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Networks, System};
+
+use crate::platform::CollectionStats;
+
+/// Case details supplied by the examiner.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseInfo {
+    pub case_number: Option<String>,
+    pub examiner: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl CaseInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_case_number(mut self, case_number: Option<String>) -> Self {
+        self.case_number = case_number.filter(|s| !s.trim().is_empty());
+        self
+    }
+
+    pub fn with_examiner(mut self, examiner: Option<String>) -> Self {
+        self.examiner = examiner.filter(|s| !s.trim().is_empty());
+        self
+    }
+
+    pub fn with_notes(mut self, notes: Option<String>) -> Self {
+        self.notes = notes.filter(|s| !s.trim().is_empty());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub version: String,
+    pub command_line: Vec<String>,
+}
+
+/// Host identifiers reported by `sysinfo`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub distribution_id: String,
+    pub cpu_arch: String,
+    pub boot_time: u64,
+    pub mac_addresses: Vec<String>,
+}
+
+impl HostInfo {
+    pub fn current() -> Self {
+        let networks = Networks::new_with_refreshed_list();
+        let mut mac_addresses: Vec<String> = networks
+            .list()
+            .values()
+            .map(|data| data.mac_address())
+            .filter(|mac| !mac.is_unspecified())
+            .map(|mac| mac.to_string())
+            .collect();
+        mac_addresses.sort();
+        mac_addresses.dedup();
+
+        Self {
+            hostname: System::host_name(),
+            os_name: System::name(),
+            os_version: System::long_os_version(),
+            kernel_version: System::kernel_version(),
+            distribution_id: System::distribution_id(),
+            cpu_arch: System::cpu_arch(),
+            boot_time: System::boot_time(),
+            mac_addresses,
+        }
+    }
+}
+
+/// Host clock at the start of the collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockInfo {
+    pub local_time: String,
+    pub utc_time: String,
+    pub utc_offset: String,
+    pub timezone: Option<String>,
+}
+
+impl ClockInfo {
+    pub fn current() -> Self {
+        let local = Local::now();
+
+        Self {
+            local_time: local.to_rfc3339(),
+            utc_time: local.with_timezone(&Utc).to_rfc3339(),
+            utc_offset: local.format("%:z").to_string(),
+            timezone: timezone_name(),
+        }
+    }
+}
+
+/// Case metadata document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseMetadata {
+    pub case: CaseInfo,
+    pub tool: ToolInfo,
+    pub host: HostInfo,
+    pub clock: ClockInfo,
+    /// Artifacts collected, with groups such as `All` expanded
+    pub resources: Vec<String>,
+    pub resource_set_sha256: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub stats: Option<CollectionStats>,
+    /// The collection was cancelled before its last file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+impl CaseMetadata {
+    /// Capture tool, host and clock details now.
+    pub fn new(case: CaseInfo) -> Self {
+        let clock = ClockInfo::current();
+
+        Self {
+            case,
+            tool: ToolInfo {
+                name: "collector".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                command_line: std::env::args().collect(),
+            },
+            host: HostInfo::current(),
+            started_at: clock.utc_time.clone(),
+            clock,
+            resources: Vec::new(),
+            resource_set_sha256: None,
+            finished_at: None,
+            stats: None,
+            cancelled: false,
+        }
+    }
+
+    /// Record the selected resources and the hash of the resource set.
+    pub fn with_resources(
+        mut self,
+        resources: Vec<String>,
+        resource_set_sha256: Option<String>,
+    ) -> Self {
+        self.resources = resources;
+        self.resource_set_sha256 = resource_set_sha256;
+        self
+    }
+
+    /// Mark the collection finished with its final statistics.
+    pub fn finish(&mut self, stats: &CollectionStats) {
+        self.finished_at = Some(Utc::now().to_rfc3339());
+        self.stats = Some(stats.clone());
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+impl Default for CaseMetadata {
+    fn default() -> Self {
+        Self::new(CaseInfo::default())
+    }
+}
+
+/// IANA timezone name from `TZ` or, on unix, the `/etc/localtime` link.
+fn timezone_name() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ")
+        && !tz.is_empty()
+    {
+        return Some(tz.trim_start_matches(':').to_string());
+    }
+
+    #[cfg(unix)]
+    if let Ok(target) = std::fs::read_link("/etc/localtime") {
+        let target = target.to_string_lossy().to_string();
+        if let Some((_, name)) = target.split_once("zoneinfo/") {
+            return Some(name.to_string());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_info_drops_empty_fields() {
+        let case = CaseInfo::new()
+            .with_case_number(Some("2026-042".into()))
+            .with_examiner(Some("  ".into()));
+
+        assert_eq!(case.case_number.as_deref(), Some("2026-042"));
+        assert_eq!(case.examiner, None);
+    }
+
+    #[test]
+    fn test_case_metadata_json() {
+        let mut metadata = CaseMetadata::new(CaseInfo::new().with_examiner(Some("J. Doe".into())))
+            .with_resources(vec!["MFT".into()], Some("ab".into()));
+        assert!(metadata.finished_at.is_none());
+
+        metadata.finish(&CollectionStats {
+            files_collected: 3,
+            ..Default::default()
+        });
+
+        let value: serde_json::Value = serde_json::from_slice(&metadata.to_json()).unwrap();
+        assert_eq!(value["case"]["examiner"], "J. Doe");
+        assert_eq!(value["tool"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["stats"]["files_collected"], 3);
+        assert_eq!(value["resources"][0], "MFT");
+        assert!(value["clock"]["utc_offset"].as_str().unwrap().len() == 6);
+    }
+}
@@ -1,12 +1,19 @@
+pub mod seal;
+
 use chrono::Utc;
-use csv_async::{AsyncReader, AsyncSerializer, AsyncWriterBuilder};
-use serde::Serialize;
+use csv_async::{
+    AsyncReader, AsyncReaderBuilder, AsyncSerializer, AsyncWriterBuilder, StringRecord,
+};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs::{File, OpenOptions};
 
 use crate::error::{CollectorError, Result};
+use crate::failure::FAILED_PREFIX;
+use seal::{CHAIN_GENESIS, chain_next};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct CsvLogItem {
     pub collect_time: String,
     pub source_file: String,
@@ -16,6 +23,18 @@ pub struct CsvLogItem {
     pub modified_time: String,
     pub access_time: String,
     pub file_size: u64,
+    /// Inode of the source file, empty where the platform has none.
+    pub inode: String,
+    /// Why the row differs from a plain copy, e.g. `unchanged since <run>`.
+    /// Empty for collected files.
+    pub status: String,
+    /// Ids of the indicators of compromise the file matched, `;` separated.
+    pub ioc: String,
+    /// Names of the scan rules the file matched, `;` separated.
+    pub rules: String,
+    /// Hash chaining this row to the previous one, set by [`CsvLogFile`].
+    /// Must stay the last column.
+    pub chain_hash: String,
 }
 
 impl Default for CsvLogItem {
@@ -30,6 +49,11 @@ impl Default for CsvLogItem {
             modified_time: now.clone(),
             access_time: now,
             file_size: 0,
+            inode: String::new(),
+            status: String::new(),
+            ioc: String::new(),
+            rules: String::new(),
+            chain_hash: String::new(),
         }
     }
 }
@@ -67,11 +91,102 @@ impl CsvLogItem {
         self.file_size = size;
         self
     }
+
+    pub fn with_inode(mut self, inode: Option<u64>) -> Self {
+        self.inode = inode.map(|i| i.to_string()).unwrap_or_default();
+        self
+    }
+
+    pub fn with_status<S: Into<String>>(mut self, status: S) -> Self {
+        self.status = status.into();
+        self
+    }
+
+    pub fn with_ioc<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.ioc = ids
+            .into_iter()
+            .map(|id| id.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .join(";");
+        self
+    }
+
+    /// Ids of the indicators the file matched.
+    pub fn ioc_ids(&self) -> impl Iterator<Item = &str> {
+        self.ioc.split(';').filter(|id| !id.is_empty())
+    }
+
+    pub fn with_rules<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.rules = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .join(";");
+        self
+    }
+
+    /// Names of the scan rules the file matched.
+    pub fn rule_names(&self) -> impl Iterator<Item = &str> {
+        self.rules.split(';').filter(|name| !name.is_empty())
+    }
+
+    /// Whether the file was copied into this collection. Skipped files are
+    /// listed without a destination, failed ones with the partial entry an
+    /// archive could not take back, if any.
+    pub fn is_copied(&self) -> bool {
+        !self.destination_file.is_empty() && !self.status.starts_with(FAILED_PREFIX)
+    }
+
+    /// Columns covered by the chain hash, as written to the CSV.
+    pub fn chain_fields(&self) -> Vec<String> {
+        vec![
+            self.collect_time.clone(),
+            self.source_file.clone(),
+            self.destination_file.clone(),
+            self.hash_sha1.clone(),
+            self.from_ntfs.to_string(),
+            self.modified_time.clone(),
+            self.access_time.clone(),
+            self.file_size.to_string(),
+            self.inode.clone(),
+            self.status.clone(),
+            self.ioc.clone(),
+            self.rules.clone(),
+        ]
+    }
+}
+
+/// Parse every row of a manifest. Columns missing from older manifests take
+/// their default value.
+pub async fn read_manifest_items(manifest: &[u8]) -> Result<Vec<CsvLogItem>> {
+    let read_error =
+        |e: csv_async::Error| CollectorError::CsvError(format!("Failed to read row: {}", e));
+
+    let mut reader = AsyncReaderBuilder::new()
+        .has_headers(true)
+        .create_reader(manifest);
+    let headers = reader.headers().await.map_err(read_error)?.clone();
+
+    let mut items = Vec::new();
+    let mut record = StringRecord::new();
+    while reader.read_record(&mut record).await.map_err(read_error)? {
+        items.push(record.deserialize(Some(&headers)).map_err(read_error)?);
+    }
+    Ok(items)
 }
 
 pub struct CsvLogFile {
     csv_writer: AsyncSerializer<File>,
     file_path: String,
+    last_chain: String,
 }
 
 impl CsvLogFile {
@@ -87,6 +202,11 @@ impl CsvLogFile {
             .map_err(|e| CollectorError::CsvError(format!("Failed to open CSV: {}", e)))?;
 
         let has_headers = Self::check_has_headers(&path_str).await;
+        let last_chain = if has_headers {
+            Self::read_last_chain(&path_str).await?
+        } else {
+            CHAIN_GENESIS.to_string()
+        };
 
         let csv_writer = if has_headers {
             let mut builder = AsyncWriterBuilder::new();
@@ -99,10 +219,14 @@ impl CsvLogFile {
         Ok(Self {
             csv_writer,
             file_path: path_str,
+            last_chain,
         })
     }
 
-    pub async fn add_row(&mut self, item: CsvLogItem) -> Result<()> {
+    pub async fn add_row(&mut self, mut item: CsvLogItem) -> Result<()> {
+        item.chain_hash = chain_next(&self.last_chain, &item.chain_fields());
+        self.last_chain = item.chain_hash.clone();
+
         self.csv_writer
             .serialize(item)
             .await
@@ -110,10 +234,30 @@ impl CsvLogFile {
         Ok(())
     }
 
+    pub async fn flush(&mut self) -> Result<()> {
+        self.csv_writer
+            .flush()
+            .await
+            .map_err(|e| CollectorError::CsvError(format!("Failed to flush CSV: {}", e)))
+    }
+
     pub fn file_path(&self) -> &str {
         &self.file_path
     }
 
+    /// Chain hash of the last row written.
+    pub fn chain_head(&self) -> &str {
+        &self.last_chain
+    }
+
+    async fn read_last_chain(path: &str) -> Result<String> {
+        let content = tokio::fs::read(path)
+            .await
+            .map_err(|e| CollectorError::CsvError(format!("Failed to read CSV: {}", e)))?;
+        let walk = seal::walk_chain(&content).await?;
+        Ok(walk.stored_head)
+    }
+
     async fn check_has_headers(path: &str) -> bool {
         let path = Path::new(path);
         if !path.exists() {
@@ -182,4 +326,45 @@ mod tests {
         let result = logger.add_row(item).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_read_manifest_items() {
+        let manifest = "source_file,hash_sha1,file_size\n/etc/hosts,abc,12\n";
+        let items = read_manifest_items(manifest.as_bytes()).await.unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].source_file, "/etc/hosts");
+        assert_eq!(items[0].file_size, 12);
+        assert!(items[0].chain_hash.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_csv_log_file_chain_survives_reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let csv_path = temp_dir.path().join("chain.csv");
+
+        let mut logger = CsvLogFile::new(&csv_path).await.unwrap();
+        logger
+            .add_row(CsvLogItem::with_paths("a", "a"))
+            .await
+            .unwrap();
+        logger.flush().await.unwrap();
+        let head = logger.chain_head().to_string();
+        drop(logger);
+
+        let mut logger = CsvLogFile::new(&csv_path).await.unwrap();
+        assert_eq!(logger.chain_head(), head);
+        logger
+            .add_row(CsvLogItem::with_paths("b", "b"))
+            .await
+            .unwrap();
+        logger.flush().await.unwrap();
+
+        let walk = seal::walk_chain(&std::fs::read(&csv_path).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(walk.rows, 2);
+        assert_eq!(walk.broken_row, None);
+        assert_eq!(walk.head, logger.chain_head());
+    }
 }
@@ -0,0 +1,477 @@
+//! Tamper evidence for the collection manifest.
+//!
+//! Every manifest row carries `chain_hash = SHA256(previous chain_hash || "\n"
+//! || row)`, where `row` is the row's other columns joined with `\x1f` and the
+//! first row chains from [`CHAIN_GENESIS`]. When the collection ends a
+//! [`ManifestSeal`] records the row count, the last chain hash, the SHA256 of
+//! the whole manifest file and the collection metadata, optionally signed with
+//! Ed25519. Editing, inserting, removing or reordering rows breaks the chain;
+//! rewriting the whole chain breaks the signature.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use csv_async::{AsyncReaderBuilder, StringRecord};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{CollectorError, Result};
+
+/// Chain value preceding the first manifest row.
+pub const CHAIN_GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+pub const SEAL_VERSION: u32 = 1;
+
+const FIELD_SEPARATOR: &str = "\x1f";
+const SIGNATURE_ALGORITHM: &str = "ed25519";
+
+/// Next chain value for a row whose columns (without `chain_hash`) are `fields`.
+pub fn chain_next<S: AsRef<str>>(previous: &str, fields: &[S]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(previous.as_bytes());
+    hasher.update(b"\n");
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            hasher.update(FIELD_SEPARATOR.as_bytes());
+        }
+        hasher.update(field.as_ref().as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Result of walking the chain of a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainWalk {
+    pub rows: u64,
+    /// Chain value of the last row, as recomputed.
+    pub head: String,
+    /// Chain value stored in the last row.
+    pub stored_head: String,
+    /// 1-based number of the first row whose stored chain value is wrong.
+    pub broken_row: Option<u64>,
+}
+
+/// Recompute the chain of a manifest. The `chain_hash` column must be last.
+pub async fn walk_chain(manifest: &[u8]) -> Result<ChainWalk> {
+    let mut reader = AsyncReaderBuilder::new()
+        .has_headers(true)
+        .create_reader(manifest);
+
+    let headers = reader
+        .headers()
+        .await
+        .map_err(|e| CollectorError::CsvError(format!("Failed to read headers: {}", e)))?
+        .clone();
+    let mut walk = ChainWalk {
+        rows: 0,
+        head: CHAIN_GENESIS.to_string(),
+        stored_head: CHAIN_GENESIS.to_string(),
+        broken_row: None,
+    };
+    // Nothing collected, e.g. cancelled before the first file
+    if headers.is_empty() {
+        return Ok(walk);
+    }
+    if headers.iter().next_back() != Some("chain_hash") {
+        return Err(CollectorError::ManifestVerification(
+            "manifest has no chain_hash column".into(),
+        ));
+    }
+
+    let mut record = StringRecord::new();
+    loop {
+        let more = reader
+            .read_record(&mut record)
+            .await
+            .map_err(|e| CollectorError::CsvError(format!("Failed to read row: {}", e)))?;
+        if !more {
+            break;
+        }
+
+        let fields: Vec<&str> = record.iter().collect();
+        let Some((stored, columns)) = fields.split_last() else {
+            continue;
+        };
+
+        walk.rows += 1;
+        walk.head = chain_next(&walk.head, columns);
+        walk.stored_head = stored.to_string();
+
+        if walk.broken_row.is_none() && walk.head != *stored {
+            walk.broken_row = Some(walk.rows);
+        }
+    }
+
+    Ok(walk)
+}
+
+/// Collection metadata covered by the seal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealMetadata {
+    pub hostname: String,
+    pub tool_version: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub files_collected: u64,
+    pub bytes_collected: u64,
+    pub failed_extractions: u64,
+    /// The collection was cancelled and lists only the files done.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    /// SHA256 of the case metadata document written with the manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_metadata_sha256: Option<String>,
+    /// SHA256 of the other reports written with the manifest, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub report_sha256: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealSignature {
+    pub algorithm: String,
+    /// Hex encoded Ed25519 public key.
+    pub public_key: String,
+    /// Hex encoded signature over the seal serialized without `signature`.
+    pub value: String,
+}
+
+/// Final digest of a manifest, written next to it as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestSeal {
+    pub version: u32,
+    pub manifest: String,
+    pub rows: u64,
+    pub chain_head: String,
+    pub manifest_sha256: String,
+    pub metadata: SealMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SealSignature>,
+}
+
+impl ManifestSeal {
+    /// Seal the manifest content `manifest` stored as `manifest_name`.
+    pub async fn new<S: Into<String>>(
+        manifest_name: S,
+        manifest: &[u8],
+        metadata: SealMetadata,
+    ) -> Result<Self> {
+        let walk = walk_chain(manifest).await?;
+
+        Ok(Self {
+            version: SEAL_VERSION,
+            manifest: manifest_name.into(),
+            rows: walk.rows,
+            chain_head: walk.head,
+            manifest_sha256: hex::encode(Sha256::digest(manifest)),
+            metadata,
+            signature: None,
+        })
+    }
+
+    /// Bytes covered by the signature.
+    fn signed_payload(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned)
+            .map_err(|e| CollectorError::ManifestVerification(e.to_string()))
+    }
+
+    pub fn sign(mut self, key: &SigningKey) -> Result<Self> {
+        let signature = key.sign(&self.signed_payload()?);
+        self.signature = Some(SealSignature {
+            algorithm: SIGNATURE_ALGORITHM.to_string(),
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+            value: hex::encode(signature.to_bytes()),
+        });
+        Ok(self)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+            .map_err(|e| CollectorError::ManifestVerification(e.to_string()))
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data)
+            .map_err(|e| CollectorError::ManifestVerification(e.to_string()))
+    }
+
+    fn check_signature(&self, trusted_key: Option<&VerifyingKey>) -> SignatureStatus {
+        let Some(ref signature) = self.signature else {
+            return if trusted_key.is_some() {
+                SignatureStatus::Missing
+            } else {
+                SignatureStatus::Unsigned
+            };
+        };
+
+        let Some(embedded_key) = hex::decode(&signature.public_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        else {
+            return SignatureStatus::Invalid;
+        };
+        let Some(value) = hex::decode(&signature.value)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        else {
+            return SignatureStatus::Invalid;
+        };
+        let Ok(payload) = self.signed_payload() else {
+            return SignatureStatus::Invalid;
+        };
+
+        if signature.algorithm != SIGNATURE_ALGORITHM
+            || embedded_key.verify_strict(&payload, &value).is_err()
+        {
+            return SignatureStatus::Invalid;
+        }
+
+        match trusted_key {
+            Some(trusted) if *trusted != embedded_key => SignatureStatus::UntrustedKey,
+            _ => SignatureStatus::Valid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// No signature and none required.
+    Unsigned,
+    /// A trusted key was given but the seal is not signed.
+    Missing,
+    Valid,
+    Invalid,
+    /// Valid signature from a key other than the trusted one.
+    UntrustedKey,
+}
+
+/// Outcome of checking a manifest against its seal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestVerification {
+    pub rows: u64,
+    pub broken_row: Option<u64>,
+    pub rows_match: bool,
+    pub chain_matches: bool,
+    pub digest_matches: bool,
+    pub signature: SignatureStatus,
+    /// The seal marks a cancelled, partial collection
+    pub cancelled: bool,
+}
+
+impl ManifestVerification {
+    pub fn is_valid(&self) -> bool {
+        self.broken_row.is_none()
+            && self.rows_match
+            && self.chain_matches
+            && self.digest_matches
+            && matches!(
+                self.signature,
+                SignatureStatus::Valid | SignatureStatus::Unsigned
+            )
+    }
+}
+
+/// Check a manifest against its seal, and the seal signature against
+/// `trusted_key` when given.
+pub async fn verify_manifest(
+    manifest: &[u8],
+    seal: &ManifestSeal,
+    trusted_key: Option<&VerifyingKey>,
+) -> Result<ManifestVerification> {
+    let walk = walk_chain(manifest).await?;
+
+    Ok(ManifestVerification {
+        rows: walk.rows,
+        broken_row: walk.broken_row,
+        rows_match: walk.rows == seal.rows,
+        chain_matches: walk.head == seal.chain_head && walk.stored_head == seal.chain_head,
+        digest_matches: hex::encode(Sha256::digest(manifest)) == seal.manifest_sha256,
+        signature: seal.check_signature(trusted_key),
+        cancelled: seal.metadata.cancelled,
+    })
+}
+
+/// [`verify_manifest`] on files.
+pub async fn verify_manifest_files<M, S>(
+    manifest_path: M,
+    seal_path: S,
+    trusted_key: Option<&VerifyingKey>,
+) -> Result<ManifestVerification>
+where
+    M: AsRef<Path>,
+    S: AsRef<Path>,
+{
+    let read = |path: &Path| {
+        let path = path.to_path_buf();
+        async move {
+            tokio::fs::read(&path)
+                .await
+                .map_err(|e| CollectorError::FileRead { path, source: e })
+        }
+    };
+
+    let manifest = read(manifest_path.as_ref()).await?;
+    let seal = ManifestSeal::from_json(&read(seal_path.as_ref()).await?)?;
+    verify_manifest(&manifest, &seal, trusted_key).await
+}
+
+/// Parse an Ed25519 signing key: 64 hex characters (the 32-byte seed), or a
+/// file containing them.
+pub fn parse_signing_key(value: &str) -> Result<SigningKey> {
+    let bytes = key_bytes(value)?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Parse an Ed25519 public key: 64 hex characters, or a file containing them.
+pub fn parse_verifying_key(value: &str) -> Result<VerifyingKey> {
+    let bytes = key_bytes(value)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| CollectorError::InvalidKey(e.to_string()))
+}
+
+fn key_bytes(value: &str) -> Result<[u8; 32]> {
+    let value = value.trim();
+    let text = match std::fs::read_to_string(value) {
+        Ok(content) => content.trim().to_string(),
+        Err(_) => value.to_string(),
+    };
+
+    hex::decode(&text)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| CollectorError::InvalidKey("expected 32 bytes as 64 hex characters".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST_HEADER: &str = "source_file,hash_sha1,chain_hash\n";
+
+    fn manifest(rows: &[(&str, &str)]) -> String {
+        let mut chain = CHAIN_GENESIS.to_string();
+        let mut out = MANIFEST_HEADER.to_string();
+        for (source, hash) in rows {
+            chain = chain_next(&chain, &[source, hash]);
+            out.push_str(&format!("{},{},{}\n", source, hash, chain));
+        }
+        out
+    }
+
+    fn test_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    #[tokio::test]
+    async fn test_verify_signed_manifest() {
+        let data = manifest(&[("/etc/passwd", "aa"), ("/etc/shadow", "bb")]);
+        let key = test_key();
+        let seal = ManifestSeal::new("manifest.csv", data.as_bytes(), SealMetadata::default())
+            .await
+            .unwrap()
+            .sign(&key)
+            .unwrap();
+        assert_eq!(seal.rows, 2);
+
+        let report = verify_manifest(data.as_bytes(), &seal, Some(&key.verifying_key()))
+            .await
+            .unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.signature, SignatureStatus::Valid);
+    }
+
+    #[tokio::test]
+    async fn test_seal_empty_manifest() {
+        let seal = ManifestSeal::new("manifest.csv", b"", SealMetadata::default())
+            .await
+            .unwrap();
+        assert_eq!(seal.rows, 0);
+        assert_eq!(seal.chain_head, CHAIN_GENESIS);
+
+        let report = verify_manifest(b"", &seal, None).await.unwrap();
+        assert!(report.rows_match && report.chain_matches && report.digest_matches);
+
+        let added = manifest(&[("/etc/passwd", "aa")]);
+        let report = verify_manifest(added.as_bytes(), &seal, None)
+            .await
+            .unwrap();
+        assert!(!report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_detect_edited_row() {
+        let data = manifest(&[("/etc/passwd", "aa"), ("/etc/shadow", "bb")]);
+        let seal = ManifestSeal::new("manifest.csv", data.as_bytes(), SealMetadata::default())
+            .await
+            .unwrap();
+
+        let tampered = data.replace("/etc/shadow,bb", "/etc/shadow,cc");
+        let report = verify_manifest(tampered.as_bytes(), &seal, None)
+            .await
+            .unwrap();
+
+        assert!(!report.is_valid());
+        assert_eq!(report.broken_row, Some(2));
+        assert!(!report.digest_matches);
+    }
+
+    #[tokio::test]
+    async fn test_detect_rewritten_chain() {
+        let data = manifest(&[("/etc/passwd", "aa")]);
+        let key = test_key();
+        let seal = ManifestSeal::new("manifest.csv", data.as_bytes(), SealMetadata::default())
+            .await
+            .unwrap()
+            .sign(&key)
+            .unwrap();
+
+        // A consistent forged manifest with a forged seal fails the signature
+        let forged = manifest(&[("/etc/passwd", "ff")]);
+        let mut forged_seal =
+            ManifestSeal::new("manifest.csv", forged.as_bytes(), SealMetadata::default())
+                .await
+                .unwrap();
+        forged_seal.signature = seal.signature.clone();
+
+        let report = verify_manifest(forged.as_bytes(), &forged_seal, None)
+            .await
+            .unwrap();
+        assert_eq!(report.signature, SignatureStatus::Invalid);
+        assert!(!report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_and_missing_signature() {
+        let data = manifest(&[("/etc/hosts", "aa")]);
+        let seal = ManifestSeal::new("manifest.csv", data.as_bytes(), SealMetadata::default())
+            .await
+            .unwrap();
+        let trusted = SigningKey::from_bytes(&[9u8; 32]).verifying_key();
+
+        let report = verify_manifest(data.as_bytes(), &seal, Some(&trusted))
+            .await
+            .unwrap();
+        assert_eq!(report.signature, SignatureStatus::Missing);
+
+        let signed = seal.sign(&test_key()).unwrap();
+        let report = verify_manifest(data.as_bytes(), &signed, Some(&trusted))
+            .await
+            .unwrap();
+        assert_eq!(report.signature, SignatureStatus::UntrustedKey);
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let key = test_key();
+        let seed = hex::encode(key.to_bytes());
+        assert_eq!(parse_signing_key(&seed).unwrap().to_bytes(), key.to_bytes());
+
+        let public = hex::encode(key.verifying_key().to_bytes());
+        assert_eq!(parse_verifying_key(&public).unwrap(), key.verifying_key());
+        assert!(parse_signing_key("abcd").is_err());
+    }
+}
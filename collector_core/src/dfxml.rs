@@ -0,0 +1,217 @@
+//! Digital Forensics XML (DFXML 1.2) description of a collection: the
+//! creator and execution environment from the case metadata, and one
+//! `fileobject` per manifest row.
+
+use std::io::{self, Write};
+
+use chrono::DateTime;
+use filetime::FileTime;
+use serde::Serialize;
+
+use crate::case::CaseMetadata;
+use crate::timeline::TimelineEntry;
+
+const DFXML_NAMESPACE: &str = "http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML";
+
+/// A contiguous range of a file on the source volume, known when the file
+/// was read through the raw NTFS parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ByteRun {
+    pub file_offset: u64,
+    /// Offset on the volume, in bytes
+    pub img_offset: u64,
+    pub len: u64,
+}
+
+/// DFXML document of entries held in memory.
+pub fn document(case: &CaseMetadata, entries: &[TimelineEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_header(&mut out, case).expect("writing to a Vec cannot fail");
+    for entry in entries {
+        write_fileobject(&mut out, entry).expect("writing to a Vec cannot fail");
+    }
+    write_footer(&mut out).expect("writing to a Vec cannot fail");
+    out
+}
+
+/// Write the start of the document, up to the first `fileobject`.
+pub fn write_header<W: Write>(out: &mut W, case: &CaseMetadata) -> io::Result<()> {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<dfxml xmlns=\"{}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" version=\"1.2.0\">\n",
+        DFXML_NAMESPACE
+    ));
+    xml.push_str("  <metadata>\n    <dc:type>File collection</dc:type>\n  </metadata>\n");
+
+    xml.push_str("  <creator version=\"1.0\">\n");
+    element(&mut xml, 4, "program", &case.tool.name);
+    element(&mut xml, 4, "version", &case.tool.version);
+    xml.push_str("    <execution_environment>\n");
+    let host = &case.host;
+    for (name, value) in [
+        ("os_sysname", host.os_name.as_deref()),
+        ("os_release", host.kernel_version.as_deref()),
+        ("os_version", host.os_version.as_deref()),
+        ("host", host.hostname.as_deref()),
+        ("arch", Some(host.cpu_arch.as_str())),
+    ] {
+        if let Some(value) = value {
+            element(&mut xml, 6, name, value);
+        }
+    }
+    element(
+        &mut xml,
+        6,
+        "command_line",
+        &case.tool.command_line.join(" "),
+    );
+    element(&mut xml, 6, "start_time", &case.started_at);
+    if let Some(finished_at) = &case.finished_at {
+        element(&mut xml, 6, "end_time", finished_at);
+    }
+    xml.push_str("    </execution_environment>\n");
+    xml.push_str("  </creator>\n");
+    out.write_all(xml.as_bytes())
+}
+
+/// Write the end of the document, after the last `fileobject`.
+pub fn write_footer<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(b"</dfxml>\n")
+}
+
+/// Write the `fileobject` of an entry.
+pub fn write_fileobject<W: Write>(out: &mut W, entry: &TimelineEntry) -> io::Result<()> {
+    let mut xml = String::new();
+    fileobject(&mut xml, entry);
+    out.write_all(xml.as_bytes())
+}
+
+fn fileobject(xml: &mut String, entry: &TimelineEntry) {
+    xml.push_str("  <fileobject>\n");
+    element(xml, 4, "filename", &entry.source_file);
+    element(xml, 4, "filesize", &entry.size.to_string());
+    if !entry.inode.is_empty() {
+        element(xml, 4, "inode", &entry.inode);
+    }
+    if let Some(mode) = entry.mode {
+        element(xml, 4, "mode", &mode.to_string());
+    }
+    if let Some(uid) = entry.uid {
+        element(xml, 4, "uid", &uid.to_string());
+    }
+    if let Some(gid) = entry.gid {
+        element(xml, 4, "gid", &gid.to_string());
+    }
+    for (name, time) in [
+        ("mtime", entry.modified),
+        ("ctime", entry.changed),
+        ("atime", entry.accessed),
+        ("crtime", entry.created),
+    ] {
+        if let Some(time) = time.and_then(iso_time) {
+            element(xml, 4, name, &time);
+        }
+    }
+
+    if !entry.byte_runs.is_empty() {
+        xml.push_str("    <byte_runs>\n");
+        for run in &entry.byte_runs {
+            xml.push_str(&format!(
+                "      <byte_run file_offset=\"{}\" img_offset=\"{}\" len=\"{}\"/>\n",
+                run.file_offset, run.img_offset, run.len
+            ));
+        }
+        xml.push_str("    </byte_runs>\n");
+    }
+
+    if !entry.hash_sha1.is_empty() {
+        xml.push_str(&format!(
+            "    <hashdigest type=\"sha1\">{}</hashdigest>\n",
+            escape(&entry.hash_sha1)
+        ));
+    }
+    xml.push_str("  </fileobject>\n");
+}
+
+fn element(xml: &mut String, indent: usize, name: &str, value: &str) {
+    xml.push_str(&format!(
+        "{:indent$}<{name}>{}</{name}>\n",
+        "",
+        escape(value),
+        indent = indent,
+        name = name
+    ));
+}
+
+/// RFC 3339 time, as used by the XML and JSON-LD exports.
+pub(crate) fn iso_time(time: FileTime) -> Option<String> {
+    DateTime::from_timestamp(time.unix_seconds(), time.nanoseconds()).map(|t| t.to_rfc3339())
+}
+
+/// Escape text for XML, dropping control characters XML 1.0 cannot carry.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::case::CaseInfo;
+
+    #[test]
+    fn test_dfxml_document() {
+        let case = CaseMetadata::new(CaseInfo::default());
+        let entry = TimelineEntry {
+            source_file: "C:\\Users\\a&b\\<ntuser>.dat".to_string(),
+            hash_sha1: "ab".repeat(20),
+            inode: "42".to_string(),
+            size: 8192,
+            modified: Some(FileTime::from_unix_time(1_700_000_000, 0)),
+            byte_runs: vec![
+                ByteRun {
+                    file_offset: 0,
+                    img_offset: 4096,
+                    len: 4096,
+                },
+                ByteRun {
+                    file_offset: 4096,
+                    img_offset: 65536,
+                    len: 4096,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let xml = String::from_utf8(document(&case, &[entry])).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<dfxml "));
+        assert!(xml.contains(&format!("<program>{}</program>", case.tool.name)));
+        assert!(xml.contains("<filename>C:\\Users\\a&amp;b\\&lt;ntuser&gt;.dat</filename>"));
+        assert!(xml.contains("<mtime>2023-11-14T22:13:20+00:00</mtime>"));
+        assert!(xml.contains("<byte_run file_offset=\"4096\" img_offset=\"65536\" len=\"4096\"/>"));
+        assert!(xml.contains(&format!(
+            "<hashdigest type=\"sha1\">{}</hashdigest>",
+            "ab".repeat(20)
+        )));
+        assert!(!xml.contains("<uid>"));
+        assert!(xml.trim_end().ends_with("</dfxml>"));
+    }
+
+    #[test]
+    fn test_escape_control_characters() {
+        assert_eq!(escape("a\u{1}b\t\"c\""), "a\u{FFFD}b\t&quot;c&quot;");
+    }
+}
@@ -0,0 +1,433 @@
+//! Compare two collections of the same host by original source path and
+//! hash: files added, removed and modified between the two runs. Rows are
+//! told apart by the artifact that found them when the collection has a JSON
+//! Lines manifest.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use csv_async::AsyncWriterBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::csv::{CsvLogItem, read_manifest_items};
+use crate::error::{CollectorError, Result};
+use crate::failure::FAILED_PREFIX;
+use crate::platform::{JSONL_MANIFEST_NAME, MANIFEST_NAME};
+use crate::verify::read_collection_files;
+
+/// Output format of a collection diff.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl fmt::Display for DiffFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DiffFormat::Csv => "csv",
+            DiffFormat::Json => "json",
+        })
+    }
+}
+
+impl FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(DiffFormat::Csv),
+            "json" => Ok(DiffFormat::Json),
+            other => Err(format!(
+                "unknown diff format '{}' (expected csv or json)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+    /// Not read in one of the collections, so its content is unknown
+    Failed,
+}
+
+/// Metadata of a file in one of the two collections.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileState {
+    pub hash_sha1: String,
+    pub file_size: u64,
+    pub modified_time: String,
+    pub access_time: String,
+    pub collect_time: String,
+    pub status: String,
+}
+
+impl FileState {
+    /// Whether the file was not read, e.g. a failed copy
+    fn failed(&self) -> bool {
+        self.status.starts_with(FAILED_PREFIX) || self.hash_sha1.is_empty()
+    }
+}
+
+impl From<CsvLogItem> for FileState {
+    fn from(item: CsvLogItem) -> Self {
+        Self {
+            hash_sha1: item.hash_sha1,
+            file_size: item.file_size,
+            modified_time: item.modified_time,
+            access_time: item.access_time,
+            collect_time: item.collect_time,
+            status: item.status,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChange {
+    pub change: ChangeKind,
+    pub source_file: String,
+    /// Resource pattern or inventory root that found the file, if known
+    pub artifact: Option<String>,
+    pub old: Option<FileState>,
+    pub new: Option<FileState>,
+}
+
+/// Flat CSV row of a [`FileChange`].
+#[derive(Serialize)]
+struct DiffRow<'a> {
+    change: ChangeKind,
+    source_file: &'a str,
+    old_hash_sha1: &'a str,
+    new_hash_sha1: &'a str,
+    old_file_size: Option<u64>,
+    new_file_size: Option<u64>,
+    old_modified_time: &'a str,
+    new_modified_time: &'a str,
+    artifact: &'a str,
+}
+
+impl<'a> From<&'a FileChange> for DiffRow<'a> {
+    fn from(change: &'a FileChange) -> Self {
+        let old = change.old.as_ref();
+        let new = change.new.as_ref();
+
+        Self {
+            change: change.change,
+            source_file: &change.source_file,
+            old_hash_sha1: old.map_or("", |s| s.hash_sha1.as_str()),
+            new_hash_sha1: new.map_or("", |s| s.hash_sha1.as_str()),
+            old_file_size: old.map(|s| s.file_size),
+            new_file_size: new.map(|s| s.file_size),
+            old_modified_time: old.map_or("", |s| s.modified_time.as_str()),
+            new_modified_time: new.map_or("", |s| s.modified_time.as_str()),
+            artifact: change.artifact.as_deref().unwrap_or(""),
+        }
+    }
+}
+
+/// Differences between an old and a new collection.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CollectionDiff {
+    pub old: String,
+    pub new: String,
+    pub unchanged: u64,
+    pub changes: Vec<FileChange>,
+}
+
+/// Manifest row with the artifact that found it, if known.
+pub type ManifestRow = (CsvLogItem, Option<String>);
+
+/// Rows of a collection by source path and artifact. Rows sharing both are
+/// numbered in manifest order, so they are compared in pairs.
+type Keyed = BTreeMap<(String, Option<String>, usize), FileState>;
+
+impl CollectionDiff {
+    /// Compare manifest rows by source path and hash.
+    pub fn from_items(old: Vec<CsvLogItem>, new: Vec<CsvLogItem>) -> Self {
+        let without_artifact = |items: Vec<CsvLogItem>| items.into_iter().map(|item| (item, None));
+        Self::from_rows(
+            without_artifact(old).collect(),
+            without_artifact(new).collect(),
+        )
+    }
+
+    /// Compare manifest rows by source path, artifact and hash. Rows that
+    /// were not read in either collection are reported as failed.
+    pub fn from_rows(old: Vec<ManifestRow>, new: Vec<ManifestRow>) -> Self {
+        let keyed = |rows: Vec<ManifestRow>| -> Keyed {
+            let mut seen = HashMap::new();
+            rows.into_iter()
+                .map(|(item, artifact)| {
+                    let count = seen
+                        .entry((item.source_file.clone(), artifact.clone()))
+                        .or_insert(0);
+                    *count += 1;
+                    (
+                        (item.source_file.clone(), artifact, *count),
+                        FileState::from(item),
+                    )
+                })
+                .collect()
+        };
+        let mut old = keyed(old);
+        let new = keyed(new);
+
+        let mut diff = Self::default();
+        let mut push = |change, (source_file, artifact, _), old, new| {
+            diff.changes.push(FileChange {
+                change,
+                source_file,
+                artifact,
+                old,
+                new,
+            })
+        };
+        let mut unchanged = 0;
+        for (key, new_state) in new {
+            match old.remove(&key) {
+                None if new_state.failed() => push(ChangeKind::Failed, key, None, Some(new_state)),
+                None => push(ChangeKind::Added, key, None, Some(new_state)),
+                Some(old_state) if old_state.failed() || new_state.failed() => {
+                    push(ChangeKind::Failed, key, Some(old_state), Some(new_state))
+                }
+                Some(old_state)
+                    if !old_state
+                        .hash_sha1
+                        .eq_ignore_ascii_case(&new_state.hash_sha1) =>
+                {
+                    push(ChangeKind::Modified, key, Some(old_state), Some(new_state))
+                }
+                Some(_) => unchanged += 1,
+            }
+        }
+        for (key, old_state) in old {
+            match old_state.failed() {
+                true => push(ChangeKind::Failed, key, Some(old_state), None),
+                false => push(ChangeKind::Removed, key, Some(old_state), None),
+            }
+        }
+
+        diff.unchanged = unchanged;
+        diff.changes
+            .sort_by(|a, b| (&a.source_file, &a.artifact).cmp(&(&b.source_file, &b.artifact)));
+        diff
+    }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.change == kind).count()
+    }
+
+    pub async fn to_csv(&self) -> Result<Vec<u8>> {
+        let mut serializer = AsyncWriterBuilder::new().create_serializer(Vec::new());
+        for change in &self.changes {
+            serializer
+                .serialize(DiffRow::from(change))
+                .await
+                .map_err(|e| CollectorError::CsvError(format!("Failed to write row: {}", e)))?;
+        }
+        serializer
+            .into_inner()
+            .await
+            .map_err(|e| CollectorError::CsvError(format!("Failed to flush CSV: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(|e| CollectorError::CsvError(e.to_string()))
+    }
+
+    pub async fn render(&self, format: DiffFormat) -> Result<Vec<u8>> {
+        match format {
+            DiffFormat::Csv => self.to_csv().await,
+            DiffFormat::Json => self.to_json(),
+        }
+    }
+}
+
+/// Diff two collections, each a collection folder, an archive or a manifest
+/// CSV.
+pub async fn diff_collections<O, N>(old: O, new: N) -> Result<CollectionDiff>
+where
+    O: AsRef<Path>,
+    N: AsRef<Path>,
+{
+    let old_path = old.as_ref().to_path_buf();
+    let new_path = new.as_ref().to_path_buf();
+
+    let old_items = load_items(old_path.clone()).await?;
+    let new_items = load_items(new_path.clone()).await?;
+
+    let mut diff = CollectionDiff::from_rows(old_items, new_items);
+    diff.old = old_path.to_string_lossy().to_string();
+    diff.new = new_path.to_string_lossy().to_string();
+    Ok(diff)
+}
+
+/// Manifest rows of a collection, with their artifacts from the JSON Lines
+/// manifest if there is one, matched by chain hash.
+async fn load_items(path: PathBuf) -> Result<Vec<ManifestRow>> {
+    let names = [MANIFEST_NAME, JSONL_MANIFEST_NAME];
+    let mut files = tokio::task::spawn_blocking(move || read_collection_files(&path, &names))
+        .await
+        .map_err(|e| CollectorError::CollectionFailed(e.to_string()))??;
+    let manifest = files.remove(MANIFEST_NAME).ok_or_else(|| {
+        CollectorError::ManifestVerification(format!("{} not found", MANIFEST_NAME))
+    })?;
+    let items = read_manifest_items(&manifest).await?;
+
+    let mut artifacts = HashMap::new();
+    for line in files
+        .get(JSONL_MANIFEST_NAME)
+        .into_iter()
+        .flat_map(|data| data.split(|&b| b == b'\n'))
+    {
+        let Ok(record) = serde_json::from_slice::<JsonlArtifact>(line) else {
+            continue;
+        };
+        if let Some(artifact) = record.artifact {
+            artifacts.insert(record.chain_hash, artifact);
+        }
+    }
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let artifact = artifacts.remove(&item.chain_hash);
+            (item, artifact)
+        })
+        .collect())
+}
+
+/// Fields of a JSON Lines manifest record the diff needs.
+#[derive(Deserialize)]
+struct JsonlArtifact {
+    artifact: Option<String>,
+    chain_hash: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(source: &str, hash: &str) -> CsvLogItem {
+        CsvLogItem::with_paths(source, source).with_hash(hash.to_string())
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let old = vec![item("/a", "1"), item("/b", "2"), item("/c", "3")];
+        let new = vec![item("/a", "1"), item("/b", "9"), item("/d", "4")];
+
+        let diff = CollectionDiff::from_items(old, new);
+
+        assert_eq!(diff.unchanged, 1);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|c| (c.change, c.source_file.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (ChangeKind::Modified, "/b"),
+                (ChangeKind::Removed, "/c"),
+                (ChangeKind::Added, "/d"),
+            ]
+        );
+        assert_eq!(diff.changes[0].old.as_ref().unwrap().hash_sha1, "2");
+        assert_eq!(diff.changes[0].new.as_ref().unwrap().hash_sha1, "9");
+    }
+
+    #[test]
+    fn test_diff_failed_rows() {
+        let failed = |source: &str| {
+            CsvLogItem::with_paths(source, "")
+                .with_status(format!("{}FileRead: permission denied", FAILED_PREFIX))
+        };
+        let old = vec![item("/a", "1"), failed("/b"), item("/c", "3")];
+        let new = vec![failed("/a"), failed("/b"), failed("/d"), item("/c", "3")];
+
+        let diff = CollectionDiff::from_items(old, new);
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.count(ChangeKind::Failed), 3);
+        assert_eq!(diff.count(ChangeKind::Modified), 0);
+        assert_eq!(diff.count(ChangeKind::Added), 0);
+    }
+
+    #[test]
+    fn test_diff_duplicate_sources() {
+        let row = |hash: &str, artifact: &str| (item("/a", hash), Some(artifact.to_string()));
+        let old = vec![row("1", "/*"), row("1", "/a*"), row("1", "/a*")];
+        let new = vec![row("2", "/*"), row("1", "/a*"), row("1", "/a*")];
+
+        let diff = CollectionDiff::from_rows(old, new);
+
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].change, ChangeKind::Modified);
+        assert_eq!(diff.changes[0].artifact.as_deref(), Some("/*"));
+    }
+
+    #[tokio::test]
+    async fn test_diff_output() {
+        let diff = CollectionDiff::from_items(vec![item("/a", "1")], vec![item("/b", "2")]);
+
+        let csv = String::from_utf8(diff.to_csv().await.unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("change,source_file,old_hash_sha1,new_hash_sha1"));
+        assert!(lines[1].starts_with("removed,/a,1,,"));
+        assert!(lines[2].starts_with("added,/b,,2,"));
+
+        let json: serde_json::Value = serde_json::from_slice(&diff.to_json().unwrap()).unwrap();
+        assert_eq!(json["changes"][1]["change"], "added");
+        assert!(json["changes"][1]["old"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_diff_manifest_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old = temp_dir.path().join("old.csv");
+        let new = temp_dir.path().join("new.csv");
+        std::fs::write(&old, "source_file,hash_sha1\n/a,1\n").unwrap();
+        std::fs::write(&new, "source_file,hash_sha1\n/a,2\n").unwrap();
+
+        let diff = diff_collections(&old, &new).await.unwrap();
+        assert_eq!(diff.count(ChangeKind::Modified), 1);
+        assert!(diff.old.ends_with("old.csv"));
+    }
+
+    #[tokio::test]
+    async fn test_diff_artifacts_from_jsonl_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (run, hash) in [("old", "1"), ("new", "2")] {
+            let dir = temp_dir.path().join(run);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(
+                dir.join(MANIFEST_NAME),
+                format!("source_file,hash_sha1,chain_hash\n/a,{hash},c1\n/a,1,c2\n"),
+            )
+            .unwrap();
+            std::fs::write(
+                dir.join(JSONL_MANIFEST_NAME),
+                "{\"artifact\":\"/*\",\"chain_hash\":\"c1\"}\n\
+                 {\"artifact\":\"/a\",\"chain_hash\":\"c2\"}\n",
+            )
+            .unwrap();
+        }
+
+        let diff = diff_collections(temp_dir.path().join("old"), temp_dir.path().join("new"))
+            .await
+            .unwrap();
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.count(ChangeKind::Modified), 1);
+        assert_eq!(diff.changes[0].artifact.as_deref(), Some("/*"));
+    }
+}
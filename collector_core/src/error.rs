@@ -1,6 +1,69 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
 use std::path::PathBuf;
 use thiserror::Error;
 
+/// Process exit code of a successful run.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Process exit code when `verify` finds missing, extra or modified files.
+pub const EXIT_MISMATCH: i32 = 1;
+/// Process exit code of a collection that completed with files not collected.
+pub const EXIT_PARTIAL: i32 = 3;
+/// Process exit code of a cancelled collection, sealed with the files done so far.
+pub const EXIT_CANCELLED: i32 = 130;
+
+/// Broad class of a [`CollectorError`], each with its own process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    Config,
+    Permission,
+    Resource,
+    Input,
+    Io,
+    Acquisition,
+    Archive,
+    Manifest,
+    Collection,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Config => "config",
+            ErrorCategory::Permission => "permission",
+            ErrorCategory::Resource => "resource",
+            ErrorCategory::Input => "input",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Acquisition => "acquisition",
+            ErrorCategory::Archive => "archive",
+            ErrorCategory::Manifest => "manifest",
+            ErrorCategory::Collection => "collection",
+        }
+    }
+
+    /// Process exit code of a run that failed with an error of this category.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCategory::Config => 2,
+            ErrorCategory::Permission => 4,
+            ErrorCategory::Resource => 5,
+            ErrorCategory::Input => 6,
+            ErrorCategory::Io => 7,
+            ErrorCategory::Acquisition => 8,
+            ErrorCategory::Archive => 9,
+            ErrorCategory::Manifest => 10,
+            ErrorCategory::Collection => 11,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Main error type for collector operations.
 #[derive(Error, Debug)]
 pub enum CollectorError {
@@ -85,10 +148,50 @@ pub enum CollectorError {
     #[error("Failed to create ZIP '{path}': {reason}")]
     ZipCreation { path: PathBuf, reason: String },
 
+    // Encryption Errors
+    #[error("Invalid recipient '{recipient}': {reason}")]
+    InvalidRecipient { recipient: String, reason: String },
+
+    #[error("Encryption failed: {0}")]
+    Encryption(String),
+
+    #[error("Invalid signing key: {0}")]
+    InvalidKey(String),
+
+    // Verification Errors
+    #[error("Manifest verification failed: {0}")]
+    ManifestVerification(String),
+
+    #[error("Not a collection folder or supported archive: {0}")]
+    UnsupportedCollection(PathBuf),
+
+    // Hash Set Errors
+    #[error("Hash set '{path}': {reason}")]
+    HashSet { path: PathBuf, reason: String },
+
+    // Indicator Errors
+    #[error("Invalid indicator at {path}:{line}: {reason}")]
+    InvalidIndicator {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+
+    #[error("Invalid rule at {path}:{line}: {reason}")]
+    InvalidRule {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+
     // CSV Errors
     #[error("CSV error: {0}")]
     CsvError(String),
 
+    // Manifest Errors
+    #[error("Manifest database error: {0}")]
+    ManifestDatabase(String),
+
     // Pattern Errors
     #[error("Invalid pattern '{pattern}': {reason}")]
     InvalidPattern { pattern: String, reason: String },
@@ -116,6 +219,9 @@ pub enum CollectorError {
     #[error("No files matched patterns")]
     NoFilesMatched,
 
+    #[error("Collection cancelled")]
+    Cancelled,
+
     #[error("Failed to extract '{source_path}' to '{destination}': {reason}")]
     ExtractionFailed {
         source_path: PathBuf,
@@ -124,6 +230,149 @@ pub enum CollectorError {
     },
 }
 
+impl CollectorError {
+    /// Name of the variant, e.g. `FileRead`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CollectorError::FileRead { .. } => "FileRead",
+            CollectorError::FileWrite { .. } => "FileWrite",
+            CollectorError::DirectoryCreate { .. } => "DirectoryCreate",
+            CollectorError::PathNotFound(_) => "PathNotFound",
+            CollectorError::Io(_) => "Io",
+            CollectorError::InsufficientPrivileges => "InsufficientPrivileges",
+            CollectorError::NtfsExtraction { .. } => "NtfsExtraction",
+            CollectorError::InvalidDriveLetter(_) => "InvalidDriveLetter",
+            CollectorError::NtfsError(_) => "NtfsError",
+            CollectorError::SectorReaderError(_) => "SectorReaderError",
+            CollectorError::VssOperation(_) => "VssOperation",
+            CollectorError::NoVssSnapshots(_) => "NoVssSnapshots",
+            CollectorError::VssComInit(_) => "VssComInit",
+            CollectorError::VssMountFailed(_) => "VssMountFailed",
+            CollectorError::ResourceParse { .. } => "ResourceParse",
+            CollectorError::ResourceNotFound(_) => "ResourceNotFound",
+            CollectorError::InvalidResource { .. } => "InvalidResource",
+            CollectorError::ResourcesDirectoryNotFound(_) => "ResourcesDirectoryNotFound",
+            CollectorError::NoResourcesSpecified => "NoResourcesSpecified",
+            CollectorError::ZipError(_) => "ZipError",
+            CollectorError::ZipCreation { .. } => "ZipCreation",
+            CollectorError::InvalidRecipient { .. } => "InvalidRecipient",
+            CollectorError::Encryption(_) => "Encryption",
+            CollectorError::InvalidKey(_) => "InvalidKey",
+            CollectorError::ManifestVerification(_) => "ManifestVerification",
+            CollectorError::UnsupportedCollection(_) => "UnsupportedCollection",
+            CollectorError::HashSet { .. } => "HashSet",
+            CollectorError::InvalidIndicator { .. } => "InvalidIndicator",
+            CollectorError::InvalidRule { .. } => "InvalidRule",
+            CollectorError::CsvError(_) => "CsvError",
+            CollectorError::ManifestDatabase(_) => "ManifestDatabase",
+            CollectorError::InvalidPattern { .. } => "InvalidPattern",
+            CollectorError::GlobError(_) => "GlobError",
+            CollectorError::PatternError(_) => "PatternError",
+            CollectorError::Config(_) => "Config",
+            CollectorError::InvalidSourcePath(_) => "InvalidSourcePath",
+            CollectorError::InvalidDestinationPath(_) => "InvalidDestinationPath",
+            CollectorError::CollectionFailed(_) => "CollectionFailed",
+            CollectorError::NoFilesMatched => "NoFilesMatched",
+            CollectorError::Cancelled => "Cancelled",
+            CollectorError::ExtractionFailed { .. } => "ExtractionFailed",
+        }
+    }
+
+    /// Stable numeric code of the variant. The hundreds are the exit code of its category.
+    pub fn code(&self) -> u16 {
+        match self {
+            CollectorError::Config(_) => 201,
+            CollectorError::InvalidSourcePath(_) => 202,
+            CollectorError::InvalidDestinationPath(_) => 203,
+            CollectorError::InvalidPattern { .. } => 204,
+            CollectorError::GlobError(_) => 205,
+            CollectorError::PatternError(_) => 206,
+            CollectorError::InsufficientPrivileges => 401,
+            CollectorError::ResourceParse { .. } => 501,
+            CollectorError::ResourceNotFound(_) => 502,
+            CollectorError::InvalidResource { .. } => 503,
+            CollectorError::ResourcesDirectoryNotFound(_) => 504,
+            CollectorError::NoResourcesSpecified => 505,
+            CollectorError::HashSet { .. } => 601,
+            CollectorError::InvalidIndicator { .. } => 602,
+            CollectorError::InvalidRule { .. } => 603,
+            CollectorError::FileRead { .. } => 701,
+            CollectorError::FileWrite { .. } => 702,
+            CollectorError::DirectoryCreate { .. } => 703,
+            CollectorError::PathNotFound(_) => 704,
+            CollectorError::Io(_) => 705,
+            CollectorError::NtfsExtraction { .. } => 801,
+            CollectorError::InvalidDriveLetter(_) => 802,
+            CollectorError::NtfsError(_) => 803,
+            CollectorError::SectorReaderError(_) => 804,
+            CollectorError::VssOperation(_) => 811,
+            CollectorError::NoVssSnapshots(_) => 812,
+            CollectorError::VssComInit(_) => 813,
+            CollectorError::VssMountFailed(_) => 814,
+            CollectorError::ZipError(_) => 901,
+            CollectorError::ZipCreation { .. } => 902,
+            CollectorError::InvalidRecipient { .. } => 911,
+            CollectorError::Encryption(_) => 912,
+            CollectorError::InvalidKey(_) => 913,
+            CollectorError::ManifestVerification(_) => 1001,
+            CollectorError::UnsupportedCollection(_) => 1002,
+            CollectorError::CsvError(_) => 1003,
+            CollectorError::ManifestDatabase(_) => 1004,
+            CollectorError::CollectionFailed(_) => 1101,
+            CollectorError::NoFilesMatched => 1102,
+            CollectorError::ExtractionFailed { .. } => 1103,
+            CollectorError::Cancelled => 1104,
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self.code() / 100 {
+            2 => ErrorCategory::Config,
+            4 => ErrorCategory::Permission,
+            5 => ErrorCategory::Resource,
+            6 => ErrorCategory::Input,
+            7 => ErrorCategory::Io,
+            8 => ErrorCategory::Acquisition,
+            9 => ErrorCategory::Archive,
+            10 => ErrorCategory::Manifest,
+            _ => ErrorCategory::Collection,
+        }
+    }
+
+    /// Process exit code of a run that failed with this error. A cancelled
+    /// run exits with [`EXIT_CANCELLED`] whichever way it stopped.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CollectorError::Cancelled => EXIT_CANCELLED,
+            _ => self.category().exit_code(),
+        }
+    }
+
+    /// Underlying I/O error, if any.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            CollectorError::FileRead { source, .. }
+            | CollectorError::FileWrite { source, .. }
+            | CollectorError::DirectoryCreate { source, .. }
+            | CollectorError::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized as `{code, category, kind, message, os_error}` for machine-readable reports.
+impl Serialize for CollectorError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CollectorError", 5)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("category", &self.category())?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("os_error", &self.io_error().and_then(|e| e.raw_os_error()))?;
+        state.end()
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CollectorError>;
 
 #[cfg(test)]
@@ -146,6 +395,56 @@ mod tests {
         assert!(err.to_string().contains("/test"));
     }
 
+    #[test]
+    fn test_error_kind() {
+        let io_err = std::io::Error::from_raw_os_error(13);
+        let err = CollectorError::FileRead {
+            path: PathBuf::from("/test"),
+            source: io_err,
+        };
+        assert_eq!(err.kind(), "FileRead");
+        assert_eq!(err.io_error().and_then(|e| e.raw_os_error()), Some(13));
+        assert_eq!(CollectorError::NoFilesMatched.kind(), "NoFilesMatched");
+        assert!(CollectorError::NoFilesMatched.io_error().is_none());
+    }
+
+    #[test]
+    fn test_error_code() {
+        let err = CollectorError::InsufficientPrivileges;
+        assert_eq!(err.code(), 401);
+        assert_eq!(err.category(), ErrorCategory::Permission);
+        assert_eq!(err.exit_code(), 4);
+
+        let err = CollectorError::ResourceNotFound("MFT".to_string());
+        assert_eq!(err.category(), ErrorCategory::Resource);
+        assert_eq!(err.exit_code(), 5);
+
+        assert_eq!(
+            CollectorError::NoFilesMatched.category(),
+            ErrorCategory::Collection
+        );
+        assert_eq!(CollectorError::NoFilesMatched.exit_code(), 11);
+        assert_eq!(CollectorError::Config(String::new()).exit_code(), 2);
+        assert_eq!(CollectorError::Cancelled.exit_code(), EXIT_CANCELLED);
+    }
+
+    #[test]
+    fn test_error_serialize() {
+        let err = CollectorError::FileRead {
+            path: PathBuf::from("/test"),
+            source: std::io::Error::from_raw_os_error(13),
+        };
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], 701);
+        assert_eq!(json["category"], "io");
+        assert_eq!(json["kind"], "FileRead");
+        assert_eq!(json["os_error"], 13);
+        assert!(json["message"].as_str().unwrap().contains("/test"));
+
+        let json = serde_json::to_value(CollectorError::NoFilesMatched).unwrap();
+        assert!(json["os_error"].is_null());
+    }
+
     #[test]
     fn test_insufficient_privileges_error() {
         let err = CollectorError::InsufficientPrivileges;
@@ -0,0 +1,195 @@
+//! Events emitted while a collection runs, for progress display and logs.
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::failure::{ExtractionMethod, FileFailure};
+use crate::platform::CollectionStats;
+
+/// Why a file was not copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Already copied by the interrupted run being resumed
+    Resumed,
+    /// Unchanged since the previous collection
+    Unchanged,
+    /// Found in a known-good hash set
+    Known,
+    /// Large file matching no scan rule
+    NoRuleMatch,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Resumed => "resumed",
+            SkipReason::Unchanged => "unchanged",
+            SkipReason::Known => "known",
+            SkipReason::NoRuleMatch => "no_rule_match",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Files found by one resource pattern or inventory root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactPlan {
+    pub artifact: String,
+    pub files: u64,
+}
+
+/// Something that happened during a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CollectionEvent {
+    /// Every pattern was expanded, before any file is read
+    PlanReady {
+        files: u64,
+        artifacts: Vec<ArtifactPlan>,
+    },
+    ArtifactStarted {
+        artifact: String,
+        files: u64,
+    },
+    /// `index` is 1-based, out of the `files` of the plan
+    FileStarted {
+        index: u64,
+        total: u64,
+        artifact: String,
+        path: String,
+    },
+    FileFinished {
+        path: String,
+        bytes: u64,
+        sha1: String,
+        method: ExtractionMethod,
+    },
+    /// File listed by an inventory, without its content
+    FileListed {
+        path: String,
+        size: u64,
+        sha1: Option<String>,
+    },
+    FileSkipped {
+        path: String,
+        reason: SkipReason,
+    },
+    FileFailed {
+        failure: FileFailure,
+    },
+    /// The collection stopped on its cancel token after `files_done` files
+    Cancelled {
+        files_done: u64,
+        total: u64,
+    },
+    /// Staged file added to the archive
+    ArchiveProgress {
+        index: u64,
+        total: u64,
+        entry: String,
+    },
+    /// Output finalized: collection folder, or archive files
+    Finished {
+        stats: CollectionStats,
+        outputs: Vec<PathBuf>,
+    },
+}
+
+/// Receiver of collection events. Called from the collecting task, so it
+/// should return quickly.
+pub trait EventHandler: Send + Sync {
+    fn handle(&self, event: &CollectionEvent);
+}
+
+impl<F> EventHandler for F
+where
+    F: Fn(&CollectionEvent) + Send + Sync,
+{
+    fn handle(&self, event: &CollectionEvent) {
+        self(event)
+    }
+}
+
+impl EventHandler for UnboundedSender<CollectionEvent> {
+    fn handle(&self, event: &CollectionEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Writes every event as a JSON line with the time it was received.
+pub struct JsonEventLog<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonEventLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> EventHandler for JsonEventLog<W> {
+    fn handle(&self, event: &CollectionEvent) {
+        let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(event) else {
+            return;
+        };
+        let mut line = serde_json::Map::new();
+        line.insert("time".to_string(), Utc::now().to_rfc3339().into());
+        line.extend(fields);
+
+        let Ok(mut writer) = self.writer.lock() else {
+            return;
+        };
+        let written = serde_json::to_writer(&mut *writer, &line)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(e) = written {
+            log::warn!("Failed to write event log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_event_log() {
+        let log = JsonEventLog::new(Vec::new());
+        log.handle(&CollectionEvent::FileSkipped {
+            path: "/etc/passwd".to_string(),
+            reason: SkipReason::NoRuleMatch,
+        });
+        log.handle(&CollectionEvent::ArtifactStarted {
+            artifact: "/etc/*".to_string(),
+            files: 2,
+        });
+
+        let output = log.writer.into_inner().unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "file_skipped");
+        assert_eq!(lines[0]["reason"], "no_rule_match");
+        assert!(lines[0]["time"].is_string());
+        assert_eq!(lines[1]["event"], "artifact_started");
+        assert_eq!(lines[1]["files"], 2);
+    }
+}
@@ -1,53 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+#[cfg(target_os = "windows")]
 use std::path::PathBuf;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+#[cfg(target_os = "windows")]
+use crate::dfxml::ByteRun;
 use crate::error::{CollectorError, Result};
 #[cfg(target_os = "windows")]
 use crate::extract::lowfs;
+use crate::utils::FILE_BUFFER_SIZE;
 
 #[cfg(target_os = "windows")]
 use crate::mount::VssSnapshot;
 #[cfg(target_os = "windows")]
 use regex::Regex;
 
-pub async fn extract_via_filesystem(source: &PathBuf, dest_file: &mut File) -> Result<u64> {
-    let mut source_file = File::open(source)
-        .await
-        .map_err(|e| CollectorError::FileRead {
-            path: source.clone(),
-            source: e,
-        })?;
-
-    let mut contents = Vec::new();
-    source_file
-        .read_to_end(&mut contents)
-        .await
-        .map_err(|e| CollectorError::FileRead {
-            path: source.clone(),
-            source: e,
-        })?;
-
-    let bytes_written = contents.len() as u64;
-
-    dest_file
-        .write_all(&contents)
-        .await
-        .map_err(|e| CollectorError::FileWrite {
-            path: source.clone(),
-            source: e,
-        })?;
+/// How the content of a file is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractionMethod {
+    Filesystem,
+    /// Raw NTFS parsing of the volume (Windows)
+    Ntfs,
+}
+
+impl ExtractionMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtractionMethod::Filesystem => "filesystem",
+            ExtractionMethod::Ntfs => "ntfs",
+        }
+    }
+}
+
+impl fmt::Display for ExtractionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Copy a file read through the filesystem into `dest`.
+pub fn extract_via_filesystem<W: Write + ?Sized>(source: &Path, dest: &mut W) -> Result<u64> {
+    let mut source_file = File::open(source).map_err(|e| CollectorError::FileRead {
+        path: source.to_path_buf(),
+        source: e,
+    })?;
+
+    let mut buffer = vec![0u8; FILE_BUFFER_SIZE];
+    let mut total = 0u64;
+
+    loop {
+        let bytes_read = source_file
+            .read(&mut buffer)
+            .map_err(|e| CollectorError::FileRead {
+                path: source.to_path_buf(),
+                source: e,
+            })?;
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        dest.write_all(&buffer[..bytes_read])
+            .map_err(|e| CollectorError::FileWrite {
+                path: source.to_path_buf(),
+                source: e,
+            })?;
+        total += bytes_read as u64;
+    }
 
     log::info!("Extracted via filesystem: {}", source.display());
-    Ok(bytes_written)
+    Ok(total)
 }
 
+/// Copy a file read by parsing the NTFS volume (or VSS snapshot) into
+/// `dest`, with the volume ranges it was read from.
 #[cfg(target_os = "windows")]
-pub async fn extract_via_ntfs(
-    source: &PathBuf,
-    dest_file: &mut File,
+pub fn extract_via_ntfs<W: Write + ?Sized>(
+    source: &Path,
+    dest: &mut W,
     vss_snapshot: Option<&VssSnapshot>,
-) -> Result<u64> {
+) -> Result<(u64, Vec<ByteRun>)> {
     let drive_letter = get_drive_letter(source)?;
 
     let mut volume_entry = drive_letter.clone();
@@ -62,39 +98,14 @@ pub async fn extract_via_ntfs(
     };
 
     let relative_path = source.to_string_lossy().replace(&drive_letter, "");
-    let bytes = lowfs::extract_ntfs(build_source, relative_path, dest_file).await?;
+    let extracted = lowfs::extract_ntfs(build_source, relative_path, dest)?;
 
     log::info!("Extracted via NTFS: {}", source.display());
-    Ok(bytes)
+    Ok(extracted)
 }
 
 #[cfg(target_os = "windows")]
-pub async fn extract_file(
-    source: &PathBuf,
-    dest_file: &mut File,
-    vss_snapshot: Option<&VssSnapshot>,
-) -> Result<(u64, bool)> {
-    match extract_via_filesystem(source, dest_file).await {
-        Ok(bytes) => return Ok((bytes, false)),
-        Err(e) => log::debug!("Filesystem failed, trying NTFS: {}", e),
-    }
-
-    let bytes = extract_via_ntfs(source, dest_file, vss_snapshot).await?;
-    Ok((bytes, true))
-}
-
-#[cfg(not(target_os = "windows"))]
-pub async fn extract_file(
-    source: &PathBuf,
-    dest_file: &mut File,
-    _vss_snapshot: Option<()>,
-) -> Result<(u64, bool)> {
-    let bytes = extract_via_filesystem(source, dest_file).await?;
-    Ok((bytes, false))
-}
-
-#[cfg(target_os = "windows")]
-fn get_drive_letter(path: &PathBuf) -> Result<String> {
+fn get_drive_letter(path: &Path) -> Result<String> {
     let path_str = path.to_string_lossy();
     let re = Regex::new(r"^([A-Za-z]:\\)").expect("Invalid regex");
 
@@ -108,26 +119,21 @@ fn get_drive_letter(path: &PathBuf) -> Result<String> {
 mod tests {
     use super::*;
     use tempfile::tempdir;
-    use tokio::io::AsyncWriteExt;
 
-    #[tokio::test]
-    async fn test_extract_via_filesystem() {
+    #[test]
+    fn test_extract_via_filesystem() {
         let temp_dir = tempdir().unwrap();
 
         // Create source file
         let source_path = temp_dir.path().join("source.txt");
-        let mut source_file = File::create(&source_path).await.unwrap();
-        source_file.write_all(b"test content").await.unwrap();
-        drop(source_file);
-
-        // Create destination file
-        let dest_path = temp_dir.path().join("dest.txt");
-        let mut dest_file = File::create(&dest_path).await.unwrap();
+        std::fs::write(&source_path, b"test content").unwrap();
 
-        // Extract
-        let result = extract_via_filesystem(&source_path, &mut dest_file).await;
+        // Extract into memory
+        let mut dest = Vec::new();
+        let result = extract_via_filesystem(&source_path, &mut dest);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 12); // "test content" = 12 bytes
+        assert_eq!(dest, b"test content");
     }
 
     #[cfg(target_os = "windows")]
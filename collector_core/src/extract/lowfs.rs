@@ -1,10 +1,10 @@
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, Read, Seek, Write};
 
+use ntfs::attribute_value::NtfsAttributeValue;
 use ntfs::indexes::NtfsFileNameIndex;
 use ntfs::{Ntfs, NtfsFile, NtfsReadSeek};
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 
+use crate::dfxml::ByteRun;
 use crate::error::{CollectorError, Result};
 use crate::extract::sector_reader::SectorReader;
 use crate::utils::NTFS_READ_BUFFER_SIZE;
@@ -15,11 +15,11 @@ struct NtfsContext<'n, T: Read + Seek> {
     ntfs: &'n Ntfs,
 }
 
-pub async fn extract_ntfs(
+pub fn extract_ntfs<W: Write + ?Sized>(
     device_name: String,
     artifact_path: String,
-    output_file: &mut File,
-) -> Result<u64> {
+    output: &mut W,
+) -> Result<(u64, Vec<ByteRun>)> {
     let file = std::fs::File::open(&device_name).map_err(|e| {
         CollectorError::NtfsError(format!("Failed to open volume {}: {}", device_name, e))
     })?;
@@ -65,9 +65,7 @@ pub async fn extract_ntfs(
     }
 
     let file = find_file(&mut context, filename)?;
-    let bytes = write_file_contents(&mut context, &file, output_file).await?;
-
-    Ok(bytes)
+    write_file_contents(&mut context, &file, output)
 }
 
 fn navigate_to_directory<T: Read + Seek>(
@@ -132,11 +130,11 @@ fn find_file<'n, T: Read + Seek>(
         .map_err(|e| CollectorError::NtfsError(format!("File conversion error: {}", e)))
 }
 
-async fn write_file_contents<T: Read + Seek>(
+fn write_file_contents<T: Read + Seek, W: Write + ?Sized>(
     context: &mut NtfsContext<'_, T>,
     file: &NtfsFile<'_>,
-    output: &mut File,
-) -> Result<u64> {
+    output: &mut W,
+) -> Result<(u64, Vec<ByteRun>)> {
     let data_item = file
         .data(&mut context.fs, "")
         .ok_or_else(|| CollectorError::NtfsError("No data attribute".to_string()))?
@@ -149,6 +147,7 @@ async fn write_file_contents<T: Read + Seek>(
     let mut data_value = data_attribute
         .value(&mut context.fs)
         .map_err(|e| CollectorError::NtfsError(format!("Value error: {}", e)))?;
+    let runs = byte_runs(&data_value);
 
     let mut total_bytes = 0u64;
     let mut buffer = [0u8; NTFS_READ_BUFFER_SIZE];
@@ -164,7 +163,6 @@ async fn write_file_contents<T: Read + Seek>(
 
         output
             .write_all(&buffer[..bytes_read])
-            .await
             .map_err(|e| CollectorError::FileWrite {
                 path: "output".into(),
                 source: e,
@@ -173,5 +171,45 @@ async fn write_file_contents<T: Read + Seek>(
         total_bytes += bytes_read as u64;
     }
 
-    Ok(total_bytes)
+    Ok((total_bytes, runs))
+}
+
+/// Volume ranges holding the data. Sparse runs have no location and are
+/// left out; values spread over an attribute list are not mapped.
+fn byte_runs(value: &NtfsAttributeValue<'_, '_>) -> Vec<ByteRun> {
+    match value {
+        NtfsAttributeValue::Resident(resident) => resident
+            .data_position()
+            .value()
+            .map(|position| ByteRun {
+                file_offset: 0,
+                img_offset: position.get(),
+                len: resident.len(),
+            })
+            .into_iter()
+            .collect(),
+        NtfsAttributeValue::NonResident(non_resident) => {
+            let mut runs = Vec::new();
+            let mut file_offset = 0u64;
+            let mut remaining = non_resident.len();
+            for run in non_resident.data_runs() {
+                let Ok(run) = run else { break };
+                // The last run may be allocated past the end of the data
+                let len = run.allocated_size().min(remaining);
+                if let Some(position) = run.data_position().value()
+                    && len > 0
+                {
+                    runs.push(ByteRun {
+                        file_offset,
+                        img_offset: position.get(),
+                        len,
+                    });
+                }
+                file_offset += len;
+                remaining -= len;
+            }
+            runs
+        }
+        NtfsAttributeValue::AttributeListNonResident(_) => Vec::new(),
+    }
 }
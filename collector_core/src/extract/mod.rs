@@ -6,5 +6,5 @@ mod sector_reader;
 mod get;
 
 pub use get::*;
-// extract_via_filesystem, extract_file
-// cfg(windows) : extract_via_ntfs, , extract_file, get_drive_letter,
+// extract_via_filesystem, ExtractionMethod
+// cfg(windows) : extract_via_ntfs, get_drive_letter
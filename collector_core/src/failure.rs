@@ -0,0 +1,160 @@
+//! Files that could not be collected: why, and how they were tried.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CollectorError;
+pub use crate::extract::ExtractionMethod;
+
+/// Status prefix of manifest rows of files that could not be collected.
+pub const FAILED_PREFIX: &str = "failed: ";
+
+/// A file that could not be collected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFailure {
+    pub source_file: String,
+    /// Resource pattern or inventory root the file was found by
+    pub artifact: Option<String>,
+    /// `CollectorError` variant, e.g. `FileRead`
+    pub error: String,
+    /// Stable code of the error, see [`CollectorError::code`]
+    pub code: u16,
+    /// OS error code (errno, or Win32 error on Windows)
+    pub os_error: Option<i32>,
+    /// I/O error kind, e.g. `permission denied`
+    pub cause: Option<String>,
+    /// Extraction methods tried, in order
+    pub methods: Vec<ExtractionMethod>,
+    pub message: String,
+}
+
+impl FileFailure {
+    pub fn new(
+        source_file: String,
+        artifact: Option<String>,
+        error: &CollectorError,
+        methods: Vec<ExtractionMethod>,
+    ) -> Self {
+        let io_error = error.io_error();
+        Self {
+            source_file,
+            artifact,
+            error: error.kind().to_string(),
+            code: error.code(),
+            os_error: io_error.and_then(|e| e.raw_os_error()),
+            cause: io_error.map(|e| e.kind().to_string()),
+            methods,
+            message: error.to_string(),
+        }
+    }
+
+    /// Short reason failures are grouped by, e.g. `FileRead: permission denied`.
+    pub fn reason(&self) -> String {
+        match self.cause {
+            Some(ref cause) => format!("{}: {}", self.error, cause),
+            None => self.error.clone(),
+        }
+    }
+
+    /// Manifest status of the file.
+    pub fn status(&self) -> String {
+        format!("{}{}", FAILED_PREFIX, self.reason())
+    }
+}
+
+/// Number of failures for one reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureSummary {
+    pub reason: String,
+    pub count: u64,
+}
+
+/// Failures grouped by reason, most frequent first.
+pub fn summarize(failures: &[FileFailure]) -> Vec<FailureSummary> {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    for failure in failures {
+        *counts.entry(failure.reason()).or_default() += 1;
+    }
+    let mut summary: Vec<FailureSummary> = counts
+        .into_iter()
+        .map(|(reason, count)| FailureSummary { reason, count })
+        .collect();
+    summary.sort_by_key(|s| Reverse(s.count));
+    summary
+}
+
+/// Failures report written next to the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureReport {
+    pub files_failed: u64,
+    pub by_reason: Vec<FailureSummary>,
+    pub failures: Vec<FileFailure>,
+}
+
+impl FailureReport {
+    pub fn new(failures: &[FileFailure]) -> Self {
+        Self {
+            files_failed: failures.len() as u64,
+            by_reason: summarize(failures),
+            failures: failures.to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+    use std::path::PathBuf;
+
+    fn failure(source: &str, error: CollectorError) -> FileFailure {
+        FileFailure::new(
+            source.to_string(),
+            Some("/var/log/*".to_string()),
+            &error,
+            vec![ExtractionMethod::Filesystem],
+        )
+    }
+
+    #[test]
+    fn test_failure_summary() {
+        let denied = |path: &str| CollectorError::FileRead {
+            path: PathBuf::from(path),
+            source: Error::from(ErrorKind::PermissionDenied),
+        };
+        let failures = vec![
+            failure("/var/log/a", denied("/var/log/a")),
+            failure("/var/log/b", CollectorError::CsvError("full".to_string())),
+            failure("/var/log/c", denied("/var/log/c")),
+        ];
+
+        assert_eq!(failures[0].reason(), "FileRead: permission denied");
+        assert_eq!(failures[0].status(), "failed: FileRead: permission denied");
+        assert_eq!(failures[0].code, 701);
+        assert_eq!(failures[1].cause, None);
+
+        let report = FailureReport::new(&failures);
+        assert_eq!(report.files_failed, 3);
+        assert_eq!(
+            report.by_reason,
+            vec![
+                FailureSummary {
+                    reason: "FileRead: permission denied".to_string(),
+                    count: 2
+                },
+                FailureSummary {
+                    reason: "CsvError".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        let json: serde_json::Value = serde_json::from_slice(&report.to_json()).unwrap();
+        assert_eq!(json["failures"][0]["methods"][0], "filesystem");
+    }
+}
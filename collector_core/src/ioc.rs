@@ -0,0 +1,409 @@
+//! Indicators of compromise checked against every file of a collection:
+//! known-bad hashes, file names and path regexes.
+//!
+//! Indicator lists hold one indicator per line as `<kind>:<value>`, where
+//! kind is `md5`, `sha1`, `sha256`, `filename` or `path` (a regex matched
+//! against the full source path). A bare hash is accepted as is. Empty lines
+//! and lines starting with `#` are ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::Utc;
+use md5::Md5;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::csv::CsvLogItem;
+use crate::error::{CollectorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IocKind {
+    Md5,
+    Sha1,
+    Sha256,
+    Filename,
+    Path,
+}
+
+impl IocKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "md5" => Some(IocKind::Md5),
+            "sha1" => Some(IocKind::Sha1),
+            "sha256" => Some(IocKind::Sha256),
+            "filename" => Some(IocKind::Filename),
+            "path" => Some(IocKind::Path),
+            _ => None,
+        }
+    }
+
+    /// Kind of a bare hash, from its length.
+    fn of_hash(value: &str) -> Option<Self> {
+        if !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        match value.len() {
+            32 => Some(IocKind::Md5),
+            40 => Some(IocKind::Sha1),
+            64 => Some(IocKind::Sha256),
+            _ => None,
+        }
+    }
+
+    fn hash_len(self) -> Option<usize> {
+        match self {
+            IocKind::Md5 => Some(32),
+            IocKind::Sha1 => Some(40),
+            IocKind::Sha256 => Some(64),
+            IocKind::Filename | IocKind::Path => None,
+        }
+    }
+}
+
+impl fmt::Display for IocKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IocKind::Md5 => "md5",
+            IocKind::Sha1 => "sha1",
+            IocKind::Sha256 => "sha256",
+            IocKind::Filename => "filename",
+            IocKind::Path => "path",
+        })
+    }
+}
+
+/// A single indicator. Its id, `<list name>:<line>`, is what the manifest
+/// records for a matching file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Indicator {
+    pub id: String,
+    pub kind: IocKind,
+    pub value: String,
+}
+
+/// Indicators loaded from one or more lists.
+#[derive(Debug, Default)]
+pub struct IocSet {
+    indicators: Vec<Indicator>,
+    by_id: HashMap<String, usize>,
+    /// Lowercase hex of every hash indicator
+    hashes: HashMap<String, Vec<usize>>,
+    /// Lowercase file names
+    filenames: HashMap<String, Vec<usize>>,
+    paths: Vec<(Regex, usize)>,
+}
+
+impl IocSet {
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut set = Self::default();
+        for path in paths {
+            let path = path.as_ref();
+            let data = std::fs::read_to_string(path).map_err(|e| CollectorError::FileRead {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+            set.parse(path, &data)?;
+        }
+
+        log::info!("Loaded {} indicators", set.len());
+        Ok(set)
+    }
+
+    fn parse(&mut self, path: &Path, data: &str) -> Result<()> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let invalid = |line: usize, reason: String| CollectorError::InvalidIndicator {
+            path: path.to_path_buf(),
+            line,
+            reason,
+        };
+
+        for (index, line) in data.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (kind, value) = match line
+                .split_once(':')
+                .and_then(|(kind, value)| IocKind::parse(kind).map(|kind| (kind, value.trim())))
+            {
+                Some(typed) => typed,
+                None => match IocKind::of_hash(line) {
+                    Some(kind) => (kind, line),
+                    None => {
+                        return Err(invalid(
+                            line_number,
+                            "expected <kind>:<value> or a bare hash".into(),
+                        ));
+                    }
+                },
+            };
+
+            if let Some(len) = kind.hash_len()
+                && IocKind::of_hash(value).and_then(IocKind::hash_len) != Some(len)
+            {
+                return Err(invalid(line_number, format!("not a {} hash", kind)));
+            }
+            if value.is_empty() {
+                return Err(invalid(line_number, "empty value".into()));
+            }
+
+            let index = self.indicators.len();
+            match kind {
+                IocKind::Md5 | IocKind::Sha1 | IocKind::Sha256 => self
+                    .hashes
+                    .entry(value.to_ascii_lowercase())
+                    .or_default()
+                    .push(index),
+                IocKind::Filename => self
+                    .filenames
+                    .entry(value.to_lowercase())
+                    .or_default()
+                    .push(index),
+                IocKind::Path => {
+                    let regex =
+                        Regex::new(value).map_err(|e| invalid(line_number, e.to_string()))?;
+                    self.paths.push((regex, index));
+                }
+            }
+
+            let id = format!("{}:{}", name, line_number);
+            self.by_id.insert(id.clone(), index);
+            self.indicators.push(Indicator {
+                id,
+                kind,
+                value: value.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+
+    /// Whether MD5 digests must be computed while collecting.
+    pub fn needs_md5(&self) -> bool {
+        self.indicators.iter().any(|i| i.kind == IocKind::Md5)
+    }
+
+    /// Whether SHA256 digests must be computed while collecting.
+    pub fn needs_sha256(&self) -> bool {
+        self.indicators.iter().any(|i| i.kind == IocKind::Sha256)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Indicator> {
+        self.by_id.get(id).map(|&index| &self.indicators[index])
+    }
+
+    /// Indicators matching a manifest row and the digests computed for it.
+    pub fn check(&self, item: &CsvLogItem, digests: &ExtraDigests) -> Vec<&Indicator> {
+        let mut matched: Vec<usize> = Vec::new();
+
+        let hashes = [
+            Some(item.hash_sha1.as_str()),
+            digests.md5.as_deref(),
+            digests.sha256.as_deref(),
+        ];
+        for hash in hashes.into_iter().flatten().filter(|h| !h.is_empty()) {
+            if let Some(found) = self.hashes.get(&hash.to_ascii_lowercase()) {
+                matched.extend(found);
+            }
+        }
+
+        if let Some(name) = Path::new(&item.source_file).file_name()
+            && let Some(found) = self.filenames.get(&name.to_string_lossy().to_lowercase())
+        {
+            matched.extend(found);
+        }
+
+        matched.extend(
+            self.paths
+                .iter()
+                .filter(|(regex, _)| regex.is_match(&item.source_file))
+                .map(|(_, index)| *index),
+        );
+
+        matched.sort_unstable();
+        matched.dedup();
+        matched.into_iter().map(|i| &self.indicators[i]).collect()
+    }
+}
+
+/// MD5 and SHA256 of a file, computed only when an indicator needs them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtraDigests {
+    pub md5: Option<String>,
+    pub sha256: Option<String>,
+}
+
+/// Writer adapter computing the extra digests an [`IocSet`] needs.
+pub struct DigestWriter<W> {
+    inner: W,
+    md5: Option<Md5>,
+    sha256: Option<Sha256>,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W, iocs: Option<&IocSet>) -> Self {
+        Self {
+            inner,
+            md5: iocs.filter(|s| s.needs_md5()).map(|_| Md5::new()),
+            sha256: iocs.filter(|s| s.needs_sha256()).map(|_| Sha256::new()),
+        }
+    }
+
+    pub fn finish(self) -> (W, ExtraDigests) {
+        let digests = ExtraDigests {
+            md5: self.md5.map(|h| hex::encode(h.finalize())),
+            sha256: self.sha256.map(|h| hex::encode(h.finalize())),
+        };
+        (self.inner, digests)
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(ref mut md5) = self.md5 {
+            md5.update(&buf[..written]);
+        }
+        if let Some(ref mut sha256) = self.sha256 {
+            sha256.update(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A collected file matching one or more indicators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IocFinding {
+    pub source_file: String,
+    pub destination_file: String,
+    pub indicators: Vec<Indicator>,
+}
+
+/// Findings report written as `Collector_findings.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IocReport {
+    pub generated_at: String,
+    pub indicators_loaded: usize,
+    pub files_checked: u64,
+    pub findings: Vec<IocFinding>,
+}
+
+impl IocReport {
+    /// Build the report from manifest rows flagged with indicator ids.
+    pub fn from_items<'a, I>(iocs: &IocSet, files_checked: u64, items: I) -> Self
+    where
+        I: IntoIterator<Item = &'a CsvLogItem>,
+    {
+        let findings = items
+            .into_iter()
+            .filter(|item| !item.ioc.is_empty())
+            .map(|item| IocFinding {
+                source_file: item.source_file.clone(),
+                destination_file: item.destination_file.clone(),
+                indicators: item
+                    .ioc_ids()
+                    .filter_map(|id| iocs.get(id).cloned())
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            generated_at: Utc::now().to_rfc3339(),
+            indicators_loaded: iocs.len(),
+            files_checked,
+            findings,
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    const ABC_MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+
+    fn set(data: &str) -> IocSet {
+        let mut set = IocSet::default();
+        set.parse(Path::new("bad.txt"), data).unwrap();
+        set
+    }
+
+    #[test]
+    fn test_parse_and_check() {
+        let iocs = set(&format!(
+            "# sweep\n{}\nmd5:{}\nfilename:Mimikatz.exe\npath:(?i)/tmp/\\.hidden/\n",
+            ABC_SHA1.to_uppercase(),
+            ABC_MD5
+        ));
+        assert_eq!(iocs.len(), 4);
+        assert!(iocs.needs_md5());
+        assert!(!iocs.needs_sha256());
+
+        let item = CsvLogItem::with_paths("/tmp/.hidden/mimikatz.exe", "tmp/.hidden/mimikatz.exe")
+            .with_hash(ABC_SHA1.to_string());
+        let digests = ExtraDigests {
+            md5: Some(ABC_MD5.to_string()),
+            sha256: None,
+        };
+        let ids: Vec<_> = iocs
+            .check(&item, &digests)
+            .iter()
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["bad:2", "bad:3", "bad:4", "bad:5"]);
+
+        let clean = CsvLogItem::with_paths("/usr/bin/ls", "usr/bin/ls").with_hash("00".repeat(20));
+        assert!(iocs.check(&clean, &ExtraDigests::default()).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_indicator() {
+        let mut iocs = IocSet::default();
+        let err = iocs.parse(Path::new("bad.txt"), "sha1:1234\n").unwrap_err();
+        assert!(err.to_string().contains(":1"));
+        assert!(
+            iocs.parse(Path::new("bad.txt"), "path:(unclosed\n")
+                .is_err()
+        );
+        assert!(iocs.parse(Path::new("bad.txt"), "evil.exe\n").is_err());
+    }
+
+    #[test]
+    fn test_digest_writer() {
+        let iocs = set(&format!("md5:{}\n", ABC_MD5));
+        let mut writer = DigestWriter::new(Vec::new(), Some(&iocs));
+        writer.write_all(b"abc").unwrap();
+        let (data, digests) = writer.finish();
+
+        assert_eq!(data, b"abc");
+        assert_eq!(digests.md5.as_deref(), Some(ABC_MD5));
+        assert_eq!(digests.sha256, None);
+    }
+}
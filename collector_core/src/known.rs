@@ -0,0 +1,291 @@
+//! Known-good hash sets (plain SHA-1 lists or NSRL RDS SQLite databases)
+//! used to leave vendor files out of a collection, or to flag them.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CollectorError, Result};
+
+/// Status prefix of files found in a known-good hash set.
+pub const KNOWN_PREFIX: &str = "known: ";
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// What to do with a file whose hash is in a known-good set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KnownAction {
+    /// List the file in the manifest without copying it
+    #[default]
+    Skip,
+    /// Copy the file and flag it in the manifest
+    Mark,
+}
+
+impl fmt::Display for KnownAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KnownAction::Skip => "skip",
+            KnownAction::Mark => "mark",
+        })
+    }
+}
+
+impl FromStr for KnownAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(KnownAction::Skip),
+            "mark" => Ok(KnownAction::Mark),
+            other => Err(format!(
+                "unknown action '{}' (expected skip or mark)",
+                other
+            )),
+        }
+    }
+}
+
+enum HashSource {
+    List(HashSet<String>),
+    /// NSRL RDS v3 database, queried per file
+    Rds(Connection),
+}
+
+/// A single hash set, named after its file.
+pub struct KnownHashSet {
+    name: String,
+    path: PathBuf,
+    source: HashSource,
+}
+
+impl KnownHashSet {
+    /// Load a hash set. SQLite files are read as NSRL RDS v3 databases,
+    /// anything else as a list with one SHA-1 per line (the first field of
+    /// `sha1sum` output or of the legacy `NSRLFile.txt` also works).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+        let source = if is_sqlite(&path)? {
+            HashSource::Rds(open_rds(&path)?)
+        } else {
+            HashSource::List(read_list(&path)?)
+        };
+
+        Ok(Self { name, path, source })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the set contains the SHA-1 `hash`.
+    pub fn contains(&self, hash: &str) -> Result<bool> {
+        match self.source {
+            HashSource::List(ref hashes) => Ok(hashes.contains(&hash.to_ascii_lowercase())),
+            HashSource::Rds(ref connection) => connection
+                .query_row(
+                    "SELECT 1 FROM FILE WHERE sha1 = ?1 LIMIT 1",
+                    [hash.to_ascii_uppercase()],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map(|found| found.is_some())
+                .map_err(|e| hash_set_error(&self.path, e)),
+        }
+    }
+}
+
+/// Known-good hash sets and the action applied to matching files.
+#[derive(Default)]
+pub struct KnownFiles {
+    sets: Vec<KnownHashSet>,
+    action: KnownAction,
+}
+
+impl KnownFiles {
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let sets = paths
+            .iter()
+            .map(KnownHashSet::load)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            sets,
+            action: KnownAction::default(),
+        })
+    }
+
+    pub fn with_action(mut self, action: KnownAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn action(&self) -> KnownAction {
+        self.action
+    }
+
+    pub fn sets(&self) -> &[KnownHashSet] {
+        &self.sets
+    }
+
+    /// Name of the first set containing `hash`.
+    pub fn lookup(&self, hash: &str) -> Result<Option<&str>> {
+        for set in &self.sets {
+            if set.contains(hash)? {
+                return Ok(Some(set.name()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn hash_set_error<E: fmt::Display>(path: &Path, e: E) -> CollectorError {
+    CollectorError::HashSet {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    }
+}
+
+fn is_sqlite(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 16];
+    let mut file = std::fs::File::open(path).map_err(|e| CollectorError::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    Ok(file.read_exact(&mut magic).is_ok() && magic == SQLITE_MAGIC)
+}
+
+fn open_rds(path: &Path) -> Result<Connection> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| hash_set_error(path, e))?;
+
+    let has_file_table: bool = connection
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'FILE'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count > 0)
+        .map_err(|e| hash_set_error(path, e))?;
+    if !has_file_table {
+        return Err(hash_set_error(
+            path,
+            "no FILE table (expected an NSRL RDS v3 database)",
+        ));
+    }
+
+    let indexed: i64 = connection
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = 'FILE' AND sql LIKE '%sha1%'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    if indexed == 0 {
+        log::warn!(
+            "{} has no index on FILE.sha1, lookups will be slow",
+            path.display()
+        );
+    }
+
+    Ok(connection)
+}
+
+fn read_list(path: &Path) -> Result<HashSet<String>> {
+    let data = std::fs::read_to_string(path).map_err(|e| CollectorError::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    let mut hashes = HashSet::new();
+    let mut ignored = 0usize;
+    for line in data.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let field = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .trim_matches('"');
+
+        if field.len() == 40 && field.bytes().all(|b| b.is_ascii_hexdigit()) {
+            hashes.insert(field.to_ascii_lowercase());
+        } else {
+            ignored += 1;
+        }
+    }
+
+    if ignored > 0 {
+        log::warn!(
+            "{}: ignored {} lines without a SHA-1",
+            path.display(),
+            ignored
+        );
+    }
+    log::info!("Loaded {} hashes from {}", hashes.len(), path.display());
+    Ok(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+
+    #[test]
+    fn test_plain_hash_list() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("vendor.txt");
+        std::fs::write(
+            &path,
+            format!(
+                "# vendor binaries\n\"SHA-1\",\"MD5\"\n\"{}\",\"x\"\n{}  /usr/bin/ls\n",
+                ABC_SHA1.to_uppercase(),
+                "0".repeat(40)
+            ),
+        )
+        .unwrap();
+
+        let known = KnownFiles::load(&[&path]).unwrap();
+        assert_eq!(known.lookup(ABC_SHA1).unwrap(), Some("vendor"));
+        assert_eq!(known.lookup(&"0".repeat(40)).unwrap(), Some("vendor"));
+        assert_eq!(known.lookup(&"1".repeat(40)).unwrap(), None);
+        assert_eq!(known.action(), KnownAction::Skip);
+    }
+
+    #[test]
+    fn test_rds_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("RDS_modern_minimal.db");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(&format!(
+                "CREATE TABLE FILE (sha256 TEXT, sha1 TEXT, md5 TEXT, crc32 TEXT, file_name TEXT, file_size INTEGER, package_id INTEGER);
+                 CREATE INDEX FILE_SHA1 ON FILE(sha1);
+                 INSERT INTO FILE (sha1, file_name, file_size) VALUES ('{}', 'abc', 3);",
+                ABC_SHA1.to_uppercase()
+            ))
+            .unwrap();
+        drop(connection);
+
+        let known = KnownFiles::load(&[&path])
+            .unwrap()
+            .with_action(KnownAction::Mark);
+        assert_eq!(known.lookup(ABC_SHA1).unwrap(), Some("RDS_modern_minimal"));
+        assert_eq!(known.lookup(&"1".repeat(40)).unwrap(), None);
+        assert_eq!("mark".parse::<KnownAction>().unwrap(), known.action());
+    }
+}
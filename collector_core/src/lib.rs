@@ -1,8 +1,24 @@
+pub mod cancel;
+pub mod case;
 pub mod csv;
+pub mod dfxml;
+pub mod diff;
 pub mod error;
+pub mod event;
+pub mod failure;
+pub mod ioc;
+pub mod known;
+pub mod manifest;
 pub mod platform;
 pub mod resource;
+pub mod scan;
+pub mod sink;
+pub mod summary;
+pub mod throttle;
+pub mod timeline;
+pub mod uco;
 pub mod utils;
+pub mod verify;
 pub mod writer;
 
 mod extract;
@@ -11,10 +27,23 @@ mod extract;
 pub mod mount;
 
 pub mod prelude {
+    pub use crate::cancel::CancelToken;
+    pub use crate::case::{CaseInfo, CaseMetadata};
     pub use crate::csv::{CsvLogFile, CsvLogItem};
-    pub use crate::error::{CollectorError, Result};
-    pub use crate::platform::{ArtifactCollector, CollectionStats, VssCollector};
+    pub use crate::error::{CollectorError, ErrorCategory, Result};
+    pub use crate::event::{CollectionEvent, EventHandler, SkipReason};
+    pub use crate::ioc::IocSet;
+    pub use crate::known::{KnownAction, KnownFiles};
+    pub use crate::platform::{
+        ArtifactCollector, CollectionStats, InventoryOptions, PreviousCollection, VssCollector,
+    };
     pub use crate::resource::{ResourcesParser, YamlArtifact, YamlParser};
+    pub use crate::scan::RuleSet;
+    pub use crate::sink::{
+        ArchiveFormat, ArchiveOptions, DirectorySink, EntryMetadata, OutputSink, StreamSink,
+        TarSink, ZipSink,
+    };
+    pub use crate::throttle::ThrottleOptions;
     pub use crate::utils::{FormatSource, is_admin, require_admin};
     pub use crate::writer::Writer;
 }
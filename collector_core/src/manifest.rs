@@ -0,0 +1,584 @@
+//! Alternative manifest formats. The CSV manifest stays the sealed record of
+//! the collection; the JSON Lines and SQLite manifests follow it row by row,
+//! with the source metadata and the artifact each file was found by.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+
+use crate::case::CaseMetadata;
+use crate::csv::CsvLogItem;
+use crate::dfxml::{ByteRun, iso_time};
+use crate::error::{CollectorError, Result};
+use crate::failure::{ExtractionMethod, FileFailure};
+use crate::timeline::TimelineEntry;
+
+/// Manifest format. The CSV manifest is always written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    #[default]
+    Csv,
+    /// One JSON object per file, with nested metadata
+    Jsonl,
+    /// SQLite database with files, artifacts, errors and run tables
+    Sqlite,
+}
+
+impl fmt::Display for ManifestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ManifestFormat::Csv => "csv",
+            ManifestFormat::Jsonl => "jsonl",
+            ManifestFormat::Sqlite => "sqlite",
+        })
+    }
+}
+
+impl FromStr for ManifestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ManifestFormat::Csv),
+            "jsonl" => Ok(ManifestFormat::Jsonl),
+            "sqlite" | "db" => Ok(ManifestFormat::Sqlite),
+            other => Err(format!(
+                "unknown manifest format '{}' (expected csv, jsonl or sqlite)",
+                other
+            )),
+        }
+    }
+}
+
+/// Artifacts (resource patterns or inventory roots) of a run and the one
+/// each source file was found by.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactIndex {
+    names: Vec<String>,
+    by_source: HashMap<String, usize>,
+}
+
+impl ArtifactIndex {
+    /// Register an artifact, even if it matches no file.
+    pub fn add_artifact(&mut self, artifact: &str) -> usize {
+        match self.names.iter().position(|name| name == artifact) {
+            Some(index) => index,
+            None => {
+                self.names.push(artifact.to_string());
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Record the artifact a source file was found by. The first one wins
+    /// when patterns overlap.
+    pub fn add_file(&mut self, artifact: &str, source_file: &str) {
+        let index = self.add_artifact(artifact);
+        self.by_source
+            .entry(source_file.to_string())
+            .or_insert(index);
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Artifact of a source file.
+    pub fn get(&self, source_file: &str) -> Option<&str> {
+        self.by_source
+            .get(source_file)
+            .map(|&index| self.names[index].as_str())
+    }
+}
+
+/// Manifest row with typed and nested metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestRecord {
+    pub collect_time: String,
+    pub artifact: Option<String>,
+    pub source: SourceRecord,
+    pub destination: Option<String>,
+    /// Digests by algorithm
+    pub hashes: HashMap<&'static str, String>,
+    /// How the content was read, for copied files
+    pub extraction: Option<ExtractionRecord>,
+    pub status: Option<String>,
+    pub ioc: Vec<String>,
+    pub rules: Vec<String>,
+    pub chain_hash: String,
+}
+
+/// Source file metadata, times in RFC 3339.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceRecord {
+    pub path: String,
+    pub size: u64,
+    pub inode: Option<String>,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub modified: Option<String>,
+    pub accessed: Option<String>,
+    pub changed: Option<String>,
+    pub created: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtractionRecord {
+    pub method: ExtractionMethod,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub byte_runs: Vec<ByteRun>,
+}
+
+impl ManifestRecord {
+    /// Record of a row, with the metadata read when it was collected if
+    /// known, otherwise the times stored in the row.
+    pub fn new(item: &CsvLogItem, entry: Option<&TimelineEntry>, artifact: Option<&str>) -> Self {
+        let fallback;
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                fallback = TimelineEntry::from_item(item);
+                &fallback
+            }
+        };
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
+        let mut hashes = HashMap::new();
+        if !item.hash_sha1.is_empty() {
+            hashes.insert("sha1", item.hash_sha1.clone());
+        }
+
+        Self {
+            collect_time: item.collect_time.clone(),
+            artifact: artifact.map(str::to_string),
+            source: SourceRecord {
+                path: item.source_file.clone(),
+                size: item.file_size,
+                inode: non_empty(&item.inode),
+                mode: entry.mode,
+                uid: entry.uid,
+                gid: entry.gid,
+                modified: entry.modified.and_then(iso_time),
+                accessed: entry.accessed.and_then(iso_time),
+                changed: entry.changed.and_then(iso_time),
+                created: entry.created.and_then(iso_time),
+            },
+            destination: non_empty(&item.destination_file),
+            hashes,
+            extraction: item.is_copied().then(|| ExtractionRecord {
+                method: if item.from_ntfs {
+                    ExtractionMethod::Ntfs
+                } else {
+                    ExtractionMethod::Filesystem
+                },
+                byte_runs: entry.byte_runs.clone(),
+            }),
+            status: non_empty(&item.status),
+            ioc: item.ioc_ids().map(str::to_string).collect(),
+            rules: item.rule_names().map(str::to_string).collect(),
+            chain_hash: item.chain_hash.clone(),
+        }
+    }
+}
+
+/// Append a record to a JSON Lines manifest.
+pub fn write_jsonl_record<W: Write>(out: &mut W, record: &ManifestRecord) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE run (key TEXT PRIMARY KEY, value TEXT);
+    CREATE TABLE artifacts (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+    CREATE TABLE files (
+        id INTEGER PRIMARY KEY,
+        collect_time TEXT NOT NULL,
+        artifact_id INTEGER REFERENCES artifacts(id),
+        source_file TEXT NOT NULL,
+        destination_file TEXT,
+        size INTEGER NOT NULL,
+        inode TEXT,
+        mode INTEGER,
+        uid INTEGER,
+        gid INTEGER,
+        modified TEXT,
+        accessed TEXT,
+        changed TEXT,
+        created TEXT,
+        extraction TEXT,
+        status TEXT,
+        chain_hash TEXT NOT NULL
+    );
+    CREATE TABLE hashes (
+        file_id INTEGER NOT NULL REFERENCES files(id),
+        algorithm TEXT NOT NULL,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE matches (
+        file_id INTEGER NOT NULL REFERENCES files(id),
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE byte_runs (
+        file_id INTEGER NOT NULL REFERENCES files(id),
+        file_offset INTEGER NOT NULL,
+        img_offset INTEGER NOT NULL,
+        len INTEGER NOT NULL
+    );
+    CREATE TABLE errors (
+        id INTEGER PRIMARY KEY,
+        artifact_id INTEGER REFERENCES artifacts(id),
+        source_file TEXT NOT NULL,
+        error TEXT NOT NULL,
+        code INTEGER NOT NULL,
+        os_error INTEGER,
+        reason TEXT NOT NULL,
+        methods TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX files_source_file ON files(source_file);
+    CREATE INDEX hashes_value ON hashes(value);
+";
+
+/// SQLite manifest, filled row by row in one transaction and completed with
+/// the run, artifacts and errors at the end.
+pub struct SqliteManifest {
+    conn: Connection,
+    path: PathBuf,
+    /// Artifacts inserted so far, ahead of the files referring to them
+    artifacts: usize,
+}
+
+impl SqliteManifest {
+    /// Create the database at `path`, replacing any left by an interrupted
+    /// run.
+    pub fn create(path: PathBuf) -> Result<Self> {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(CollectorError::FileWrite { path, source: e });
+            }
+            _ => {}
+        }
+        let conn = Connection::open(&path).map_err(database_error)?;
+        // Rebuilt by a resumed run, so no rollback journal next to it
+        conn.execute_batch("PRAGMA journal_mode = OFF; BEGIN;")
+            .and_then(|_| conn.execute_batch(SQLITE_SCHEMA))
+            .map_err(database_error)?;
+        Ok(Self {
+            conn,
+            path,
+            artifacts: 0,
+        })
+    }
+
+    /// Insert a record.
+    pub fn add(&mut self, record: &ManifestRecord, artifacts: &ArtifactIndex) -> Result<()> {
+        self.insert_artifacts(artifacts)?;
+        insert_record(&self.conn, record, artifacts).map_err(database_error)
+    }
+
+    fn insert_artifacts(&mut self, artifacts: &ArtifactIndex) -> Result<()> {
+        for (index, name) in artifacts.names().iter().enumerate().skip(self.artifacts) {
+            self.conn
+                .execute(
+                    "INSERT INTO artifacts (id, name) VALUES (?1, ?2)",
+                    params![index as i64 + 1, name],
+                )
+                .map_err(database_error)?;
+            self.artifacts = index + 1;
+        }
+        Ok(())
+    }
+
+    /// Write the run and the errors, and close the database, returning its
+    /// location.
+    pub fn finish(
+        mut self,
+        case: &CaseMetadata,
+        artifacts: &ArtifactIndex,
+        failures: &[FileFailure],
+    ) -> Result<PathBuf> {
+        self.insert_artifacts(artifacts)?;
+        insert_run(&self.conn, case, artifacts, failures)
+            .and_then(|_| self.conn.execute_batch("COMMIT"))
+            .map_err(database_error)?;
+        self.conn.close().map_err(|(_, e)| database_error(e))?;
+        Ok(self.path)
+    }
+}
+
+fn artifact_id(artifacts: &ArtifactIndex, name: &Option<String>) -> Option<i64> {
+    name.as_ref().and_then(|name| {
+        artifacts
+            .names()
+            .iter()
+            .position(|n| n == name)
+            .map(|index| index as i64 + 1)
+    })
+}
+
+fn insert_record(
+    conn: &Connection,
+    record: &ManifestRecord,
+    artifacts: &ArtifactIndex,
+) -> rusqlite::Result<()> {
+    let source = &record.source;
+    conn.execute(
+        "INSERT INTO files (collect_time, artifact_id, source_file, destination_file, size,
+            inode, mode, uid, gid, modified, accessed, changed, created, extraction, status,
+            chain_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            record.collect_time,
+            artifact_id(artifacts, &record.artifact),
+            source.path,
+            record.destination,
+            source.size as i64,
+            source.inode,
+            source.mode,
+            source.uid,
+            source.gid,
+            source.modified,
+            source.accessed,
+            source.changed,
+            source.created,
+            record.extraction.as_ref().map(|e| e.method.as_str()),
+            record.status,
+            record.chain_hash,
+        ],
+    )?;
+    let file_id = conn.last_insert_rowid();
+
+    for (algorithm, value) in &record.hashes {
+        conn.execute(
+            "INSERT INTO hashes (file_id, algorithm, value) VALUES (?1, ?2, ?3)",
+            params![file_id, algorithm, value],
+        )?;
+    }
+    let matches = record.ioc.iter().map(|id| ("ioc", id));
+    for (kind, name) in matches.chain(record.rules.iter().map(|name| ("rule", name))) {
+        conn.execute(
+            "INSERT INTO matches (file_id, kind, name) VALUES (?1, ?2, ?3)",
+            params![file_id, kind, name],
+        )?;
+    }
+    for run in record.extraction.iter().flat_map(|e| &e.byte_runs) {
+        conn.execute(
+            "INSERT INTO byte_runs (file_id, file_offset, img_offset, len)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                file_id,
+                run.file_offset as i64,
+                run.img_offset as i64,
+                run.len as i64
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn insert_run(
+    conn: &Connection,
+    case: &CaseMetadata,
+    artifacts: &ArtifactIndex,
+    failures: &[FileFailure],
+) -> rusqlite::Result<()> {
+    let mut run = vec![
+        ("tool", Some(case.tool.name.clone())),
+        ("version", Some(case.tool.version.clone())),
+        ("command_line", Some(case.tool.command_line.join(" "))),
+        ("hostname", case.host.hostname.clone()),
+        ("os_name", case.host.os_name.clone()),
+        ("os_version", case.host.os_version.clone()),
+        ("case_number", case.case.case_number.clone()),
+        ("examiner", case.case.examiner.clone()),
+        ("notes", case.case.notes.clone()),
+        ("started_at", Some(case.started_at.clone())),
+        ("finished_at", case.finished_at.clone()),
+        ("resources", serde_json::to_string(&case.resources).ok()),
+    ];
+    if let Some(ref stats) = case.stats {
+        run.push(("stats", serde_json::to_string(stats).ok()));
+    }
+    if case.cancelled {
+        run.push(("cancelled", Some("true".to_string())));
+    }
+    for (key, value) in run {
+        conn.execute(
+            "INSERT INTO run (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+    }
+
+    for failure in failures {
+        let methods: Vec<&str> = failure.methods.iter().map(|m| m.as_str()).collect();
+        conn.execute(
+            "INSERT INTO errors
+             (artifact_id, source_file, error, code, os_error, reason, methods, message)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                artifact_id(artifacts, &failure.artifact),
+                failure.source_file,
+                failure.error,
+                failure.code,
+                failure.os_error,
+                failure.reason(),
+                methods.join(";"),
+                failure.message,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn database_error(e: rusqlite::Error) -> CollectorError {
+    CollectorError::ManifestDatabase(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::case::CaseInfo;
+
+    fn sample() -> (Vec<CsvLogItem>, ArtifactIndex) {
+        let copied = CsvLogItem::with_paths("/var/log/syslog", "var/log/syslog")
+            .with_hash("ab".repeat(20))
+            .with_size(10)
+            .with_inode(Some(7))
+            .with_rules(["Suspicious"]);
+        let skipped = CsvLogItem::with_paths("/var/log/old.log", "")
+            .with_timestamps(
+                filetime::FileTime::from_unix_time(1_700_000_000, 0).to_string(),
+                String::new(),
+            )
+            .with_status("unchanged since run")
+            .with_ioc(["ioc-1", "ioc-2"]);
+
+        let mut artifacts = ArtifactIndex::default();
+        artifacts.add_artifact("/etc/*");
+        artifacts.add_file("/var/log/*", "/var/log/syslog");
+        artifacts.add_file("/var/log/*", "/var/log/old.log");
+        (vec![copied, skipped], artifacts)
+    }
+
+    #[test]
+    fn test_jsonl_records() {
+        let (items, artifacts) = sample();
+        let entry = TimelineEntry {
+            source_file: "/var/log/syslog".to_string(),
+            mode: Some(0o100640),
+            ..Default::default()
+        };
+
+        let mut data = Vec::new();
+        for item in &items {
+            let entry = (item.source_file == entry.source_file).then_some(&entry);
+            let record = ManifestRecord::new(item, entry, artifacts.get(&item.source_file));
+            write_jsonl_record(&mut data, &record).unwrap();
+        }
+        let lines: Vec<serde_json::Value> = data
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["artifact"], "/var/log/*");
+        assert_eq!(lines[0]["source"]["mode"], 0o100640);
+        assert_eq!(lines[0]["source"]["inode"], "7");
+        assert_eq!(lines[0]["hashes"]["sha1"], "ab".repeat(20));
+        assert_eq!(lines[0]["extraction"]["method"], "filesystem");
+        assert_eq!(lines[0]["rules"][0], "Suspicious");
+        assert!(lines[1]["destination"].is_null());
+        assert!(lines[1]["extraction"].is_null());
+        assert_eq!(lines[1]["ioc"].as_array().unwrap().len(), 2);
+        assert!(lines[1]["source"]["modified"].is_string());
+        assert!(lines[1]["source"]["accessed"].is_null());
+    }
+
+    #[test]
+    fn test_sqlite_manifest() {
+        let (items, artifacts) = sample();
+        let failures = vec![FileFailure::new(
+            "/var/log/locked".to_string(),
+            Some("/var/log/*".to_string()),
+            &CollectorError::FileRead {
+                path: "/var/log/locked".into(),
+                source: std::io::Error::from_raw_os_error(13),
+            },
+            vec![ExtractionMethod::Filesystem],
+        )];
+        let case = CaseMetadata::new(CaseInfo::new().with_case_number(Some("C-7".to_string())));
+
+        // Left by an interrupted run
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.sqlite");
+        std::fs::write(&path, b"torn").unwrap();
+
+        let mut database = SqliteManifest::create(path.clone()).unwrap();
+        for item in &items {
+            let record = ManifestRecord::new(item, None, artifacts.get(&item.source_file));
+            database.add(&record, &artifacts).unwrap();
+        }
+        assert_eq!(database.finish(&case, &artifacts, &failures).unwrap(), path);
+        assert!(!dir.path().join("manifest.sqlite-journal").exists());
+        let conn = Connection::open(&path).unwrap();
+
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM files"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM artifacts"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM matches WHERE kind = 'ioc'"), 2);
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM files JOIN hashes ON hashes.file_id = files.id
+                 WHERE hashes.value = 'abababababababababababababababababababab'"
+            ),
+            1
+        );
+        let (artifact, error, code, os_error, methods): (String, String, i64, i64, String) = conn
+            .query_row(
+                "SELECT artifacts.name, error, code, os_error, methods
+                 FROM errors JOIN artifacts ON artifacts.id = errors.artifact_id",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(artifact, "/var/log/*");
+        assert_eq!(error, "FileRead");
+        assert_eq!(code, 701);
+        assert_eq!(os_error, 13);
+        assert_eq!(methods, "filesystem");
+        let case_number: String = conn
+            .query_row(
+                "SELECT value FROM run WHERE key = 'case_number'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(case_number, "C-7");
+    }
+
+    #[test]
+    fn test_manifest_format_from_str() {
+        assert_eq!("JSONL".parse::<ManifestFormat>(), Ok(ManifestFormat::Jsonl));
+        assert_eq!(
+            "sqlite".parse::<ManifestFormat>(),
+            Ok(ManifestFormat::Sqlite)
+        );
+        assert!("xml".parse::<ManifestFormat>().is_err());
+    }
+}
@@ -1,22 +1,86 @@
-use std::io::Read;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use filetime::FileTime;
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
 use glob::glob;
-use sha1::{Digest, Sha1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use tokio::fs::{self, File};
 use tokio::task::spawn_blocking;
 
+use crate::cancel::{CancelToken, CancelWriter};
+use crate::case::CaseMetadata;
+use crate::csv::seal::{ManifestSeal, SealMetadata};
 use crate::csv::{CsvLogFile, CsvLogItem};
+use crate::dfxml::ByteRun;
 use crate::error::{CollectorError, Result};
-use crate::extract::extract_file;
-use crate::utils::{FormatSource, HASH_BUFFER_SIZE, require_admin};
+use crate::event::{ArtifactPlan, CollectionEvent, EventHandler, SkipReason};
+use crate::extract::extract_via_filesystem;
+#[cfg(target_os = "windows")]
+use crate::extract::extract_via_ntfs;
+use crate::failure::{ExtractionMethod, FAILED_PREFIX, FailureReport, FileFailure};
+use crate::ioc::{DigestWriter, ExtraDigests, IocReport, IocSet};
+use crate::known::{KNOWN_PREFIX, KnownAction, KnownFiles};
+use crate::manifest::{ArtifactIndex, ManifestFormat};
+use crate::platform::exports::{ExportOptions, Exports};
+use crate::platform::incremental::{PreviousCollection, UNCHANGED_PREFIX, copied_in};
+use crate::platform::inventory::{INVENTORY_STATUS, InventoryOptions, walk_files};
+use crate::platform::journal::{JOURNAL_NAME, Journal, is_intact};
+use crate::scan::{NO_MATCH_STATUS, RuleMatch, RuleSet, ScanFinding, ScanReport, ScanWriter};
+use crate::sink::{
+    ArchiveOptions, DirectorySink, EntryMetadata, FixedSizeWriter, HashingWriter, OutputSink,
+    Resized, copy_buffered, entry_name,
+};
+use crate::throttle::{Throttle, ThrottleOptions, Throttled};
+use crate::timeline::TimelineEntry;
+use crate::utils::{FormatSource, require_admin};
 use crate::writer::Writer;
 
 #[cfg(target_os = "windows")]
 use crate::mount::VssSnapshot;
 
+/// Name of the manifest inside the collection output
+pub const MANIFEST_NAME: &str = "Collector_copy.csv";
+
+/// Name of the manifest seal written next to the manifest
+pub const SEAL_NAME: &str = "Collector_copy.seal.json";
+
+/// Name of the case metadata document
+pub const CASE_METADATA_NAME: &str = "Collector_case.json";
+
+/// Name of the indicator of compromise findings report
+pub const FINDINGS_NAME: &str = "Collector_findings.json";
+
+/// Name of the rule scan findings report
+pub const SCAN_FINDINGS_NAME: &str = "Collector_scan.json";
+
+/// Name of the TSK 3.x bodyfile of the source metadata
+pub const BODYFILE_NAME: &str = "Collector_timeline.body";
+
+/// Name of the Timesketch JSONL timeline of the source metadata
+pub const TIMELINE_NAME: &str = "Collector_timeline.jsonl";
+
+/// Name of the DFXML description of the collection
+pub const DFXML_NAME: &str = "Collector_dfxml.xml";
+
+/// Name of the CASE/UCO JSON-LD provenance of the collection
+pub const CASE_UCO_NAME: &str = "Collector_case_uco.jsonld";
+
+/// Name of the JSON Lines manifest
+pub const JSONL_MANIFEST_NAME: &str = "Collector_copy.jsonl";
+
+/// Name of the SQLite manifest
+pub const SQLITE_MANIFEST_NAME: &str = "Collector_copy.sqlite";
+
+/// Name of the report of files that could not be collected
+pub const FAILURES_NAME: &str = "Collector_failures.json";
+
 /// Collection statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CollectionStats {
     pub files_collected: u64,
     pub bytes_collected: u64,
@@ -24,6 +88,26 @@ pub struct CollectionStats {
     pub ntfs_extractions: u64,
     pub failed_extractions: u64,
     pub patterns_processed: u64,
+    /// Files skipped because they did not change since the previous collection
+    pub files_unchanged: u64,
+    /// Files already copied by an interrupted run and kept on resume
+    pub files_resumed: u64,
+    /// Files found in a known-good hash set, skipped or flagged
+    pub files_known: u64,
+    /// Files matching at least one indicator of compromise
+    pub ioc_matches: u64,
+    /// Files matching at least one scan rule
+    pub rule_matches: u64,
+    /// Large files left out because they matched no scan rule
+    pub files_filtered: u64,
+    /// Files listed by an inventory, without content
+    pub files_inventoried: u64,
+    /// Total size of the files listed by an inventory
+    pub bytes_inventoried: u64,
+    /// Bytes read to hash inventoried files
+    pub bytes_hashed: u64,
+    /// Time spent walking and collecting, in milliseconds
+    pub elapsed_ms: u64,
 }
 
 impl CollectionStats {
@@ -35,6 +119,24 @@ impl CollectionStats {
         self.ntfs_extractions += other.ntfs_extractions;
         self.failed_extractions += other.failed_extractions;
         self.patterns_processed += other.patterns_processed;
+        self.files_unchanged += other.files_unchanged;
+        self.files_resumed += other.files_resumed;
+        self.files_known += other.files_known;
+        self.ioc_matches += other.ioc_matches;
+        self.rule_matches += other.rule_matches;
+        self.files_filtered += other.files_filtered;
+        self.files_inventoried += other.files_inventoried;
+        self.bytes_inventoried += other.bytes_inventoried;
+        self.bytes_hashed += other.bytes_hashed;
+        self.elapsed_ms += other.elapsed_ms;
+    }
+
+    /// Files processed and bytes read per second
+    pub fn throughput(&self) -> (f64, f64) {
+        let seconds = self.elapsed_ms.max(1) as f64 / 1000.0;
+        let files = self.files_collected + self.files_inventoried;
+        let bytes = self.bytes_collected + self.bytes_hashed;
+        (files as f64 / seconds, bytes as f64 / seconds)
     }
 }
 
@@ -43,8 +145,45 @@ pub struct ArtifactCollector {
     source_directory: FormatSource,
     artifact_patterns: Vec<String>,
     writer: Writer,
+    /// Output of the copies, handed to each copy task while it runs
+    sink: Option<Box<dyn OutputSink>>,
     csv_logger: CsvLogFile,
+    scratch_manifest: Option<PathBuf>,
     stats: CollectionStats,
+    signing_key: Option<SigningKey>,
+    case_metadata: CaseMetadata,
+    previous: Option<PreviousCollection>,
+    known: Option<KnownFiles>,
+    iocs: Option<Arc<IocSet>>,
+    /// Manifest rows flagged by an indicator, for the findings report
+    ioc_rows: Vec<CsvLogItem>,
+    rules: Option<Arc<RuleSet>>,
+    scan_findings: Vec<ScanFinding>,
+    files_scanned: u64,
+    timeline_enabled: bool,
+    /// Exports written row by row, open once collecting starts
+    exports: Option<Exports>,
+    dfxml_enabled: bool,
+    case_uco_enabled: bool,
+    /// Manifest formats written next to the CSV manifest
+    manifest_formats: Vec<ManifestFormat>,
+    artifacts: ArtifactIndex,
+    failures: Vec<FileFailure>,
+    /// Extraction methods tried on the current file
+    attempted: Vec<ExtractionMethod>,
+    /// Entry the sink kept from the current file after it failed
+    partial_entry: Option<String>,
+    events: Vec<Box<dyn EventHandler>>,
+    cancel: CancelToken,
+    /// Whether the last collection stopped before its last file
+    cancelled: bool,
+    throttle: Arc<Throttle>,
+    inventory: Option<InventoryOptions>,
+    rows: u64,
+    journal: Option<Journal>,
+    /// Files restored by `resume`, with their row until it is exported
+    completed: HashMap<String, Option<CsvLogItem>>,
+    sealed: bool,
     #[cfg(target_os = "windows")]
     vss_snapshot: Option<VssSnapshot>,
 }
@@ -56,23 +195,218 @@ impl ArtifactCollector {
         S: Into<PathBuf>,
         D: Into<PathBuf>,
     {
-        let source_path = source.into();
-        let dest_path = destination.into();
+        let writer = Writer::new(destination.into())?;
+        Self::staging(source.into(), writer, patterns, Vec::new()).await
+    }
+
+    /// Continue an interrupted collection in the staging folder. Files listed
+    /// in its journal whose copies still hash as recorded are kept; the
+    /// manifest is rebuilt from them and the remaining files are collected.
+    pub async fn resume<S, D>(source: S, destination: D, patterns: Vec<String>) -> Result<Self>
+    where
+        S: Into<PathBuf>,
+        D: Into<PathBuf>,
+    {
+        let writer = Writer::new(destination.into())?;
+
+        let mut done = Vec::new();
+        for item in Journal::read(writer.get_file_path(JOURNAL_NAME)).await? {
+            if item.status.starts_with(FAILED_PREFIX) {
+                continue;
+            }
+            if item.is_copied() && !is_intact(&writer.get_file_path(&item.destination_file), &item)
+            {
+                log::warn!(
+                    "Copy of {} is damaged, collecting it again",
+                    item.source_file
+                );
+                continue;
+            }
+            done.push(item);
+        }
+        log::info!("Resuming collection: {} files already done", done.len());
+
+        Self::staging(source.into(), writer, patterns, done).await
+    }
 
-        let writer = Writer::new(&dest_path)?;
+    /// Collector writing into the staging folder, starting a fresh manifest
+    /// and journal with the rows of files already done.
+    async fn staging(
+        source_path: PathBuf,
+        writer: Writer,
+        patterns: Vec<String>,
+        done: Vec<CsvLogItem>,
+    ) -> Result<Self> {
         let csv_path = writer.csv_log_path();
-        writer.create_file("Collector_copy.csv").await?;
+        writer.create_file(MANIFEST_NAME).await?;
+        let csv_logger = CsvLogFile::new(&csv_path).await?;
+        let journal = Journal::create(writer.get_file_path(JOURNAL_NAME), &done).await?;
+        let sink = Box::new(DirectorySink::new(writer.clone()));
+
+        let mut collector = Self::assemble(
+            source_path,
+            patterns,
+            writer,
+            sink,
+            csv_logger,
+            None,
+            Some(journal),
+        );
+
+        for item in done {
+            let known = item.status.starts_with(KNOWN_PREFIX);
+            if known {
+                collector.stats.files_known += 1;
+            }
+            if item.is_copied() {
+                collector.stats.files_collected += 1;
+                collector.stats.files_resumed += 1;
+                collector.stats.bytes_collected += item.file_size;
+                if item.from_ntfs {
+                    collector.stats.ntfs_extractions += 1;
+                } else {
+                    collector.stats.filesystem_extractions += 1;
+                }
+            } else if item.status == NO_MATCH_STATUS {
+                collector.stats.files_filtered += 1;
+            } else if item.status == INVENTORY_STATUS {
+                collector.stats.files_inventoried += 1;
+                collector.stats.bytes_inventoried += item.file_size;
+            } else if !known {
+                collector.stats.files_unchanged += 1;
+            }
+            if item.is_copied() || item.status == NO_MATCH_STATUS {
+                collector.files_scanned += 1;
+            }
+            if !item.ioc.is_empty() {
+                collector.stats.ioc_matches += 1;
+                collector.ioc_rows.push(item.clone());
+            }
+            if !item.rules.is_empty() {
+                collector.stats.rule_matches += 1;
+                collector.scan_findings.push(ScanFinding::from_item(&item));
+            }
+            collector.rows += 1;
+            collector.csv_logger.add_row(item.clone()).await?;
+            let mut item = item;
+            item.chain_hash = collector.csv_logger.chain_head().to_string();
+            collector
+                .completed
+                .insert(item.source_file.clone(), Some(item));
+        }
+
+        Ok(collector)
+    }
+
+    /// Create a collector streaming every file straight into an archive,
+    /// without a plaintext staging copy. The manifest is appended as the
+    /// last entry by `finish`.
+    pub async fn new_archive<S, D>(
+        source: S,
+        destination: D,
+        patterns: Vec<String>,
+        options: ArchiveOptions,
+    ) -> Result<Self>
+    where
+        S: Into<PathBuf>,
+        D: Into<PathBuf>,
+    {
+        let destination = destination.into();
+        let sink = Writer::new(&destination)?.create_archive_sink(&options)?;
+        Self::new_with_sink(source, destination, patterns, sink).await
+    }
+
+    /// Create a collector writing every file and the manifest to `sink`,
+    /// e.g. a [`StreamSink`](crate::sink::StreamSink) piping to another host.
+    /// `destination` only holds the scratch manifest until `finish`.
+    pub async fn new_with_sink<S, D>(
+        source: S,
+        destination: D,
+        patterns: Vec<String>,
+        sink: Box<dyn OutputSink>,
+    ) -> Result<Self>
+    where
+        S: Into<PathBuf>,
+        D: Into<PathBuf>,
+    {
+        let source_path = source.into();
+        let writer = Writer::new(destination.into())?;
+        let base = writer.base_destination().to_path_buf();
+        fs::create_dir_all(&base)
+            .await
+            .map_err(|e| CollectorError::DirectoryCreate {
+                path: base,
+                source: e,
+            })?;
+
+        let csv_path = writer.scratch_manifest_path();
+        File::create(&csv_path)
+            .await
+            .map_err(|e| CollectorError::FileWrite {
+                path: csv_path.clone(),
+                source: e,
+            })?;
         let csv_logger = CsvLogFile::new(&csv_path).await?;
 
-        Ok(Self {
+        Ok(Self::assemble(
+            source_path,
+            patterns,
+            writer,
+            sink,
+            csv_logger,
+            Some(csv_path),
+            None,
+        ))
+    }
+
+    /// Collector state shared by every constructor, writing to `sink`
+    fn assemble(
+        source_path: PathBuf,
+        patterns: Vec<String>,
+        writer: Writer,
+        sink: Box<dyn OutputSink>,
+        csv_logger: CsvLogFile,
+        scratch_manifest: Option<PathBuf>,
+        journal: Option<Journal>,
+    ) -> Self {
+        Self {
             source_directory: FormatSource::new(source_path),
             artifact_patterns: patterns,
             writer,
+            sink: Some(sink),
             csv_logger,
+            scratch_manifest,
             stats: CollectionStats::default(),
+            signing_key: None,
+            case_metadata: CaseMetadata::default(),
+            previous: None,
+            known: None,
+            iocs: None,
+            ioc_rows: Vec::new(),
+            rules: None,
+            scan_findings: Vec::new(),
+            files_scanned: 0,
+            timeline_enabled: false,
+            exports: None,
+            dfxml_enabled: false,
+            case_uco_enabled: false,
+            manifest_formats: Vec::new(),
+            artifacts: ArtifactIndex::default(),
+            failures: Vec::new(),
+            attempted: Vec::new(),
+            partial_entry: None,
+            events: Vec::new(),
+            cancel: CancelToken::default(),
+            cancelled: false,
+            throttle: Arc::default(),
+            inventory: None,
+            rows: 0,
+            journal,
+            completed: HashMap::new(),
+            sealed: false,
             #[cfg(target_os = "windows")]
             vss_snapshot: None,
-        })
+        }
     }
 
     /// Set VSS snapshot for Windows
@@ -82,6 +416,118 @@ impl ArtifactCollector {
         self
     }
 
+    /// Sign the manifest seal with an Ed25519 key
+    pub fn with_signing_key(mut self, key: Option<SigningKey>) -> Self {
+        self.signing_key = key;
+        self
+    }
+
+    /// Record case details in the case metadata document
+    pub fn with_case_metadata(mut self, metadata: CaseMetadata) -> Self {
+        self.case_metadata = metadata;
+        self
+    }
+
+    /// Skip files unchanged since a previous collection
+    pub fn with_previous_collection(mut self, previous: Option<PreviousCollection>) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    /// Skip or flag files found in known-good hash sets
+    pub fn with_known_files(mut self, known: Option<KnownFiles>) -> Self {
+        self.known = known;
+        self
+    }
+
+    /// Flag files matching indicators of compromise and write a findings
+    /// report
+    pub fn with_ioc_set(mut self, iocs: Option<IocSet>) -> Self {
+        self.iocs = iocs.map(Arc::new);
+        self
+    }
+
+    /// Scan collected content with rules and write a scan findings report
+    pub fn with_rules(mut self, rules: Option<RuleSet>) -> Self {
+        self.rules = rules.map(Arc::new);
+        self
+    }
+
+    /// Write a bodyfile and a Timesketch timeline of the source metadata
+    pub fn with_timeline(mut self, enabled: bool) -> Self {
+        self.timeline_enabled = enabled;
+        self
+    }
+
+    /// Write a DFXML description of the collection
+    pub fn with_dfxml(mut self, enabled: bool) -> Self {
+        self.dfxml_enabled = enabled;
+        self
+    }
+
+    /// Write a CASE/UCO JSON-LD provenance of the collection
+    pub fn with_case_uco(mut self, enabled: bool) -> Self {
+        self.case_uco_enabled = enabled;
+        self
+    }
+
+    /// Also write the manifest as JSON Lines or SQLite. The CSV manifest is
+    /// always written, it is the one sealed and verified.
+    pub fn with_manifest_formats(mut self, formats: Vec<ManifestFormat>) -> Self {
+        self.manifest_formats = formats
+            .into_iter()
+            .filter(|format| *format != ManifestFormat::Csv)
+            .collect();
+        self
+    }
+
+    /// List files with their metadata instead of copying them
+    pub fn with_inventory(mut self, inventory: Option<InventoryOptions>) -> Self {
+        self.inventory = inventory;
+        self
+    }
+
+    /// Stop collecting when this token is cancelled. The files done so far
+    /// are kept and the output is sealed as a cancelled collection.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Limit the read rates and lower the priority of the collection. The
+    /// priority is applied to the whole process when collecting starts.
+    pub fn with_throttle(mut self, options: ThrottleOptions) -> Self {
+        self.throttle = Arc::new(Throttle::new(options));
+        self
+    }
+
+    /// Send collection events to this handler, in addition to any added
+    /// before.
+    pub fn with_event_handler<H: EventHandler + 'static>(mut self, handler: H) -> Self {
+        self.events.push(Box::new(handler));
+        self
+    }
+
+    /// Whether the collection was cancelled before its last file
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Files that could not be collected so far
+    pub fn failures(&self) -> &[FileFailure] {
+        &self.failures
+    }
+
+    /// Files matching a scan rule so far
+    pub fn scan_findings(&self) -> &[ScanFinding] {
+        &self.scan_findings
+    }
+
+    /// Manifest rows flagged by an indicator of compromise so far
+    pub fn ioc_findings(&self) -> &[CsvLogItem] {
+        &self.ioc_rows
+    }
+
     /// Get current statistics
     pub fn stats(&self) -> &CollectionStats {
         &self.stats
@@ -92,67 +538,184 @@ impl ArtifactCollector {
         &self.writer
     }
 
+    /// Whether files are streamed into an archive rather than staged on disk
+    pub fn is_direct_archive(&self) -> bool {
+        self.scratch_manifest.is_some()
+    }
+
     /// Count total files matching all patterns (before collection)
     pub fn count_files(&self) -> u64 {
         self.get_all_files().len() as u64
     }
 
-    /// Get all files matching patterns
-    fn get_all_files(&self) -> Vec<PathBuf> {
+    /// Get all files matching patterns, or under the inventory roots, with
+    /// the pattern or root that found them
+    fn get_all_files(&self) -> Vec<(String, PathBuf)> {
         let mut files = Vec::new();
 
+        if let Some(ref inventory) = self.inventory
+            && !inventory.roots.is_empty()
+        {
+            for root in &inventory.roots {
+                let artifact = root.to_string_lossy();
+                let normalized = artifact.trim_start_matches('\\').trim_start_matches('/');
+                let root_path = self.source_directory.join(normalized);
+                let found = walk_files(root_path.as_path(), &self.cancel);
+                files.extend(found.into_iter().map(|file| (artifact.to_string(), file)));
+            }
+            return files;
+        }
+
         for pattern in &self.artifact_patterns {
             let normalized = pattern.trim_start_matches('\\').trim_start_matches('/');
             let source_pattern = self.source_directory.join(normalized).to_string_lossy();
 
             if let Ok(entries) = glob(&source_pattern) {
-                files.extend(entries.filter_map(|e| e.ok()).filter(|p| p.is_file()));
+                files.extend(
+                    entries
+                        .take_while(|_| !self.cancel.is_cancelled())
+                        .filter_map(|e| e.ok())
+                        .filter(|p| p.is_file())
+                        .map(|file| (pattern.clone(), file)),
+                );
             }
         }
 
         files
     }
 
-    /// Collect all artifacts (no progress callback)
-    pub async fn collect(&mut self) -> Result<CollectionStats> {
-        self.collect_internal::<fn(u64, u64, &str)>(None).await
-    }
-
-    /// Collect all artifacts with progress callback
-    pub async fn collect_with_progress<F>(&mut self, callback: F) -> Result<CollectionStats>
-    where
-        F: Fn(u64, u64, &str) + Send + Sync + 'static,
-    {
-        self.collect_internal(Some(callback)).await
+    fn emit(&self, event: CollectionEvent) {
+        for handler in &self.events {
+            handler.handle(&event);
+        }
     }
 
-    /// Internal collection implementation
-    async fn collect_internal<F>(&mut self, callback: Option<F>) -> Result<CollectionStats>
-    where
-        F: Fn(u64, u64, &str),
-    {
+    /// Collect all artifacts, reporting progress to the event handlers
+    pub async fn collect(&mut self) -> Result<CollectionStats> {
         require_admin()?;
 
         log::info!("Starting collection from {}", self.source_directory);
+        self.open_exports().await?;
+        // Rows restored by `resume` are only kept for the exports
+        if self.exports.as_ref().is_none_or(Exports::is_empty) {
+            self.completed.values_mut().for_each(|item| *item = None);
+        }
 
-        let files = self.get_all_files();
+        let timer = std::time::Instant::now();
+        let mut files = self.get_all_files();
+        // Cancelled while expanding the patterns: the plan is incomplete
+        let interrupted = self.cancel.is_cancelled();
+        if interrupted {
+            files.clear();
+        }
         let total = files.len() as u64;
 
         log::info!("Found {} files to collect", total);
 
-        for (index, file) in files.iter().enumerate() {
-            let current = index as u64 + 1;
+        let mut plan: Vec<ArtifactPlan> = Vec::new();
+        for (artifact, _) in &files {
+            match plan.last_mut() {
+                Some(last) if last.artifact == *artifact => last.files += 1,
+                _ => plan.push(ArtifactPlan {
+                    artifact: artifact.clone(),
+                    files: 1,
+                }),
+            }
+        }
+        self.emit(CollectionEvent::PlanReady {
+            files: total,
+            artifacts: plan.clone(),
+        });
+        let mut plan = plan.into_iter();
+
+        match self.inventory {
+            Some(ref inventory) if !inventory.roots.is_empty() => {
+                for root in &inventory.roots {
+                    self.artifacts.add_artifact(&root.to_string_lossy());
+                }
+            }
+            _ => {
+                for pattern in &self.artifact_patterns {
+                    self.artifacts.add_artifact(pattern);
+                }
+            }
+        }
 
-            if let Some(ref cb) = callback {
-                cb(current, total, &file.to_string_lossy());
+        let mut current_artifact: Option<&String> = None;
+        for (index, (artifact, file)) in files.iter().enumerate() {
+            if self.cancel.is_cancelled() {
+                self.stop_cancelled(index as u64, total);
+                break;
             }
+            self.throttle.wait_file().await;
+            if current_artifact != Some(artifact)
+                && let Some(started) = plan.next()
+            {
+                current_artifact = Some(artifact);
+                self.emit(CollectionEvent::ArtifactStarted {
+                    artifact: started.artifact,
+                    files: started.files,
+                });
+            }
+
+            self.artifacts.add_file(artifact, &file.to_string_lossy());
+            self.attempted.clear();
+            self.partial_entry = None;
+            self.emit(CollectionEvent::FileStarted {
+                index: index as u64 + 1,
+                total,
+                artifact: artifact.clone(),
+                path: file.to_string_lossy().to_string(),
+            });
 
-            if let Err(e) = self.process_file(file).await {
+            let result = if self.inventory.is_some() {
+                self.process_inventory(file).await
+            } else {
+                self.process_file(file).await
+            };
+            if let Err(CollectorError::Cancelled) = result {
+                // A partial entry left in the archive must still be listed
+                if self.partial_entry.is_some() {
+                    let failure = FileFailure::new(
+                        file.to_string_lossy().to_string(),
+                        Some(artifact.clone()),
+                        &CollectorError::Cancelled,
+                        std::mem::take(&mut self.attempted),
+                    );
+                    if let Err(e) = self.record_failure(file, &failure).await {
+                        log::error!("Failed to list {} in the manifest: {}", file.display(), e);
+                    }
+                }
+                self.stop_cancelled(index as u64, total);
+                break;
+            }
+            if let Err(e) = result {
                 log::error!("Failed to process {}: {}", file.display(), e);
                 self.stats.failed_extractions += 1;
+                let failure = FileFailure::new(
+                    file.to_string_lossy().to_string(),
+                    Some(artifact.clone()),
+                    &e,
+                    std::mem::take(&mut self.attempted),
+                );
+                if let Err(e) = self.record_failure(file, &failure).await {
+                    log::error!("Failed to list {} in the manifest: {}", file.display(), e);
+                }
+                self.emit(CollectionEvent::FileFailed {
+                    failure: failure.clone(),
+                });
+                self.failures.push(failure);
             }
         }
 
+        if interrupted {
+            self.stop_cancelled(0, 0);
+        }
+        if let Some(ref mut journal) = self.journal {
+            journal.sync().await?;
+        }
+
+        self.stats.elapsed_ms += timer.elapsed().as_millis() as u64;
         log::info!(
             "Collection complete: {} files ({} bytes)",
             self.stats.files_collected,
@@ -162,162 +725,1842 @@ impl ArtifactCollector {
         Ok(self.stats.clone())
     }
 
-    /// Create ZIP archive
-    pub async fn create_archive(&self, password: Option<String>) -> Result<()> {
-        log::info!("Creating ZIP archive...");
-        self.writer.create_archive(password).await
+    /// Stop the collection loop after `files_done` files
+    fn stop_cancelled(&mut self, files_done: u64, total: u64) {
+        log::warn!(
+            "Collection cancelled after {} of {} files",
+            files_done,
+            total
+        );
+        self.cancelled = true;
+        self.emit(CollectionEvent::Cancelled { files_done, total });
     }
 
-    /// Process a single file
-    async fn process_file(&mut self, source_path: &PathBuf) -> Result<()> {
-        let relative_path = self.get_relative_path(source_path);
-        let mut output_file = self.writer.create_file(&relative_path).await?;
-
-        #[cfg(target_os = "windows")]
-        let (bytes, used_ntfs) =
-            extract_file(source_path, &mut output_file, self.vss_snapshot.as_ref()).await?;
-
-        #[cfg(not(target_os = "windows"))]
-        let (bytes, used_ntfs) = extract_file(source_path, &mut output_file, None).await?;
-
-        self.stats.files_collected += 1;
-        self.stats.bytes_collected += bytes;
-
-        if used_ntfs {
-            self.stats.ntfs_extractions += 1;
-        } else {
-            self.stats.filesystem_extractions += 1;
-        }
+    /// Archive the staging folder, returning the archive files
+    pub async fn create_archive(&mut self, options: &ArchiveOptions) -> Result<Vec<PathBuf>> {
+        log::info!("Creating {} archive...", options.format);
+        self.write_closing_documents().await?;
 
-        self.log_extraction(source_path, &relative_path, used_ntfs)
+        let events = &self.events;
+        let outputs = self
+            .writer
+            .create_archive_with_progress(options, |index, total, entry| {
+                let event = CollectionEvent::ArchiveProgress {
+                    index,
+                    total,
+                    entry: entry.to_string(),
+                };
+                for handler in events {
+                    handler.handle(&event);
+                }
+            })
             .await?;
-        Ok(())
+
+        self.emit(CollectionEvent::Finished {
+            stats: self.stats.clone(),
+            outputs: outputs.clone(),
+        });
+        Ok(outputs)
     }
 
-    /// Get relative path for destination
-    fn get_relative_path(&self, source_path: &PathBuf) -> String {
-        #[cfg(target_os = "windows")]
-        {
-            if let Some(ref vss) = self.vss_snapshot {
-                if let Some(snapshot_id) = vss.snapshot_id() {
-                    let path_str = source_path.to_string_lossy();
-                    let source_str = self.source_directory.to_string_lossy();
-                    return path_str.replace(&source_str, snapshot_id);
+    /// Flush and seal the manifest, then finalize the output sink
+    pub async fn finish(mut self) -> Result<Vec<PathBuf>> {
+        if let Some(manifest) = self.scratch_manifest.clone() {
+            let documents = self.closing_documents().await?;
+            let sink = self.sink()?;
+            sink.add_file(MANIFEST_NAME, &manifest)?;
+            let mut scratch = vec![manifest];
+            for (name, document) in documents {
+                match document {
+                    Closing::Data(data) => sink.add_bytes(name, &data)?,
+                    Closing::File(path) => {
+                        sink.add_file(name, &path)?;
+                        scratch.push(path);
+                    }
                 }
             }
+            for path in scratch {
+                fs::remove_file(&path)
+                    .await
+                    .map_err(|e| CollectorError::FileWrite { path, source: e })?;
+            }
+        } else if !self.sealed {
+            self.write_closing_documents().await?;
         }
-        source_path.to_string_lossy().to_string()
-    }
 
-    /// Log extraction to CSV
-    async fn log_extraction(
-        &mut self,
-        source: &PathBuf,
-        destination: &str,
-        from_ntfs: bool,
-    ) -> Result<()> {
-        let dest_path = self.writer.get_file_path(destination);
+        let outputs = self.sink.take().ok_or_else(sink_lost)?.finalize()?;
+        let event = CollectionEvent::Finished {
+            stats: self.stats.clone(),
+            outputs: outputs.clone(),
+        };
+        for handler in &self.events {
+            handler.handle(&event);
+        }
+        Ok(outputs)
+    }
 
-        let metadata = std::fs::metadata(&dest_path).map_err(|e| CollectorError::FileRead {
-            path: dest_path.clone(),
-            source: e,
-        })?;
+    /// Case metadata, reports, exports and manifest seal closing the
+    /// collection, the seal last
+    async fn closing_documents(&mut self) -> Result<Vec<(&'static str, Closing)>> {
+        self.case_metadata.cancelled = self.cancelled;
+        self.case_metadata.finish(&self.stats);
+        self.csv_logger.flush().await?;
+        self.open_exports().await?;
 
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
+        let mut documents = vec![(CASE_METADATA_NAME, self.case_metadata.to_json())];
+        if !self.failures.is_empty() {
+            documents.push((FAILURES_NAME, FailureReport::new(&self.failures).to_json()));
+        }
+        if let Some(ref iocs) = self.iocs {
+            let report = IocReport::from_items(iocs, self.rows, &self.ioc_rows);
+            documents.push((FINDINGS_NAME, report.to_json()));
+        }
+        if let Some(ref rules) = self.rules {
+            let report = ScanReport::new(rules, self.files_scanned, self.scan_findings.clone());
+            documents.push((SCAN_FINDINGS_NAME, report.to_json()));
+        }
 
-        // Hash computed in blocking thread (CPU-bound)
-        let hash = Self::calculate_hash_blocking(dest_path.clone()).await?;
+        let mut documents: Vec<(&'static str, Closing)> = documents
+            .into_iter()
+            .map(|(name, data)| (name, Closing::Data(data)))
+            .collect();
+        // Restored rows of files no longer matched by the patterns
+        let mut resumed: Vec<CsvLogItem> = self
+            .completed
+            .values_mut()
+            .filter_map(Option::take)
+            .collect();
+        resumed.sort_by(|a, b| a.source_file.cmp(&b.source_file));
+        for item in &resumed {
+            self.export(item, &TimelineEntry::from_item(item)).await?;
+        }
+        if let Some(exports) = self.exports.take() {
+            let files = exports
+                .finish(&self.case_metadata, &self.artifacts, &self.failures)
+                .await?;
+            for (name, path) in files {
+                documents.push((name, Closing::File(path)));
+            }
+        }
+        let seal = self.build_seal(&documents).await?;
+        documents.push((SEAL_NAME, Closing::Data(seal)));
+        Ok(documents)
+    }
 
-        let log_item = CsvLogItem::with_paths(
-            source.to_string_lossy().to_string(),
-            dest_path.to_string_lossy().to_string(),
+    /// Open the exports written row by row. Scratch files next to the
+    /// manifest stand in for them until they are added to a sink, and hold
+    /// the rows of the DFXML and CASE/UCO documents.
+    async fn open_exports(&mut self) -> Result<()> {
+        if self.exports.is_some() {
+            return Ok(());
+        }
+        let writer = &self.writer;
+        let scratch = self.scratch_manifest.is_some();
+        let options = ExportOptions {
+            timeline: self.timeline_enabled,
+            dfxml: self.dfxml_enabled,
+            case_uco: self.case_uco_enabled,
+            jsonl: self.manifest_formats.contains(&ManifestFormat::Jsonl),
+            sqlite: self.manifest_formats.contains(&ManifestFormat::Sqlite),
+        };
+        let exports = Exports::create(
+            options,
+            |name| match scratch {
+                true => writer.scratch_path(name),
+                false => writer.get_file_path(name),
+            },
+            |name| writer.scratch_path(&format!("{}.rows", name)),
         )
-        .with_hash(hash)
-        .with_ntfs_flag(from_ntfs)
-        .with_timestamps(mtime.to_string(), atime.to_string())
-        .with_size(metadata.len());
+        .await?;
+        self.exports = Some(exports);
+        Ok(())
+    }
 
-        self.csv_logger.add_row(log_item).await
+    /// Add a row to the exports, with the artifact its file was found by
+    async fn export(&mut self, item: &CsvLogItem, entry: &TimelineEntry) -> Result<()> {
+        match self.exports {
+            Some(ref mut exports) => exports.add(item, entry, &self.artifacts).await,
+            None => Ok(()),
+        }
     }
 
-    /// Calculate SHA1 hash in a blocking thread (CPU-bound operation)
-    async fn calculate_hash_blocking(path: PathBuf) -> Result<String> {
-        spawn_blocking(move || {
-            let mut file = std::fs::File::open(&path).map_err(|e| CollectorError::FileRead {
-                path: path.clone(),
+    /// Seal the flushed manifest and the `reports` written with it, and
+    /// return the seal as JSON
+    async fn build_seal(&mut self, reports: &[(&'static str, Closing)]) -> Result<Vec<u8>> {
+        let manifest_path = PathBuf::from(self.csv_logger.file_path());
+        let manifest = fs::read(&manifest_path)
+            .await
+            .map_err(|e| CollectorError::FileRead {
+                path: manifest_path,
                 source: e,
             })?;
 
-            let mut hasher = Sha1::new();
-            let mut buffer = [0u8; HASH_BUFFER_SIZE];
-
-            loop {
-                let bytes_read = file
-                    .read(&mut buffer)
-                    .map_err(|e| CollectorError::FileRead {
-                        path: path.clone(),
-                        source: e,
-                    })?;
-
-                if bytes_read == 0 {
-                    break;
-                }
-
-                hasher.update(&buffer[..bytes_read]);
+        let mut metadata = SealMetadata {
+            hostname: self.writer.hostname().to_string(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: self.case_metadata.started_at.clone(),
+            finished_at: Utc::now().to_rfc3339(),
+            files_collected: self.stats.files_collected,
+            bytes_collected: self.stats.bytes_collected,
+            failed_extractions: self.stats.failed_extractions,
+            cancelled: self.cancelled,
+            case_metadata_sha256: None,
+            report_sha256: BTreeMap::new(),
+        };
+        for (name, document) in reports {
+            let digest = match document {
+                Closing::Data(data) => hex::encode(Sha256::digest(data)),
+                Closing::File(path) => sha256_file(path.clone()).await?,
+            };
+            if *name == CASE_METADATA_NAME {
+                metadata.case_metadata_sha256 = Some(digest);
+            } else {
+                metadata.report_sha256.insert(name.to_string(), digest);
             }
+        }
 
-            Ok(hex::encode(hasher.finalize()))
-        })
-        .await
-        .map_err(|e| CollectorError::CollectionFailed(format!("Hash task failed: {}", e)))?
+        let mut seal = ManifestSeal::new(MANIFEST_NAME, &manifest, metadata).await?;
+        if let Some(ref key) = self.signing_key {
+            seal = seal.sign(key)?;
+        }
+        seal.to_json()
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_collection_stats_default() {
-        let stats = CollectionStats::default();
-        assert_eq!(stats.files_collected, 0);
-        assert_eq!(stats.bytes_collected, 0);
+    /// Write the case metadata and seal next to the manifest in the staging
+    /// folder
+    async fn write_closing_documents(&mut self) -> Result<()> {
+        for (name, document) in self.closing_documents().await? {
+            // Exports are already written in place
+            if let Closing::Data(data) = document {
+                let path = self.writer.get_file_path(name);
+                fs::write(&path, data)
+                    .await
+                    .map_err(|e| CollectorError::FileWrite { path, source: e })?;
+            }
+        }
+        if let Some(journal) = self.journal.take() {
+            journal.remove().await?;
+        }
+        self.sealed = true;
+        Ok(())
     }
 
-    #[test]
-    fn test_collection_stats_merge() {
-        let mut stats1 = CollectionStats {
-            files_collected: 10,
-            bytes_collected: 1000,
-            ..Default::default()
-        };
+    /// Collect the same patterns from a mounted VSS snapshot into this
+    /// collector's output. Returns the statistics of the snapshot alone.
+    #[cfg(target_os = "windows")]
+    pub async fn collect_from_snapshot(
+        &mut self,
+        mount_point: &Path,
+        snapshot: VssSnapshot,
+    ) -> Result<CollectionStats> {
+        let source = std::mem::replace(&mut self.source_directory, FormatSource::new(mount_point));
+        let previous_snapshot = self.vss_snapshot.replace(snapshot);
+        let mut total_stats = std::mem::take(&mut self.stats);
 
-        let stats2 = CollectionStats {
-            files_collected: 5,
-            bytes_collected: 500,
-            ntfs_extractions: 3,
-            ..Default::default()
-        };
+        let result = self.collect().await;
 
-        stats1.merge(&stats2);
+        self.source_directory = source;
+        self.vss_snapshot = previous_snapshot;
+        let snapshot_stats = std::mem::replace(&mut self.stats, CollectionStats::default());
+        total_stats.merge(&snapshot_stats);
+        self.stats = total_stats;
 
-        assert_eq!(stats1.files_collected, 15);
-        assert_eq!(stats1.bytes_collected, 1500);
-        assert_eq!(stats1.ntfs_extractions, 3);
+        result.map(|_| snapshot_stats)
     }
 
-    #[tokio::test]
-    async fn test_artifact_collector_new() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let source = temp_dir.path().join("source");
-        let dest = temp_dir.path().join("dest");
+    /// Process a single file
+    async fn process_file(&mut self, source_path: &Path) -> Result<()> {
+        if self.resumed(source_path).await? {
+            log::debug!("Already collected: {}", source_path.display());
+            self.emit(skipped(source_path, SkipReason::Resumed));
+            return Ok(());
+        }
 
-        std::fs::create_dir_all(&source).unwrap();
-        std::fs::create_dir_all(&dest).unwrap();
+        let relative_path = self.get_relative_path(source_path);
+        let metadata = EntryMetadata::from_path(source_path);
 
-        let collector = ArtifactCollector::new(&source, &dest, vec!["*.txt".to_string()]).await;
+        if let Some(ref previous) = self.previous
+            && let Some(item) = previous.unchanged(&source_path.to_string_lossy(), &metadata)
+        {
+            let log_item = CsvLogItem::with_paths(item.source_file.clone(), "")
+                .with_hash(item.hash_sha1.clone())
+                .with_ntfs_flag(item.from_ntfs)
+                .with_timestamps(item.modified_time.clone(), item.access_time.clone())
+                .with_size(item.file_size)
+                .with_inode(metadata.inode)
+                .with_status(format!("{}{}", UNCHANGED_PREFIX, copied_in(item)));
 
-        assert!(collector.is_ok());
+            self.stats.files_unchanged += 1;
+            // The manifest only carries SHA1: read again for MD5 and SHA256
+            // indicators
+            let digests = match self.needs_digests() {
+                true => self
+                    .read_source(source_path, None)
+                    .await
+                    .map(|read| read.digests)
+                    .unwrap_or_default(),
+                false => ExtraDigests::default(),
+            };
+            let log_item = self.flag_ioc(log_item, &digests);
+            self.record(log_item, &metadata, Vec::new()).await?;
+            self.emit(skipped(source_path, SkipReason::Unchanged));
+            return Ok(());
+        }
+
+        // Sinks that cannot drop an entry need known-good files hashed before
+        // anything reaches them, at the cost of a second read. Files the
+        // filesystem cannot read are copied and flagged afterwards.
+        let prehash = self
+            .known
+            .as_ref()
+            .is_some_and(|known| known.action() == KnownAction::Skip)
+            && !self.sink.as_ref().is_some_and(|sink| sink.can_rewind());
+        if prehash
+            && let Ok(read) = self.read_source(source_path, None).await
+            && let Some(set) = self.known_set(&read.hash)?
+        {
+            let log_item = CsvLogItem::with_paths(source_path.to_string_lossy().to_string(), "")
+                .with_hash(read.hash)
+                .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+                .with_size(read.bytes)
+                .with_inode(metadata.inode)
+                .with_status(format!("{}{}", KNOWN_PREFIX, set));
+
+            self.stats.files_known += 1;
+            let log_item = self.flag_ioc(log_item, &read.digests);
+            self.record(log_item, &metadata, Vec::new()).await?;
+            self.emit(skipped(source_path, SkipReason::Known));
+            return Ok(());
+        }
+
+        // Large files are only collected when they match a rule, so scan
+        // them in place first
+        let mut prescanned = None;
+        if let Some(ref rules) = self.rules
+            && rules.filters(metadata.size)
+            && let Ok(read) = self.read_source(source_path, Some(rules.clone())).await
+        {
+            let matches = read.matches.unwrap_or_default();
+            if matches.is_empty() {
+                let log_item =
+                    CsvLogItem::with_paths(source_path.to_string_lossy().to_string(), "")
+                        .with_hash(read.hash)
+                        .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+                        .with_size(read.bytes)
+                        .with_inode(metadata.inode)
+                        .with_status(NO_MATCH_STATUS);
+
+                self.files_scanned += 1;
+                self.stats.files_filtered += 1;
+                let log_item = self.flag_ioc(log_item, &read.digests);
+                self.record(log_item, &metadata, Vec::new()).await?;
+                self.emit(skipped(source_path, SkipReason::NoRuleMatch));
+                return Ok(());
+            }
+            prescanned = Some(matches);
+        }
+
+        let sink = self.sink()?;
+        let fixed_size = sink.fixed_size().then_some(metadata.size);
+        sink.start_entry(&relative_path, &metadata)?;
+        let copy = EntryCopy {
+            source: source_path.to_path_buf(),
+            fixed_size,
+            throttle: self.throttle.clone(),
+            cancel: self.cancel.clone(),
+            iocs: self.iocs.clone(),
+            rules: if prescanned.is_none() {
+                self.rules.clone()
+            } else {
+                None
+            },
+            #[cfg(target_os = "windows")]
+            entry: (relative_path.clone(), metadata.clone()),
+            #[cfg(target_os = "windows")]
+            vss_snapshot: self.vss_snapshot.clone(),
+        };
+
+        let read = match self.copy_to_sink(copy).await {
+            Ok(read) => read,
+            Err(e) => {
+                if self.sink()?.abort_entry()? {
+                    self.partial_entry = Some(entry_name(&relative_path));
+                }
+                if self.cancel.is_cancelled() {
+                    return Err(CollectorError::Cancelled);
+                }
+                return Err(e);
+            }
+        };
+        let SourceRead {
+            bytes,
+            hash,
+            digests,
+            matches,
+            byte_runs,
+            resized,
+        } = read;
+        let used_ntfs = byte_runs.is_some();
+
+        let known = self
+            .known_set(&hash)?
+            .map(|set| format!("{}{}", KNOWN_PREFIX, set));
+        let skip = self
+            .known
+            .as_ref()
+            .is_some_and(|known| known.action() == KnownAction::Skip);
+        if known.is_some() {
+            self.stats.files_known += 1;
+        }
+
+        // Known-good copy the sink can still drop: list it without one
+        if let Some(status) = known.clone()
+            && skip
+            && self.sink()?.can_rewind()
+        {
+            self.sink()?.abort_entry()?;
+            let log_item = CsvLogItem::with_paths(source_path.to_string_lossy().to_string(), "")
+                .with_hash(hash)
+                .with_ntfs_flag(used_ntfs)
+                .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+                .with_size(bytes)
+                .with_inode(metadata.inode)
+                .with_status(status);
+
+            let log_item = self.flag_ioc(log_item, &digests);
+            self.record(log_item, &metadata, Vec::new()).await?;
+            self.emit(skipped(source_path, SkipReason::Known));
+            return Ok(());
+        }
+        self.sink()?.finish_entry()?;
+
+        self.stats.files_collected += 1;
+        self.stats.bytes_collected += bytes;
+
+        if used_ntfs {
+            self.stats.ntfs_extractions += 1;
+        } else {
+            self.stats.filesystem_extractions += 1;
+        }
+
+        let log_item = CsvLogItem::with_paths(
+            source_path.to_string_lossy().to_string(),
+            entry_name(&relative_path),
+        )
+        .with_hash(hash)
+        .with_ntfs_flag(used_ntfs)
+        .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+        .with_size(bytes)
+        .with_inode(metadata.inode)
+        .with_status(
+            known
+                .into_iter()
+                .chain(resized.map(|resized| resized.status()))
+                .collect::<Vec<_>>()
+                .join("; "),
+        );
+
+        let log_item = self.flag_ioc(log_item, &digests);
+        let log_item = self.flag_rules(log_item, prescanned.or(matches));
+        let sha1 = log_item.hash_sha1.clone();
+        self.record(log_item, &metadata, byte_runs.unwrap_or_default())
+            .await?;
+        self.emit(CollectionEvent::FileFinished {
+            path: source_path.to_string_lossy().to_string(),
+            bytes,
+            sha1,
+            method: if used_ntfs {
+                ExtractionMethod::Ntfs
+            } else {
+                ExtractionMethod::Filesystem
+            },
+        });
+        Ok(())
+    }
+
+    /// List a single file in the inventory, hashing it if requested
+    async fn process_inventory(&mut self, source_path: &Path) -> Result<()> {
+        if self.resumed(source_path).await? {
+            self.emit(skipped(source_path, SkipReason::Resumed));
+            return Ok(());
+        }
+
+        let metadata = EntryMetadata::from_path(source_path);
+        let hash = self
+            .inventory
+            .as_ref()
+            .is_some_and(|inventory| inventory.hash);
+        let (hash, digests) = match hash || self.needs_digests() {
+            true => match self.read_source(source_path, None).await {
+                Ok(read) => {
+                    self.stats.bytes_hashed += read.bytes;
+                    (read.hash, read.digests)
+                }
+                Err(e) => {
+                    log::warn!("Failed to hash {}: {}", source_path.display(), e);
+                    Default::default()
+                }
+            },
+            false => Default::default(),
+        };
+
+        let log_item = CsvLogItem::with_paths(source_path.to_string_lossy().to_string(), "")
+            .with_hash(hash)
+            .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+            .with_size(metadata.size)
+            .with_inode(metadata.inode)
+            .with_status(INVENTORY_STATUS);
+
+        self.stats.files_inventoried += 1;
+        self.stats.bytes_inventoried += metadata.size;
+        let log_item = self.flag_ioc(log_item, &digests);
+        let sha1 = Some(log_item.hash_sha1.clone()).filter(|hash| !hash.is_empty());
+        self.record(log_item, &metadata, Vec::new()).await?;
+        self.emit(CollectionEvent::FileListed {
+            path: source_path.to_string_lossy().to_string(),
+            size: metadata.size,
+            sha1,
+        });
+        Ok(())
+    }
+
+    /// Known-good hash set holding `hash`, if any
+    fn known_set(&self, hash: &str) -> Result<Option<String>> {
+        match self.known {
+            Some(ref known) => Ok(known.lookup(hash)?.map(str::to_string)),
+            None => Ok(None),
+        }
+    }
+
+    /// Output sink, missing only after a copy task panicked
+    fn sink(&mut self) -> Result<&mut Box<dyn OutputSink>> {
+        self.sink.as_mut().ok_or_else(sink_lost)
+    }
+
+    /// Run a copy into the open sink entry on the blocking pool, so long
+    /// reads and throttling pauses do not hold a runtime worker, at the
+    /// priority the throttle sets
+    async fn copy_to_sink(&mut self, copy: EntryCopy) -> Result<SourceRead> {
+        let mut sink = self.sink.take().ok_or_else(sink_lost)?;
+        let mut attempted = std::mem::take(&mut self.attempted);
+
+        let (sink, attempted, result) = spawn_blocking(move || {
+            let _priority = copy.throttle.lower_priority();
+            let result = copy.run(sink.as_mut(), &mut attempted);
+            (sink, attempted, result)
+        })
+        .await
+        .map_err(|e| CollectorError::CollectionFailed(format!("Copy task failed: {}", e)))?;
+
+        self.sink = Some(sink);
+        self.attempted = attempted;
+        result
+    }
+
+    /// Whether the loaded indicators need digests other than SHA1
+    fn needs_digests(&self) -> bool {
+        self.iocs
+            .as_ref()
+            .is_some_and(|iocs| iocs.needs_md5() || iocs.needs_sha256())
+    }
+
+    /// Hash a source file on the blocking pool, held to the byte rate of the
+    /// throttle, and scan it with `rules` in the same read. The digests the
+    /// indicators need are computed along.
+    fn read_source(
+        &self,
+        source_path: &Path,
+        rules: Option<Arc<RuleSet>>,
+    ) -> impl Future<Output = Result<SourceRead>> + Send + 'static {
+        let path = source_path.to_path_buf();
+        let throttle = self.throttle.clone();
+        let iocs = self.iocs.clone();
+
+        let task = spawn_blocking(move || {
+            let _priority = throttle.lower_priority();
+            let read_error = |e| CollectorError::FileRead {
+                path: path.clone(),
+                source: e,
+            };
+            let file = std::fs::File::open(&path).map_err(read_error)?;
+            let mut output = ScanWriter::new(
+                DigestWriter::new(HashingWriter::new(io::sink()), iocs.as_deref()),
+                rules.as_deref(),
+            );
+            let bytes = copy_buffered(&mut Throttled::new(file, &throttle), &mut output)
+                .map_err(read_error)?;
+
+            let (output, matches) = output.finish();
+            let (output, digests) = output.finish();
+            Ok(SourceRead {
+                bytes,
+                hash: output.hex_digest(),
+                digests,
+                matches,
+                byte_runs: None,
+                resized: None,
+            })
+        });
+        async move {
+            task.await
+                .map_err(|e| CollectorError::CollectionFailed(format!("Read task failed: {}", e)))?
+        }
+    }
+
+    /// List a file that could not be collected in the manifest, with the
+    /// partial entry the sink kept as destination, if any
+    async fn record_failure(&mut self, source_path: &Path, failure: &FileFailure) -> Result<()> {
+        let metadata = EntryMetadata::from_path(source_path);
+        let destination = self.partial_entry.take().unwrap_or_default();
+        let log_item = CsvLogItem::with_paths(failure.source_file.clone(), destination)
+            .with_timestamps(timestamp(metadata.modified), timestamp(metadata.accessed))
+            .with_size(metadata.size)
+            .with_inode(metadata.inode)
+            .with_status(failure.status());
+
+        let log_item = self.flag_ioc(log_item, &ExtraDigests::default());
+        self.record(log_item, &metadata, Vec::new()).await
+    }
+
+    /// Get relative path for destination
+    fn get_relative_path(&self, source_path: &Path) -> String {
+        #[cfg(target_os = "windows")]
+        {
+            if let Some(ref vss) = self.vss_snapshot {
+                if let Some(snapshot_id) = vss.snapshot_id() {
+                    let path_str = source_path.to_string_lossy();
+                    let source_str = self.source_directory.to_string_lossy();
+                    return path_str.replace(&source_str, snapshot_id);
+                }
+            }
+        }
+        source_path.to_string_lossy().to_string()
+    }
+
+    /// Flag a manifest row with the indicators of compromise it matches
+    fn flag_ioc(&mut self, item: CsvLogItem, digests: &ExtraDigests) -> CsvLogItem {
+        let Some(ref iocs) = self.iocs else {
+            return item;
+        };
+        let matched = iocs.check(&item, digests);
+        if matched.is_empty() {
+            return item;
+        }
+
+        let ids: Vec<&str> = matched.iter().map(|i| i.id.as_str()).collect();
+        log::warn!("IOC match: {} ({})", item.source_file, ids.join(", "));
+
+        let item = item.with_ioc(ids);
+        self.stats.ioc_matches += 1;
+        self.ioc_rows.push(item.clone());
+        item
+    }
+
+    /// Flag a manifest row with the scan rules its content matched
+    fn flag_rules(&mut self, item: CsvLogItem, matches: Option<Vec<RuleMatch>>) -> CsvLogItem {
+        let Some(matches) = matches else {
+            return item;
+        };
+        self.files_scanned += 1;
+        if matches.is_empty() {
+            return item;
+        }
+
+        let names: Vec<&str> = matches.iter().map(|m| m.rule.as_str()).collect();
+        log::warn!("Rule match: {} ({})", item.source_file, names.join(", "));
+
+        let item = item.with_rules(names);
+        self.stats.rule_matches += 1;
+        self.scan_findings.push(ScanFinding {
+            source_file: item.source_file.clone(),
+            destination_file: item.destination_file.clone(),
+            matches,
+        });
+        item
+    }
+
+    /// Whether `resume` restored the file, exporting its row the first time
+    async fn resumed(&mut self, source_path: &Path) -> Result<bool> {
+        let Some(row) = self
+            .completed
+            .get_mut(source_path.to_string_lossy().as_ref())
+        else {
+            return Ok(false);
+        };
+        if let Some(item) = row.take() {
+            self.export(&item, &TimelineEntry::from_item(&item)).await?;
+        }
+        Ok(true)
+    }
+
+    /// Add a row to the manifest and checkpoint it in the journal
+    async fn record(
+        &mut self,
+        item: CsvLogItem,
+        metadata: &EntryMetadata,
+        byte_runs: Vec<ByteRun>,
+    ) -> Result<()> {
+        let entry = TimelineEntry::new(&item, metadata).with_byte_runs(byte_runs);
+        self.rows += 1;
+        self.csv_logger.add_row(item.clone()).await?;
+        if let Some(ref mut journal) = self.journal {
+            journal.append(&item).await?;
+        }
+
+        let mut item = item;
+        item.chain_hash = self.csv_logger.chain_head().to_string();
+        self.export(&item, &entry).await
+    }
+}
+
+/// Document closing a collection, built in memory or exported to a file
+enum Closing {
+    Data(Vec<u8>),
+    File(PathBuf),
+}
+
+/// Content of a source file as read by a copy or a hash
+struct SourceRead {
+    bytes: u64,
+    hash: String,
+    /// Extra digests computed for indicator matching
+    digests: ExtraDigests,
+    /// Rules matched by the content, if it was scanned
+    matches: Option<Vec<RuleMatch>>,
+    /// Volume ranges of a raw NTFS read
+    byte_runs: Option<Vec<ByteRun>>,
+    /// Padding or cut applied to fit a fixed-size entry
+    resized: Option<Resized>,
+}
+
+/// Copy of a source file into the open entry of a sink, run on the
+/// blocking pool
+struct EntryCopy {
+    source: PathBuf,
+    /// Size of the entry, for sinks that write it before the content
+    fixed_size: Option<u64>,
+    throttle: Arc<Throttle>,
+    cancel: CancelToken,
+    iocs: Option<Arc<IocSet>>,
+    rules: Option<Arc<RuleSet>>,
+    /// Entry started again before a raw NTFS retry
+    #[cfg(target_os = "windows")]
+    entry: (String, EntryMetadata),
+    #[cfg(target_os = "windows")]
+    vss_snapshot: Option<VssSnapshot>,
+}
+
+impl EntryCopy {
+    /// Copy the source through the filesystem. On Windows a failed read is
+    /// retried through raw NTFS, unless part of it reached a sink that
+    /// cannot drop it.
+    fn run(
+        &self,
+        sink: &mut dyn OutputSink,
+        attempted: &mut Vec<ExtractionMethod>,
+    ) -> Result<SourceRead> {
+        attempted.push(ExtractionMethod::Filesystem);
+        let (result, _written) = self.copy(sink, ExtractionMethod::Filesystem);
+
+        #[cfg(target_os = "windows")]
+        if let Err(ref e) = result
+            && !self.cancel.is_cancelled()
+            && (_written == 0 || sink.can_rewind())
+        {
+            log::debug!("Filesystem failed, trying NTFS: {}", e);
+            if _written > 0 {
+                let (ref entry, ref metadata) = self.entry;
+                sink.abort_entry()?;
+                sink.start_entry(entry, metadata)?;
+            }
+            attempted.push(ExtractionMethod::Ntfs);
+            return self.copy(sink, ExtractionMethod::Ntfs).0;
+        }
+
+        result
+    }
+
+    /// Copy the content with `method`, also returning how many bytes
+    /// reached the sink
+    fn copy(
+        &self,
+        sink: &mut dyn OutputSink,
+        method: ExtractionMethod,
+    ) -> (Result<SourceRead>, u64) {
+        let mut output = FixedSizeWriter::new(
+            ScanWriter::new(
+                DigestWriter::new(
+                    HashingWriter::new(CancelWriter::new(
+                        Throttled::new(sink, &self.throttle),
+                        self.cancel.clone(),
+                    )),
+                    self.iocs.as_deref(),
+                ),
+                self.rules.as_deref(),
+            ),
+            self.fixed_size,
+        );
+
+        let mut result = match method {
+            ExtractionMethod::Filesystem => {
+                extract_via_filesystem(&self.source, &mut output).map(|bytes| (bytes, None))
+            }
+            #[cfg(target_os = "windows")]
+            ExtractionMethod::Ntfs => {
+                extract_via_ntfs(&self.source, &mut output, self.vss_snapshot.as_ref())
+                    .map(|(bytes, runs)| (bytes, Some(runs)))
+            }
+            #[cfg(not(target_os = "windows"))]
+            ExtractionMethod::Ntfs => Err(CollectorError::NtfsError(
+                "Raw NTFS extraction is only available on Windows".into(),
+            )),
+        };
+
+        let mut resized = None;
+        if result.is_ok() {
+            match output.finish() {
+                Ok(change) => resized = change,
+                Err(e) => {
+                    result = Err(CollectorError::FileWrite {
+                        path: self.source.clone(),
+                        source: e,
+                    })
+                }
+            }
+        }
+
+        let (output, matches) = output.into_inner().finish();
+        let (output, digests) = output.finish();
+        let written = output.bytes_written();
+        let read = result.map(|(bytes, byte_runs)| SourceRead {
+            bytes: self.fixed_size.unwrap_or(bytes),
+            hash: output.hex_digest(),
+            digests,
+            matches,
+            byte_runs,
+            resized,
+        });
+        (read, written)
+    }
+}
+
+/// SHA256 of a file, read on the blocking pool
+async fn sha256_file(path: PathBuf) -> Result<String> {
+    spawn_blocking(move || {
+        let read_error = |e| CollectorError::FileRead {
+            path: path.clone(),
+            source: e,
+        };
+        let mut file = std::fs::File::open(&path).map_err(read_error)?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).map_err(read_error)?;
+        Ok(hex::encode(hasher.finalize()))
+    })
+    .await
+    .map_err(|e| CollectorError::CollectionFailed(format!("Hash task failed: {}", e)))?
+}
+
+fn sink_lost() -> CollectorError {
+    CollectorError::CollectionFailed("Output sink lost by a failed copy task".into())
+}
+
+/// Manifest form of an optional file time
+fn timestamp(time: Option<filetime::FileTime>) -> String {
+    time.map(|t| t.to_string()).unwrap_or_default()
+}
+
+fn skipped(source_path: &Path, reason: SkipReason) -> CollectionEvent {
+    CollectionEvent::FileSkipped {
+        path: source_path.to_string_lossy().to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::read_manifest_items;
+    use crate::verify::VerifyReport;
+
+    #[test]
+    fn test_collection_stats_default() {
+        let stats = CollectionStats::default();
+        assert_eq!(stats.files_collected, 0);
+        assert_eq!(stats.bytes_collected, 0);
+    }
+
+    #[test]
+    fn test_collection_stats_merge() {
+        let mut stats1 = CollectionStats {
+            files_collected: 10,
+            bytes_collected: 1000,
+            ..Default::default()
+        };
+
+        let stats2 = CollectionStats {
+            files_collected: 5,
+            bytes_collected: 500,
+            ntfs_extractions: 3,
+            ..Default::default()
+        };
+
+        stats1.merge(&stats2);
+
+        assert_eq!(stats1.files_collected, 15);
+        assert_eq!(stats1.bytes_collected, 1500);
+        assert_eq!(stats1.ntfs_extractions, 3);
+    }
+
+    #[tokio::test]
+    async fn test_artifact_collector_new() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let dest = temp_dir.path().join("dest");
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+
+        let collector = ArtifactCollector::new(&source, &dest, vec!["*.txt".to_string()]).await;
+
+        assert!(collector.is_ok());
+    }
+
+    /// Source folder of test files, collected to an `output` folder next to it
+    struct Fixture {
+        temp_dir: tempfile::TempDir,
+        source: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(files: &[(&str, &[u8])]) -> Self {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let source = temp_dir.path().join("source");
+            std::fs::create_dir_all(&source).unwrap();
+            let fixture = Self { temp_dir, source };
+            for (name, data) in files {
+                fixture.write(name, data);
+            }
+            fixture
+        }
+
+        fn write(&self, name: &str, data: &[u8]) {
+            let path = self.source.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.temp_dir.path().join(name)
+        }
+
+        fn output(&self) -> PathBuf {
+            self.path("output")
+        }
+
+        /// Pattern matching `glob` in the source folder
+        fn pattern(&self, glob: &str) -> String {
+            format!("{}/{}", self.source.display(), glob)
+        }
+
+        /// Staging collector of the files matching `glob`
+        async fn collector(&self, glob: &str) -> ArtifactCollector {
+            ArtifactCollector::new("/", self.output(), vec![self.pattern(glob)])
+                .await
+                .unwrap()
+        }
+
+        /// Remove `name` once its copy starts, as if it vanished mid-collection
+        fn vanish(&self, name: &'static str) -> impl Fn(&CollectionEvent) + Send + Sync + 'static {
+            let vanished = self.source.join(name);
+            move |event: &CollectionEvent| {
+                if let CollectionEvent::FileStarted { path, .. } = event
+                    && path.ends_with(name)
+                {
+                    let _ = std::fs::remove_file(&vanished);
+                }
+            }
+        }
+    }
+
+    /// Finish a staging collection, returning its writer and manifest rows
+    async fn finish(collector: ArtifactCollector) -> (Writer, Vec<CsvLogItem>) {
+        let writer = collector.writer().clone();
+        collector.finish().await.unwrap();
+        let manifest = std::fs::read(writer.get_file_path(MANIFEST_NAME)).unwrap();
+        (writer, read_manifest_items(&manifest).await.unwrap())
+    }
+
+    /// Manifest row of the source file ending with `name`
+    fn row<'a>(items: &'a [CsvLogItem], name: &str) -> &'a CsvLogItem {
+        items
+            .iter()
+            .find(|item| item.source_file.ends_with(name))
+            .unwrap()
+    }
+
+    /// Verify a staging collection, which must hold `matched` copies and
+    /// `skipped` rows without one
+    async fn verify(writer: &Writer, matched: u64, skipped: u64) -> VerifyReport {
+        let report = crate::verify::verify_collection(writer.full_destination(), None)
+            .await
+            .unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!((report.matched, report.skipped), (matched, skipped));
+        report
+    }
+
+    #[tokio::test]
+    async fn test_direct_archive_collection() {
+        use std::io::Read;
+
+        let fixture = Fixture::new(&[("a.txt", b"alpha")]);
+        let dest = fixture.path("dest");
+        let mut collector = ArtifactCollector::new_archive(
+            "/",
+            &dest,
+            vec![fixture.pattern("*.txt")],
+            ArchiveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(collector.is_direct_archive());
+
+        let stats = collector.collect().await.unwrap();
+        assert_eq!(stats.files_collected, 1);
+
+        let outputs = collector.finish().await.unwrap();
+        assert_eq!(outputs.len(), 1);
+
+        // Only the archive remains: no staging directory, no scratch manifest
+        let remaining: Vec<_> = std::fs::read_dir(&dest).unwrap().collect();
+        assert_eq!(remaining.len(), 1);
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&outputs[0]).unwrap()).unwrap();
+        let last = archive.len() - 1;
+        assert_eq!(archive.by_index(last - 2).unwrap().name(), MANIFEST_NAME);
+        assert_eq!(
+            archive.by_index(last - 1).unwrap().name(),
+            CASE_METADATA_NAME
+        );
+        assert_eq!(archive.by_index(last).unwrap().name(), SEAL_NAME);
+
+        let mut manifest = String::new();
+        archive
+            .by_name(MANIFEST_NAME)
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        assert!(manifest.contains("a.txt"));
+    }
+
+    /// Direct encrypted tar output writes nothing outside the destination.
+    /// The collection runs in a child test process whose TMPDIR is missing,
+    /// so any temporary file, even an unnamed one, fails it.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_direct_encrypted_tar_stays_in_destination() {
+        use std::io::Read;
+
+        const CHILD_VAR: &str = "COLLECTOR_TEST_ENCRYPTED_TAR";
+
+        if let Ok(child) = std::env::var(CHILD_VAR) {
+            let (pattern, dest, recipient): (String, PathBuf, String) =
+                serde_json::from_str(&child).unwrap();
+            let options = ArchiveOptions::default()
+                .with_format(crate::sink::ArchiveFormat::Tar)
+                .with_recipients(vec![recipient]);
+            let mut collector = ArtifactCollector::new_archive("/", dest, vec![pattern], options)
+                .await
+                .unwrap();
+            assert_eq!(collector.collect().await.unwrap().files_collected, 1);
+            collector.finish().await.unwrap();
+            return;
+        }
+
+        let fixture = Fixture::new(&[("a.txt", b"alpha")]);
+        let dest = fixture.path("dest");
+        let temp = fixture.path("tmp");
+        let identity = age::x25519::Identity::generate();
+        let child: (String, PathBuf, String) = (
+            fixture.pattern("*.txt"),
+            dest.clone(),
+            identity.to_public().to_string(),
+        );
+
+        let test = format!(
+            "{}::test_direct_encrypted_tar_stays_in_destination",
+            module_path!().split_once("::").unwrap().1
+        );
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([test.as_str(), "--exact", "--quiet"])
+            .env(CHILD_VAR, serde_json::to_string(&child).unwrap())
+            .env("TMPDIR", &temp)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        assert!(!temp.exists());
+        let outputs: Vec<PathBuf> = std::fs::read_dir(&dest)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].to_string_lossy().ends_with(".tar.age"));
+
+        let encrypted = std::fs::File::open(&outputs[0]).unwrap();
+        let decryptor = age::Decryptor::new(encrypted).unwrap();
+        let mut archive = tar::Archive::new(
+            decryptor
+                .decrypt(std::iter::once(&identity as &dyn age::Identity))
+                .unwrap(),
+        );
+        let mut copied = None;
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.path().unwrap().ends_with("a.txt") {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                copied = Some(content);
+            }
+        }
+        assert_eq!(copied.as_deref(), Some("alpha"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_sink_collection() {
+        use crate::sink::{STREAM_MAGIC, StreamSink};
+
+        let fixture = Fixture::new(&[("a.txt", b"alpha")]);
+        let dest = fixture.path("dest");
+        let stream_path = fixture.path("collection.stream");
+        let sink = StreamSink::new(std::fs::File::create(&stream_path).unwrap()).unwrap();
+        let mut collector = ArtifactCollector::new_with_sink(
+            "/",
+            &dest,
+            vec![fixture.pattern("*.txt")],
+            Box::new(sink),
+        )
+        .await
+        .unwrap();
+        assert!(collector.is_direct_archive());
+
+        let stats = collector.collect().await.unwrap();
+        assert_eq!(stats.files_collected, 1);
+        // A stream produces no file of its own, and the scratch manifest is gone
+        assert!(collector.finish().await.unwrap().is_empty());
+        assert_eq!(std::fs::read_dir(&dest).unwrap().count(), 0);
+
+        let data = std::fs::read(&stream_path).unwrap();
+        assert!(data.starts_with(STREAM_MAGIC));
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"alpha"));
+        assert!(contains(MANIFEST_NAME.as_bytes()));
+        assert!(contains(SEAL_NAME.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_signed_manifest_seal() {
+        use crate::csv::seal::{SignatureStatus, verify_manifest_files};
+
+        let fixture = Fixture::new(&[("a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let mut collector = fixture
+            .collector("*.txt")
+            .await
+            .with_signing_key(Some(key.clone()));
+        collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+        assert_eq!(items.len(), 2);
+
+        let manifest = writer.get_file_path(MANIFEST_NAME);
+        let seal = writer.get_file_path(SEAL_NAME);
+        let report = verify_manifest_files(&manifest, &seal, Some(&key.verifying_key()))
+            .await
+            .unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.rows, 2);
+        assert_eq!(report.signature, SignatureStatus::Valid);
+
+        let tampered = std::fs::read_to_string(&manifest)
+            .unwrap()
+            .replace("a.txt", "c.txt");
+        std::fs::write(&manifest, tampered).unwrap();
+        let report = verify_manifest_files(&manifest, &seal, Some(&key.verifying_key()))
+            .await
+            .unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(report.broken_row, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_collection() {
+        let fixture = Fixture::new(&[("a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let pattern = fixture.pattern("*.txt");
+
+        let mut first = ArtifactCollector::new("/", fixture.path("first"), vec![pattern.clone()])
+            .await
+            .unwrap();
+        first.collect().await.unwrap();
+        let first_root = first.writer().full_destination().to_path_buf();
+        first.finish().await.unwrap();
+
+        fixture.write("b.txt", b"beta, longer");
+
+        let previous = PreviousCollection::load(&first_root).await.unwrap();
+        let mut second = ArtifactCollector::new("/", fixture.path("second"), vec![pattern])
+            .await
+            .unwrap()
+            .with_previous_collection(Some(previous));
+        let stats = second.collect().await.unwrap();
+        let (writer, items) = finish(second).await;
+
+        assert_eq!(stats.files_collected, 1);
+        assert_eq!(stats.files_unchanged, 1);
+
+        let unchanged = row(&items, "a.txt");
+        assert!(!unchanged.is_copied());
+        assert!(unchanged.status.starts_with(UNCHANGED_PREFIX));
+        assert!(!unchanged.hash_sha1.is_empty());
+        let changed = row(&items, "b.txt");
+        assert!(changed.is_copied());
+        assert_eq!(changed.file_size, 12);
+
+        verify(&writer, 1, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_resume_collection() {
+        let fixture = Fixture::new(&[
+            ("a.txt", b"a.txt"),
+            ("b.txt", b"b.txt"),
+            ("c.txt", b"c.txt"),
+        ]);
+
+        // Interrupted run: collected but never finished
+        let mut interrupted = fixture.collector("*.txt").await;
+        interrupted.collect().await.unwrap();
+        let writer = interrupted.writer().clone();
+        drop(interrupted);
+
+        let damaged =
+            writer.get_file_path(entry_name(&fixture.source.join("b.txt").to_string_lossy()));
+        std::fs::write(&damaged, b"torn").unwrap();
+        fixture.write("d.txt", b"d.txt");
+
+        let mut resumed =
+            ArtifactCollector::resume("/", fixture.output(), vec![fixture.pattern("*.txt")])
+                .await
+                .unwrap()
+                .with_manifest_formats(vec![ManifestFormat::Jsonl])
+                .with_dfxml(true);
+        let stats = resumed.collect().await.unwrap();
+        let (writer, items) = finish(resumed).await;
+
+        assert_eq!(stats.files_resumed, 2);
+        assert_eq!(stats.files_collected, 4);
+        assert_eq!(std::fs::read(&damaged).unwrap(), b"b.txt");
+        assert!(!writer.get_file_path(JOURNAL_NAME).exists());
+
+        assert_eq!(items.len(), 4);
+        assert!(items.iter().all(|item| item.is_copied()));
+
+        // Restored rows are exported with the artifact that found them
+        let jsonl = std::fs::read_to_string(writer.get_file_path(JSONL_MANIFEST_NAME)).unwrap();
+        let records: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        for record in &records {
+            assert_eq!(record["artifact"], fixture.pattern("*.txt").as_str());
+            let item = row(&items, record["source"]["path"].as_str().unwrap());
+            assert_eq!(record["chain_hash"], item.chain_hash.as_str());
+        }
+        let xml = std::fs::read_to_string(writer.get_file_path(DFXML_NAME)).unwrap();
+        assert_eq!(xml.matches("<fileobject>").count(), 4);
+        assert!(!writer.scratch_path("Collector_dfxml.xml.rows").exists());
+
+        verify(&writer, 4, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_known_files_skipped() {
+        let fixture = Fixture::new(&[("vendor.dll", b"abc"), ("evil.dll", b"not known")]);
+        let hash_set = fixture.path("vendor.txt");
+        std::fs::write(&hash_set, "a9993e364706816aba3e25717850c26c9cd0d89d\n").unwrap();
+
+        let known = KnownFiles::load(&[&hash_set]).unwrap();
+        let mut collector = fixture
+            .collector("*.dll")
+            .await
+            .with_known_files(Some(known));
+        let stats = collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+
+        assert_eq!(stats.files_collected, 1);
+        assert_eq!(stats.files_known, 1);
+
+        let vendor = row(&items, "vendor.dll");
+        assert!(!vendor.is_copied());
+        assert_eq!(vendor.status, format!("{}vendor", KNOWN_PREFIX));
+        assert_eq!(vendor.hash_sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // Copied once, then dropped from the staging folder
+        assert!(
+            !writer
+                .get_file_path(entry_name(&vendor.source_file))
+                .exists()
+        );
+        let evil = row(&items, "evil.dll");
+        assert!(evil.is_copied());
+        assert!(evil.status.is_empty());
+
+        verify(&writer, 1, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_known_files_skipped_direct_archive() {
+        let fixture = Fixture::new(&[("vendor.dll", b"abc"), ("evil.dll", b"not known")]);
+        let hash_set = fixture.path("vendor.txt");
+        std::fs::write(&hash_set, "a9993e364706816aba3e25717850c26c9cd0d89d\n").unwrap();
+
+        let known = KnownFiles::load(&[&hash_set]).unwrap();
+        let mut collector = ArtifactCollector::new_archive(
+            "/",
+            fixture.path("dest"),
+            vec![fixture.pattern("*.dll")],
+            ArchiveOptions::default(),
+        )
+        .await
+        .unwrap()
+        .with_known_files(Some(known));
+        let stats = collector.collect().await.unwrap();
+        assert_eq!(stats.files_known, 1);
+        let outputs = collector.finish().await.unwrap();
+
+        // A streamed ZIP cannot drop entries: vendor.dll never reaches it
+        let archive = zip::ZipArchive::new(std::fs::File::open(&outputs[0]).unwrap()).unwrap();
+        assert!(archive.file_names().any(|name| name.ends_with("evil.dll")));
+        assert!(
+            !archive
+                .file_names()
+                .any(|name| name.ends_with("vendor.dll"))
+        );
+
+        let report = crate::verify::verify_collection(&outputs[0], None)
+            .await
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!((report.matched, report.skipped), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_ioc_findings() {
+        let fixture = Fixture::new(&[("dropper.bin", b"abc"), ("clean.bin", b"clean")]);
+        let list = fixture.path("sweep.txt");
+        std::fs::write(
+            &list,
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\nfilename:DROPPER.BIN\n",
+        )
+        .unwrap();
+
+        let iocs = IocSet::load(&[&list]).unwrap();
+        let mut collector = fixture.collector("*.bin").await.with_ioc_set(Some(iocs));
+        let stats = collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+
+        assert_eq!(stats.ioc_matches, 1);
+
+        assert_eq!(row(&items, "dropper.bin").ioc, "sweep:1;sweep:2");
+        assert!(row(&items, "clean.bin").ioc.is_empty());
+
+        let report: IocReport =
+            serde_json::from_slice(&std::fs::read(writer.get_file_path(FINDINGS_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].indicators[1].value, "DROPPER.BIN");
+
+        verify(&writer, 2, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_ioc_md5_on_skipped_file() {
+        let fixture = Fixture::new(&[("dropper.bin", b"abc")]);
+        let pattern = fixture.pattern("*.bin");
+        let list = fixture.path("sweep.txt");
+        std::fs::write(&list, "md5:900150983cd24fb0d6963f7d28e17f72\n").unwrap();
+
+        let mut first = ArtifactCollector::new("/", fixture.path("first"), vec![pattern.clone()])
+            .await
+            .unwrap();
+        first.collect().await.unwrap();
+        let first_root = first.writer().full_destination().to_path_buf();
+        first.finish().await.unwrap();
+
+        // Unchanged since the first run: listed without a copy, still checked
+        let previous = PreviousCollection::load(&first_root).await.unwrap();
+        let mut second = ArtifactCollector::new("/", fixture.path("second"), vec![pattern])
+            .await
+            .unwrap()
+            .with_previous_collection(Some(previous))
+            .with_ioc_set(Some(IocSet::load(&[&list]).unwrap()));
+        let stats = second.collect().await.unwrap();
+        let (_, items) = finish(second).await;
+
+        assert_eq!(stats.files_unchanged, 1);
+        assert_eq!(stats.ioc_matches, 1);
+        let skipped = row(&items, "dropper.bin");
+        assert!(!skipped.is_copied());
+        assert_eq!(skipped.ioc, "sweep:1");
+    }
+
+    #[tokio::test]
+    async fn test_rule_scan_and_size_filter() {
+        let large_hit = [&[0u8; 64][..], b"beacon"].concat();
+        let fixture = Fixture::new(&[
+            ("small.bin", b"beacon"),
+            ("large-hit.bin", &large_hit),
+            ("large-miss.bin", &[0u8; 70]),
+        ]);
+        let rule_file = fixture.path("beacon.yar");
+        std::fs::write(
+            &rule_file,
+            "rule Beacon { strings: $a = \"beacon\" condition: $a }",
+        )
+        .unwrap();
+
+        let rules = RuleSet::load(&[&rule_file])
+            .unwrap()
+            .with_size_filter(Some(32));
+        let mut collector = fixture.collector("*.bin").await.with_rules(Some(rules));
+        let stats = collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+
+        assert_eq!(stats.files_collected, 2);
+        assert_eq!(stats.rule_matches, 2);
+        assert_eq!(stats.files_filtered, 1);
+
+        let miss = row(&items, "large-miss.bin");
+        assert!(!miss.is_copied());
+        assert_eq!(miss.status, NO_MATCH_STATUS);
+        assert_eq!(row(&items, "small.bin").rules, "Beacon");
+        assert_eq!(row(&items, "large-hit.bin").rules, "Beacon");
+
+        let report: ScanReport = serde_json::from_slice(
+            &std::fs::read(writer.get_file_path(SCAN_FINDINGS_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(report.files_scanned, 3);
+        let hit = report
+            .findings
+            .iter()
+            .find(|f| f.source_file.ends_with("large-hit.bin"))
+            .unwrap();
+        assert_eq!(hit.matches[0].strings[0].offsets, vec![64]);
+
+        verify(&writer, 2, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_timeline_exports() {
+        let fixture = Fixture::new(&[("a.txt", b"a"), ("b.txt", b"bb")]);
+        let mut collector = fixture.collector("*.txt").await.with_timeline(true);
+        collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+        assert_eq!(items.len(), 2);
+
+        let body = std::fs::read_to_string(writer.get_file_path(BODYFILE_NAME)).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let fields: Vec<&str> = lines[1].split('|').collect();
+        assert_eq!(fields.len(), 11);
+        assert!(fields[1].ends_with("b.txt"));
+        assert_eq!(fields[6], "2");
+
+        let jsonl = std::fs::read_to_string(writer.get_file_path(TIMELINE_NAME)).unwrap();
+        assert!(jsonl.lines().count() >= 2);
+        for line in jsonl.lines() {
+            let event: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(event["datetime"].is_string());
+            assert!(event["timestamp_desc"].is_string());
+        }
+
+        verify(&writer, 2, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_timeline_exports_direct_archive() {
+        use std::io::Read;
+
+        let fixture = Fixture::new(&[("a.txt", b"a"), ("b.txt", b"bb")]);
+        let dest = fixture.path("dest");
+        let mut collector = ArtifactCollector::new_archive(
+            "/",
+            &dest,
+            vec![fixture.pattern("*.txt")],
+            ArchiveOptions::default(),
+        )
+        .await
+        .unwrap()
+        .with_timeline(true);
+        collector.collect().await.unwrap();
+        let outputs = collector.finish().await.unwrap();
+
+        // The scratch exports were moved into the archive
+        assert_eq!(std::fs::read_dir(&dest).unwrap().count(), 1);
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&outputs[0]).unwrap()).unwrap();
+        let mut body = String::new();
+        archive
+            .by_name(BODYFILE_NAME)
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body.lines().count(), 2);
+
+        let report = crate::verify::verify_collection(&outputs[0], None)
+            .await
+            .unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.reports_matched, 3);
+    }
+
+    #[tokio::test]
+    async fn test_dfxml_export() {
+        let fixture = Fixture::new(&[("a.txt", b"abc")]);
+        let mut collector = fixture.collector("*.txt").await.with_dfxml(true);
+        collector.collect().await.unwrap();
+        let (writer, _) = finish(collector).await;
+
+        let xml = std::fs::read_to_string(writer.get_file_path(DFXML_NAME)).unwrap();
+        assert_eq!(xml.matches("<fileobject>").count(), 1);
+        assert!(xml.contains("<filesize>3</filesize>"));
+        assert!(xml.contains(
+            "<hashdigest type=\"sha1\">a9993e364706816aba3e25717850c26c9cd0d89d</hashdigest>"
+        ));
+        assert!(!writer.get_file_path(BODYFILE_NAME).exists());
+
+        verify(&writer, 1, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_case_uco_export() {
+        let fixture = Fixture::new(&[("a.txt", b"abc")]);
+        let mut collector = fixture.collector("*.txt").await.with_case_uco(true);
+        collector.collect().await.unwrap();
+        let (writer, _) = finish(collector).await;
+
+        let data = std::fs::read(writer.get_file_path(CASE_UCO_NAME)).unwrap();
+        let bundle: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        let graph = bundle["@graph"].as_array().unwrap();
+        let action = graph
+            .iter()
+            .find(|node| node["@type"] == "case-investigation:InvestigativeAction")
+            .unwrap();
+        assert!(action["uco-action:endTime"]["@value"].is_string());
+        assert_eq!(action["uco-action:result"].as_array().unwrap().len(), 1);
+        assert!(
+            String::from_utf8_lossy(&data).contains("a9993e364706816aba3e25717850c26c9cd0d89d")
+        );
+
+        verify(&writer, 1, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_manifest_formats() {
+        let fixture = Fixture::new(&[("a.txt", b"abc"), ("b.txt", b"bb")]);
+        let mut collector = fixture
+            .collector("*.txt")
+            .await
+            .with_manifest_formats(vec![ManifestFormat::Jsonl, ManifestFormat::Sqlite]);
+        collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+
+        let jsonl = std::fs::read_to_string(writer.get_file_path(JSONL_MANIFEST_NAME)).unwrap();
+        let records: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["chain_hash"], items[1].chain_hash.as_str());
+        assert_eq!(records[0]["artifact"], fixture.pattern("*.txt").as_str());
+        assert!(records[0]["source"]["modified"].is_string());
+        assert_eq!(
+            records[0]["hashes"]["sha1"],
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+
+        let conn = rusqlite::Connection::open(writer.get_file_path(SQLITE_MANIFEST_NAME)).unwrap();
+        let files: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM files WHERE artifact_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(files, 2);
+
+        verify(&writer, 2, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_files_reported() {
+        let fixture = Fixture::new(&[("a.txt", b"abc"), ("b.txt", b"bb")]);
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut collector = fixture
+            .collector("*.txt")
+            .await
+            .with_event_handler(fixture.vanish("b.txt"))
+            .with_event_handler(sender);
+        let stats = collector.collect().await.unwrap();
+        assert_eq!(stats.files_collected, 1);
+        assert_eq!(stats.failed_extractions, 1);
+
+        let failure = &collector.failures()[0];
+        assert_eq!(collector.failures().len(), 1);
+        assert_eq!(failure.error, "FileRead");
+        assert_eq!(failure.os_error, Some(2));
+        assert_eq!(failure.methods, vec![ExtractionMethod::Filesystem]);
+        let (writer, items) = finish(collector).await;
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(
+            events[0],
+            CollectionEvent::PlanReady { files: 2, .. }
+        ));
+        assert!(matches!(
+            events[1],
+            CollectionEvent::ArtifactStarted { files: 2, .. }
+        ));
+        assert!(matches!(
+            events[2],
+            CollectionEvent::FileStarted {
+                index: 1,
+                total: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[3],
+            CollectionEvent::FileFinished {
+                bytes: 3,
+                method: ExtractionMethod::Filesystem,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[4],
+            CollectionEvent::FileStarted { index: 2, .. }
+        ));
+        assert!(matches!(events[5], CollectionEvent::FileFailed { .. }));
+        assert!(matches!(events[6], CollectionEvent::Finished { .. }));
+        assert_eq!(events.len(), 7);
+
+        assert_eq!(items.len(), 2);
+        assert!(!items[1].is_copied());
+        assert!(items[1].destination_file.is_empty());
+        assert!(items[1].status.starts_with("failed: FileRead"));
+
+        let report: FailureReport =
+            serde_json::from_slice(&std::fs::read(writer.get_file_path(FAILURES_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(report.files_failed, 1);
+        assert_eq!(report.by_reason[0].count, 1);
+
+        verify(&writer, 1, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_direct_archive_failed_entry() {
+        let fixture = Fixture::new(&[("a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let mut collector = ArtifactCollector::new_archive(
+            "/",
+            fixture.path("dest"),
+            vec![fixture.pattern("*.txt")],
+            ArchiveOptions::default(),
+        )
+        .await
+        .unwrap()
+        .with_event_handler(fixture.vanish("b.txt"));
+        collector.collect().await.unwrap();
+        assert_eq!(collector.failures().len(), 1);
+        let outputs = collector.finish().await.unwrap();
+
+        // The streamed archive keeps the empty b.txt entry, listed as failed
+        let contents = crate::verify::read_collection_manifest(&outputs[0]).unwrap();
+        let items = read_manifest_items(&contents).await.unwrap();
+        let failed = row(&items, "b.txt");
+        assert!(failed.destination_file.ends_with("b.txt"));
+        assert!(failed.status.starts_with(FAILED_PREFIX));
+        assert!(!failed.is_copied());
+
+        let report = crate::verify::verify_collection(&outputs[0], None)
+            .await
+            .unwrap();
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.skipped, 1);
+        assert!(report.extra.is_empty());
+        assert!(report.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_collection() {
+        let fixture = Fixture::new(&[
+            ("a.txt", b"a.txt"),
+            ("b.txt", b"b.txt"),
+            ("c.txt", b"c.txt"),
+        ]);
+
+        // Cancel while b.txt is being copied
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let mut collector = fixture
+            .collector("*.txt")
+            .await
+            .with_cancel_token(token)
+            .with_event_handler(move |event: &CollectionEvent| {
+                if let CollectionEvent::FileStarted { index: 2, .. } = event {
+                    cancel.cancel();
+                }
+            });
+        let stats = collector.collect().await.unwrap();
+
+        assert!(collector.is_cancelled());
+        assert_eq!(stats.files_collected, 1);
+        assert!(collector.failures().is_empty());
+        let (writer, items) = finish(collector).await;
+
+        assert_eq!(items.len(), 1);
+        assert!(items[0].source_file.ends_with("a.txt"));
+        assert!(!writer.get_file_path(JOURNAL_NAME).exists());
+
+        let seal = std::fs::read(writer.get_file_path(SEAL_NAME)).unwrap();
+        assert!(ManifestSeal::from_json(&seal).unwrap().metadata.cancelled);
+        let case = std::fs::read(writer.get_file_path(CASE_METADATA_NAME)).unwrap();
+        let case: CaseMetadata = serde_json::from_slice(&case).unwrap();
+        assert!(case.cancelled);
+
+        let report = verify(&writer, 1, 0).await;
+        assert!(report.manifest.unwrap().cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_collection() {
+        let fixture = Fixture::new(&[
+            ("a.bin", &[1u8; 20_000]),
+            ("b.txt", b"b.txt"),
+            ("c.txt", b"c.txt"),
+        ]);
+
+        let throttle = ThrottleOptions::new()
+            .with_bytes_per_sec(Some(100_000))
+            .with_files_per_sec(Some(20.0));
+        let mut collector = fixture.collector("*").await.with_throttle(throttle);
+
+        // The third file waits 100 ms for the file rate, and a.bin 100 ms
+        // for its second half
+        let timer = std::time::Instant::now();
+        let stats = collector.collect().await.unwrap();
+        assert!(timer.elapsed() >= std::time::Duration::from_millis(100));
+        assert_eq!(stats.files_collected, 3);
+        assert_eq!(stats.bytes_collected, 20_010);
+
+        let (writer, items) = finish(collector).await;
+        assert_eq!(row(&items, "a.bin").file_size, 20_000);
+        verify(&writer, 3, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_throttled_copy_leaves_runtime_free() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let fixture = Fixture::new(&[("a.bin", &[1u8; 30_000])]);
+        let throttle = ThrottleOptions::new().with_bytes_per_sec(Some(100_000));
+        let mut collector = fixture.collector("*").await.with_throttle(throttle);
+
+        // The test runtime has a single thread, which the paced copy of about
+        // 200 ms must not hold
+        let ticks = Arc::new(AtomicU32::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+        let timer = std::time::Instant::now();
+        collector.collect().await.unwrap();
+        let elapsed = timer.elapsed();
+        ticker.abort();
+
+        assert!(elapsed >= std::time::Duration::from_millis(150));
+        assert!(ticks.load(Ordering::Relaxed) >= 5);
+    }
+
+    #[tokio::test]
+    async fn test_inventory_mode() {
+        let fixture = Fixture::new(&[("etc/hosts", b"abc"), ("etc/conf.d/net", b"net")]);
+
+        let inventory = InventoryOptions::new()
+            .with_roots(vec!["etc"])
+            .with_hash(true);
+        let mut collector = ArtifactCollector::new(&fixture.source, fixture.output(), Vec::new())
+            .await
+            .unwrap()
+            .with_inventory(Some(inventory))
+            .with_timeline(true);
+        assert_eq!(collector.count_files(), 2);
+        let stats = collector.collect().await.unwrap();
+        let (writer, items) = finish(collector).await;
+
+        assert_eq!(stats.files_collected, 0);
+        assert_eq!(stats.files_inventoried, 2);
+        assert_eq!(stats.bytes_inventoried, 6);
+        assert_eq!(stats.bytes_hashed, 6);
+
+        let hosts = row(&items, "hosts");
+        assert!(!hosts.is_copied());
+        assert_eq!(hosts.status, INVENTORY_STATUS);
+        assert_eq!(hosts.hash_sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert!(
+            !writer
+                .get_file_path(entry_name(&hosts.source_file))
+                .exists()
+        );
+
+        let body = std::fs::read_to_string(writer.get_file_path(BODYFILE_NAME)).unwrap();
+        assert_eq!(body.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_case_metadata_document() {
+        use crate::case::CaseInfo;
+
+        let fixture = Fixture::new(&[("a.txt", b"alpha")]);
+        let case = CaseInfo::new().with_case_number(Some("CASE-7".into()));
+        let mut collector = fixture
+            .collector("*.txt")
+            .await
+            .with_case_metadata(CaseMetadata::new(case));
+        collector.collect().await.unwrap();
+        let (writer, _) = finish(collector).await;
+
+        let case_json = std::fs::read(writer.get_file_path(CASE_METADATA_NAME)).unwrap();
+        let metadata: CaseMetadata = serde_json::from_slice(&case_json).unwrap();
+        assert_eq!(metadata.case.case_number.as_deref(), Some("CASE-7"));
+        assert_eq!(metadata.stats.unwrap().files_collected, 1);
+        assert!(metadata.finished_at.is_some());
+
+        let seal = std::fs::read(writer.get_file_path(SEAL_NAME)).unwrap();
+        let seal = ManifestSeal::from_json(&seal).unwrap();
+        assert_eq!(
+            seal.metadata.case_metadata_sha256,
+            Some(hex::encode(Sha256::digest(&case_json)))
+        );
     }
 }
@@ -0,0 +1,264 @@
+//! Exports written row by row while collecting, like the CSV manifest: the
+//! bodyfile, the Timesketch timeline, and the JSON Lines and SQLite
+//! manifests. A staging collection writes them in its folder under their
+//! final names; a collection streamed to a sink writes them next to the
+//! scratch manifest and adds them to the sink at the end.
+//!
+//! DFXML and CASE/UCO documents wrap their rows, so the rows are spooled to
+//! scratch files and the documents assembled from them at the end.
+
+use std::fs;
+use std::io::{self, BufReader, BufWriter as StdBufWriter};
+use std::path::{Path, PathBuf};
+
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::case::CaseMetadata;
+use crate::csv::CsvLogItem;
+use crate::dfxml;
+use crate::error::{CollectorError, Result};
+use crate::failure::FileFailure;
+use crate::manifest::{ArtifactIndex, ManifestRecord, SqliteManifest, write_jsonl_record};
+use crate::platform::{
+    BODYFILE_NAME, CASE_UCO_NAME, DFXML_NAME, JSONL_MANIFEST_NAME, SQLITE_MANIFEST_NAME,
+    TIMELINE_NAME,
+};
+use crate::timeline::{TimelineEntry, write_bodyfile_line, write_timesketch_events};
+use crate::uco::{self, UcoIds};
+
+/// Exports to write.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    pub timeline: bool,
+    pub dfxml: bool,
+    pub case_uco: bool,
+    pub jsonl: bool,
+    pub sqlite: bool,
+}
+
+/// Export file being written.
+struct ExportFile {
+    name: &'static str,
+    path: PathBuf,
+    out: BufWriter<File>,
+}
+
+impl ExportFile {
+    async fn create(name: &'static str, path: PathBuf) -> Result<Self> {
+        let file = File::create(&path)
+            .await
+            .map_err(|e| CollectorError::FileWrite {
+                path: path.clone(),
+                source: e,
+            })?;
+        Ok(Self {
+            name,
+            path,
+            out: BufWriter::new(file),
+        })
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.out
+            .write_all(data)
+            .await
+            .map_err(|e| CollectorError::FileWrite {
+                path: self.path.clone(),
+                source: e,
+            })
+    }
+
+    /// Flush the file, returning its name and location.
+    async fn finish(mut self) -> Result<(&'static str, PathBuf)> {
+        self.out
+            .shutdown()
+            .await
+            .map_err(|e| CollectorError::FileWrite {
+                path: self.path.clone(),
+                source: e,
+            })?;
+        Ok((self.name, self.path))
+    }
+}
+
+/// Rows of a document, spooled until the document is written at `path`.
+struct Spool {
+    rows: ExportFile,
+    path: PathBuf,
+}
+
+impl Spool {
+    async fn create(name: &'static str, path: PathBuf, spool: PathBuf) -> Result<Self> {
+        Ok(Self {
+            rows: ExportFile::create(name, spool).await?,
+            path,
+        })
+    }
+
+    /// Write the document with `write` given the spooled rows, then remove
+    /// the spool.
+    async fn finish<F>(self, write: F) -> Result<(&'static str, PathBuf)>
+    where
+        F: FnOnce(&mut StdBufWriter<fs::File>, &Path) -> io::Result<()> + Send + 'static,
+    {
+        let (name, spool) = self.rows.finish().await?;
+        let path = self.path;
+        let written = tokio::task::spawn_blocking(move || {
+            let mut out = StdBufWriter::new(fs::File::create(&path)?);
+            write(&mut out, &spool)?;
+            out.into_inner().map_err(|e| e.into_error())?;
+            fs::remove_file(&spool)?;
+            Ok::<_, io::Error>(path)
+        })
+        .await
+        .map_err(|e| CollectorError::Io(io::Error::other(e)))?;
+        let path = written.map_err(CollectorError::Io)?;
+        Ok((name, path))
+    }
+}
+
+/// Exports of the rows of a collection.
+pub struct Exports {
+    bodyfile: Option<ExportFile>,
+    timesketch: Option<ExportFile>,
+    jsonl: Option<ExportFile>,
+    sqlite: Option<SqliteManifest>,
+    dfxml: Option<Spool>,
+    case_uco: Option<(UcoIds, Spool)>,
+    line: Vec<u8>,
+}
+
+impl Exports {
+    /// Open the enabled exports, each at the location `path` gives its name.
+    /// Rows of the DFXML and CASE/UCO documents go to the location `spool`
+    /// gives.
+    pub async fn create(
+        options: ExportOptions,
+        path: impl Fn(&str) -> PathBuf,
+        spool: impl Fn(&str) -> PathBuf,
+    ) -> Result<Self> {
+        let open = |name: &'static str| ExportFile::create(name, path(name));
+        let open_spool = |name: &'static str| Spool::create(name, path(name), spool(name));
+        let mut exports = Self {
+            bodyfile: None,
+            timesketch: None,
+            jsonl: None,
+            sqlite: None,
+            dfxml: None,
+            case_uco: None,
+            line: Vec::new(),
+        };
+        if options.timeline {
+            exports.bodyfile = Some(open(BODYFILE_NAME).await?);
+            exports.timesketch = Some(open(TIMELINE_NAME).await?);
+        }
+        if options.jsonl {
+            exports.jsonl = Some(open(JSONL_MANIFEST_NAME).await?);
+        }
+        if options.sqlite {
+            exports.sqlite = Some(SqliteManifest::create(path(SQLITE_MANIFEST_NAME))?);
+        }
+        if options.dfxml {
+            exports.dfxml = Some(open_spool(DFXML_NAME).await?);
+        }
+        if options.case_uco {
+            exports.case_uco = Some((UcoIds::new(), open_spool(CASE_UCO_NAME).await?));
+        }
+        Ok(exports)
+    }
+
+    /// Whether any export needs the rows.
+    pub fn is_empty(&self) -> bool {
+        self.bodyfile.is_none()
+            && self.timesketch.is_none()
+            && self.jsonl.is_none()
+            && self.sqlite.is_none()
+            && self.dfxml.is_none()
+            && self.case_uco.is_none()
+    }
+
+    /// Append a row, with its source metadata and the artifacts its file was
+    /// found by. `item` carries the chain hash of the row.
+    pub async fn add(
+        &mut self,
+        item: &CsvLogItem,
+        entry: &TimelineEntry,
+        artifacts: &ArtifactIndex,
+    ) -> Result<()> {
+        if let Some(ref mut bodyfile) = self.bodyfile {
+            self.line.clear();
+            write_bodyfile_line(&mut self.line, entry).map_err(CollectorError::Io)?;
+            bodyfile.write(&self.line).await?;
+        }
+        if let Some(ref mut timesketch) = self.timesketch {
+            self.line.clear();
+            write_timesketch_events(&mut self.line, entry).map_err(CollectorError::Io)?;
+            timesketch.write(&self.line).await?;
+        }
+        if self.jsonl.is_some() || self.sqlite.is_some() {
+            let artifact = artifacts.get(&item.source_file);
+            let record = ManifestRecord::new(item, Some(entry), artifact);
+            if let Some(ref mut jsonl) = self.jsonl {
+                self.line.clear();
+                write_jsonl_record(&mut self.line, &record).map_err(CollectorError::Io)?;
+                jsonl.write(&self.line).await?;
+            }
+            if let Some(ref mut sqlite) = self.sqlite {
+                sqlite.add(&record, artifacts)?;
+            }
+        }
+        if let Some(ref mut dfxml) = self.dfxml {
+            self.line.clear();
+            dfxml::write_fileobject(&mut self.line, entry).map_err(CollectorError::Io)?;
+            dfxml.rows.write(&self.line).await?;
+        }
+        if let Some((ref ids, ref mut case_uco)) = self.case_uco {
+            self.line.clear();
+            uco::write_entry(&mut self.line, ids, entry).map_err(CollectorError::Io)?;
+            case_uco.rows.write(&self.line).await?;
+        }
+        Ok(())
+    }
+
+    /// Flush every export and write the documents around the spooled rows,
+    /// returning their names and locations. The SQLite manifest also gets
+    /// the run, the artifacts and the failures.
+    pub async fn finish(
+        self,
+        case: &CaseMetadata,
+        artifacts: &ArtifactIndex,
+        failures: &[FileFailure],
+    ) -> Result<Vec<(&'static str, PathBuf)>> {
+        let mut files = Vec::new();
+        for file in [self.bodyfile, self.timesketch, self.jsonl]
+            .into_iter()
+            .flatten()
+        {
+            files.push(file.finish().await?);
+        }
+        if let Some(sqlite) = self.sqlite {
+            let path = sqlite.finish(case, artifacts, failures)?;
+            files.push((SQLITE_MANIFEST_NAME, path));
+        }
+        if let Some(spool) = self.dfxml {
+            let case = case.clone();
+            let document = spool.finish(move |out, rows| {
+                dfxml::write_header(out, &case)?;
+                io::copy(&mut fs::File::open(rows)?, out)?;
+                dfxml::write_footer(out)
+            });
+            files.push(document.await?);
+        }
+        if let Some((ids, spool)) = self.case_uco {
+            let case = case.clone();
+            let document = spool.finish(move |out, rows| {
+                uco::write_document(out, &case, &ids, || {
+                    Ok(BufReader::new(fs::File::open(rows)?))
+                })
+            });
+            files.push(document.await?);
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::csv::{CsvLogItem, read_manifest_items};
+use crate::error::{CollectorError, Result};
+use crate::failure::FAILED_PREFIX;
+use crate::sink::EntryMetadata;
+use crate::verify::read_collection_manifest;
+
+/// Status prefix of files skipped because they did not change.
+pub const UNCHANGED_PREFIX: &str = "unchanged since ";
+
+/// Manifest of an earlier collection, used to skip files that did not change
+/// since then.
+#[derive(Debug, Clone, Default)]
+pub struct PreviousCollection {
+    items: HashMap<String, CsvLogItem>,
+}
+
+impl PreviousCollection {
+    /// Load the manifest of a collection folder, archive or manifest CSV.
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let manifest = tokio::task::spawn_blocking(move || read_collection_manifest(&path))
+            .await
+            .map_err(|e| CollectorError::CollectionFailed(e.to_string()))??;
+
+        Ok(Self::from_items(read_manifest_items(&manifest).await?))
+    }
+
+    /// Files that failed are left out, so they are collected again.
+    pub fn from_items(items: Vec<CsvLogItem>) -> Self {
+        Self {
+            items: items
+                .into_iter()
+                .filter(|item| !item.status.starts_with(FAILED_PREFIX))
+                .map(|item| (item.source_file.clone(), item))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Previous row of `source` if its size, mtime and inode are unchanged.
+    pub fn unchanged(&self, source: &str, metadata: &EntryMetadata) -> Option<&CsvLogItem> {
+        let previous = self.items.get(source)?;
+        let modified = metadata.modified?.to_string();
+        let inode = metadata.inode.map(|i| i.to_string()).unwrap_or_default();
+
+        (previous.file_size == metadata.size
+            && previous.modified_time == modified
+            && previous.inode == inode)
+            .then_some(previous)
+    }
+}
+
+/// Run a skipped file was last copied in: the collect time of its previous
+/// row, or the run that row itself pointed to.
+pub fn copied_in(previous: &CsvLogItem) -> &str {
+    previous
+        .status
+        .strip_prefix(UNCHANGED_PREFIX)
+        .unwrap_or(&previous.collect_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+
+    fn metadata(size: u64, mtime: i64, inode: u64) -> EntryMetadata {
+        EntryMetadata {
+            size,
+            modified: Some(FileTime::from_unix_time(mtime, 0)),
+            inode: Some(inode),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unchanged_file() {
+        let current = metadata(10, 1000, 42);
+        let mut item = CsvLogItem::with_paths("/var/log/syslog", "var/log/syslog")
+            .with_size(10)
+            .with_inode(Some(42));
+        item.modified_time = current.modified.unwrap().to_string();
+        let previous = PreviousCollection::from_items(vec![item]);
+
+        assert!(previous.unchanged("/var/log/syslog", &current).is_some());
+        assert!(
+            previous
+                .unchanged("/var/log/syslog", &metadata(11, 1000, 42))
+                .is_none()
+        );
+        assert!(
+            previous
+                .unchanged("/var/log/syslog", &metadata(10, 1001, 42))
+                .is_none()
+        );
+        assert!(
+            previous
+                .unchanged("/var/log/syslog", &metadata(10, 1000, 43))
+                .is_none()
+        );
+        assert!(previous.unchanged("/var/log/other", &current).is_none());
+    }
+
+    #[test]
+    fn test_failed_file_not_unchanged() {
+        let current = metadata(10, 1000, 42);
+        let mut item = CsvLogItem::with_paths("/var/log/syslog", "")
+            .with_size(10)
+            .with_inode(Some(42))
+            .with_status(format!("{}FileRead: permission denied", FAILED_PREFIX));
+        item.modified_time = current.modified.unwrap().to_string();
+        let previous = PreviousCollection::from_items(vec![item]);
+
+        assert!(previous.is_empty());
+        assert!(previous.unchanged("/var/log/syslog", &current).is_none());
+    }
+
+    #[test]
+    fn test_copied_in_follows_previous_skip() {
+        let mut item = CsvLogItem::with_paths("/a", "a");
+        item.collect_time = "run-2".into();
+        assert_eq!(copied_in(&item), "run-2");
+
+        let item = item.with_status(format!("{}run-1", UNCHANGED_PREFIX));
+        assert_eq!(copied_in(&item), "run-1");
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::cancel::CancelToken;
+
+/// Status of rows listed by an inventory, without content.
+pub const INVENTORY_STATUS: &str = "inventory";
+
+/// Metadata-only walk: every file is listed in the manifest (and the
+/// timeline exports) but no content is copied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InventoryOptions {
+    /// Folders walked recursively, relative to the source. The resource
+    /// patterns are used when empty.
+    pub roots: Vec<PathBuf>,
+    /// Hash every file (SHA1), reading its content once
+    pub hash: bool,
+}
+
+impl InventoryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_roots<P: Into<PathBuf>>(mut self, roots: Vec<P>) -> Self {
+        self.roots = roots.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_hash(mut self, hash: bool) -> Self {
+        self.hash = hash;
+        self
+    }
+}
+
+/// Every file under `root`, without following links. Unreadable folders are
+/// logged and left out; the walk stops early once `cancel` is set.
+pub(crate) fn walk_files(root: &Path, cancel: &CancelToken) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::warn!("Inventory walk: {}", e);
+                None
+            }
+        })
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        std::fs::write(temp_dir.path().join("a/one"), b"1").unwrap();
+        std::fs::write(temp_dir.path().join("a/b/two"), b"2").unwrap();
+
+        let mut files = walk_files(&temp_dir.path().join("a"), &CancelToken::new());
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                temp_dir.path().join("a/b/two"),
+                temp_dir.path().join("a/one")
+            ]
+        );
+        assert!(walk_files(&temp_dir.path().join("missing"), &CancelToken::new()).is_empty());
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert!(walk_files(&temp_dir.path().join("a"), &cancel).is_empty());
+    }
+}
@@ -1,5 +1,16 @@
 mod collector;
+mod exports;
+mod incremental;
+mod inventory;
+mod journal;
 mod vss_collector;
 
-pub use collector::{ArtifactCollector, CollectionStats};
+pub use collector::{
+    ArtifactCollector, BODYFILE_NAME, CASE_METADATA_NAME, CASE_UCO_NAME, CollectionStats,
+    DFXML_NAME, FAILURES_NAME, FINDINGS_NAME, JSONL_MANIFEST_NAME, MANIFEST_NAME,
+    SCAN_FINDINGS_NAME, SEAL_NAME, SQLITE_MANIFEST_NAME, TIMELINE_NAME,
+};
+pub use incremental::{PreviousCollection, UNCHANGED_PREFIX};
+pub use inventory::{INVENTORY_STATUS, InventoryOptions};
+pub use journal::JOURNAL_NAME;
 pub use vss_collector::VssCollector;
@@ -82,6 +82,63 @@ impl VssCollector {
         Ok(combined_stats)
     }
 
+    /// Collect every snapshot into an existing collector, so VSS files end up
+    /// in the same output (and manifest) as the live collection.
+    pub async fn collect_into(
+        &mut self,
+        collector: &mut ArtifactCollector,
+    ) -> Result<CollectionStats> {
+        require_admin()?;
+
+        let vss = Vss::new(&self.drive_letter);
+        let snapshots = vss.get_snapshots()?;
+
+        log::info!(
+            "Found {} VSS snapshots for {}",
+            snapshots.len(),
+            self.drive_letter
+        );
+
+        let temp_dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&temp_dir).await?;
+        self.temp_dir = Some(temp_dir.clone());
+
+        let mut combined_stats = CollectionStats::default();
+
+        for snapshot in snapshots {
+            log::info!(
+                "Processing snapshot: {}",
+                snapshot.snapshot_id().unwrap_or("unknown")
+            );
+
+            let mount_point = match Vss::mount_snapshot(&snapshot, &temp_dir).await {
+                Ok(mount_point) if mount_point.is_symlink() => mount_point,
+                Ok(_) => {
+                    log::warn!("Failed to mount snapshot");
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("Failed to collect from snapshot: {}", e);
+                    continue;
+                }
+            };
+
+            match collector
+                .collect_from_snapshot(&mount_point, snapshot.clone())
+                .await
+            {
+                Ok(stats) => combined_stats.merge(&stats),
+                Err(e) => log::error!("Failed to collect from snapshot: {}", e),
+            }
+        }
+
+        if let Err(e) = fs::remove_dir_all(&temp_dir).await {
+            log::warn!("Failed to cleanup temp dir: {}", e);
+        }
+
+        Ok(combined_stats)
+    }
+
     async fn collect_from_snapshot(
         &self,
         snapshot: &VssSnapshot,
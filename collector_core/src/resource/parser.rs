@@ -1,5 +1,6 @@
 use glob::glob;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use tokio::fs;
@@ -14,6 +15,8 @@ pub struct ResourcesParser {
     resource_path: FormatSource,
     artifact_patterns: Vec<String>,
     processed_artifacts: HashSet<String>,
+    /// Artifacts contributing paths to the last selection, in order
+    selected_artifacts: Vec<String>,
 }
 
 impl ResourcesParser {
@@ -33,6 +36,7 @@ impl ResourcesParser {
             resource_path: format_path,
             artifact_patterns: Vec::new(),
             processed_artifacts: HashSet::new(),
+            selected_artifacts: Vec::new(),
         })
     }
 
@@ -51,6 +55,38 @@ impl ResourcesParser {
             .unwrap_or_default()
     }
 
+    /// SHA256 over the resource files, in path order, each contributing its
+    /// path relative to the resource directory, a NUL byte and its content.
+    pub async fn resource_set_hash(&self) -> Result<String> {
+        let root = self
+            .resource_path
+            .as_path()
+            .ancestors()
+            .nth(2)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut files = self.get_yaml_files();
+        files.sort();
+
+        let mut hasher = Sha256::new();
+        for file_path in files {
+            let content = fs::read(&file_path)
+                .await
+                .map_err(|e| CollectorError::FileRead {
+                    path: file_path.clone(),
+                    source: e,
+                })?;
+            let relative = file_path.strip_prefix(&root).unwrap_or(&file_path);
+
+            hasher.update(relative.to_string_lossy().replace('\\', "/").as_bytes());
+            hasher.update([0u8]);
+            hasher.update(&content);
+        }
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+
     pub async fn get_doc_struct(&self) -> Result<Vec<YamlArtifact>> {
         let mut artifacts = Vec::new();
 
@@ -132,6 +168,7 @@ impl ResourcesParser {
     ) -> Result<Vec<String>> {
         self.artifact_patterns.clear();
         self.processed_artifacts.clear();
+        self.selected_artifacts.clear();
 
         self.resolve_artifacts_recursive(&artifact_names, all_artifacts)?;
 
@@ -160,6 +197,7 @@ impl ResourcesParser {
             }
 
             if let Some(paths) = &artifact.artifact.path {
+                self.selected_artifacts.push(name.clone());
                 for path in paths {
                     let normalized = normalize_artifact_path(path);
                     if !self.artifact_patterns.contains(&normalized) {
@@ -172,6 +210,12 @@ impl ResourcesParser {
         Ok(())
     }
 
+    /// Names of the artifacts the last `select_artifact` resolved to, with
+    /// groups such as `All` expanded
+    pub fn selected_artifacts(&self) -> &[String] {
+        &self.selected_artifacts
+    }
+
     pub fn get_artifact_names(artifacts: &[YamlArtifact]) -> Vec<String> {
         artifacts.iter().map(|a| a.metadata.name.clone()).collect()
     }
@@ -296,6 +340,7 @@ mod tests {
             resource_path: FormatSource::new("."),
             artifact_patterns: Vec::new(),
             processed_artifacts: HashSet::new(),
+            selected_artifacts: Vec::new(),
         };
 
         let result = parser.select_artifact(vec!["MFT".to_string()], &artifacts);
@@ -318,6 +363,7 @@ mod tests {
             resource_path: FormatSource::new("."),
             artifact_patterns: Vec::new(),
             processed_artifacts: HashSet::new(),
+            selected_artifacts: Vec::new(),
         };
 
         let result = parser.select_artifact(vec!["NTFS".to_string()], &artifacts);
@@ -327,6 +373,7 @@ mod tests {
         assert_eq!(patterns.len(), 2);
         assert!(patterns.contains(&"$MFT".to_string()));
         assert!(patterns.contains(&"$Extend\\$UsnJrnl".to_string()));
+        assert_eq!(parser.selected_artifacts(), ["MFT", "USN"]);
     }
 
     #[test]
@@ -337,6 +384,7 @@ mod tests {
             resource_path: FormatSource::new("."),
             artifact_patterns: Vec::new(),
             processed_artifacts: HashSet::new(),
+            selected_artifacts: Vec::new(),
         };
 
         let result = parser.select_artifact(vec!["NonExistent".to_string()], &artifacts);
@@ -372,4 +420,20 @@ mod tests {
         assert!(categories.contains_key("Cat1"));
         assert!(categories.contains_key("Cat2"));
     }
+
+    #[tokio::test]
+    async fn test_resource_set_hash() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("a.yaml"), "a").unwrap();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        std::fs::write(temp_dir.path().join("sub/b.yaml"), "b").unwrap();
+
+        let parser = ResourcesParser::new(temp_dir.path()).unwrap();
+        let first = parser.resource_set_hash().await.unwrap();
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, parser.resource_set_hash().await.unwrap());
+
+        std::fs::write(temp_dir.path().join("sub/b.yaml"), "c").unwrap();
+        assert_ne!(first, parser.resource_set_hash().await.unwrap());
+    }
 }
@@ -0,0 +1,428 @@
+//! Content scanning of collected files with YARA-style rules.
+//!
+//! Rules are written in a subset of the YARA language, parsed and matched
+//! here without libyara:
+//!
+//! - `rule Name : tags { meta: … strings: … condition: … }`, with `//` and
+//!   `/* */` comments
+//! - text strings with the `nocase`, `ascii` and `wide` modifiers, hex
+//!   strings with wildcards (`??`, `4?`), jumps (`[2-4]`) and alternatives,
+//!   and regexes with the `i` and `s` flags
+//! - conditions made of `and`, `or`, `not`, parentheses, `true`, `false`,
+//!   `$a`, `#a > N`, `filesize < 1MB` and `any|all|none|N of them|($a, $b*)`
+//!
+//! Modules, includes, `fullword`, `xor`, `base64`, offsets (`@a`, `at`,
+//! `in`) and anonymous strings are rejected when loading. Each string counts
+//! non-overlapping matches only, and since files are scanned as they are
+//! streamed, a regex or a hex string with jumps only matches across chunk
+//! boundaries within a window of [`REGEX_WINDOW`] bytes.
+
+mod parser;
+mod rule;
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+pub use rule::{Cmp, Condition, Quantifier, Rule, StringDef};
+
+use crate::csv::CsvLogItem;
+use crate::error::{CollectorError, Result};
+use crate::sink::HashingWriter;
+
+/// Window kept between chunks for strings without a fixed length.
+pub const REGEX_WINDOW: usize = 4096;
+
+/// Status of a file left out because it matched no rule.
+pub const NO_MATCH_STATUS: &str = "not collected: no rule match";
+
+/// Offsets kept per string in the findings.
+const MAX_OFFSETS: usize = 16;
+
+/// Rules loaded from one or more files.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    size_filter: Option<u64>,
+}
+
+impl RuleSet {
+    /// Load rule files, or every `.yar`/`.yara` file of a directory.
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut files = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            if path.is_dir() {
+                let entries = std::fs::read_dir(path).map_err(|e| CollectorError::FileRead {
+                    path: path.to_path_buf(),
+                    source: e,
+                })?;
+                let mut rule_files: Vec<PathBuf> = entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| {
+                        p.extension().is_some_and(|ext| {
+                            ext.eq_ignore_ascii_case("yar") || ext.eq_ignore_ascii_case("yara")
+                        })
+                    })
+                    .collect();
+                rule_files.sort();
+                files.extend(rule_files);
+            } else {
+                files.push(path.to_path_buf());
+            }
+        }
+
+        let mut set = Self::default();
+        for path in files {
+            let source = std::fs::read_to_string(&path).map_err(|e| CollectorError::FileRead {
+                path: path.clone(),
+                source: e,
+            })?;
+            set.add_source(&path, &source)?;
+        }
+
+        log::info!("Loaded {} rules", set.len());
+        Ok(set)
+    }
+
+    fn add_source(&mut self, path: &Path, source: &str) -> Result<()> {
+        let rules = parser::parse_rules(source).map_err(|e| CollectorError::InvalidRule {
+            path: path.to_path_buf(),
+            line: e.line,
+            reason: e.reason,
+        })?;
+
+        for rule in rules {
+            if self.rules.iter().any(|r| r.name == rule.name) {
+                return Err(CollectorError::InvalidRule {
+                    path: path.to_path_buf(),
+                    line: 0,
+                    reason: format!("duplicate rule {}", rule.name),
+                });
+            }
+            self.rules.push(rule);
+        }
+        Ok(())
+    }
+
+    /// Only collect files larger than `size` when they match a rule.
+    pub fn with_size_filter(mut self, size: Option<u64>) -> Self {
+        self.size_filter = size;
+        self
+    }
+
+    pub fn size_filter(&self) -> Option<u64> {
+        self.size_filter
+    }
+
+    /// Whether a file of `size` bytes must match a rule to be collected.
+    pub fn filters(&self, size: u64) -> bool {
+        self.size_filter.is_some_and(|limit| size > limit)
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Bytes kept from the previous chunk so matches can span chunks.
+    fn overlap(&self) -> usize {
+        self.rules
+            .iter()
+            .flat_map(|r| &r.strings)
+            .map(|s| s.max_len.map_or(REGEX_WINDOW, |len| len.saturating_sub(1)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn scanner(&self) -> Scanner<'_> {
+        Scanner {
+            rules: self,
+            overlap: self.overlap(),
+            tail: Vec::new(),
+            base: 0,
+            strings: self
+                .rules
+                .iter()
+                .map(|r| vec![StringState::default(); r.strings.len()])
+                .collect(),
+        }
+    }
+
+    /// Scan a file on disk, also returning its SHA1 and size.
+    pub fn scan_file(&self, path: &Path) -> io::Result<(Vec<RuleMatch>, String, u64)> {
+        let mut file = std::fs::File::open(path)?;
+        self.scan_reader(&mut file)
+    }
+
+    /// Scan everything read from `reader`, also returning its SHA1 and size.
+    pub(crate) fn scan_reader<R: io::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+    ) -> io::Result<(Vec<RuleMatch>, String, u64)> {
+        let mut writer = ScanWriter::new(HashingWriter::new(io::sink()), Some(self));
+        let size = io::copy(reader, &mut writer)?;
+        let (hasher, matches) = writer.finish();
+        Ok((matches.unwrap_or_default(), hasher.hex_digest(), size))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct StringState {
+    count: u64,
+    offsets: Vec<u64>,
+    /// Matches must start at or after this offset
+    next_start: u64,
+}
+
+/// Streaming matcher of a [`RuleSet`].
+pub struct Scanner<'r> {
+    rules: &'r RuleSet,
+    overlap: usize,
+    tail: Vec<u8>,
+    /// File offset of the first byte of `tail`
+    base: u64,
+    strings: Vec<Vec<StringState>>,
+}
+
+impl Scanner<'_> {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+        let tail_len = self.tail.len();
+        let mut buffer = std::mem::take(&mut self.tail);
+        buffer.extend_from_slice(chunk);
+
+        for (rule, states) in self.rules.rules.iter().zip(self.strings.iter_mut()) {
+            for (string, state) in rule.strings.iter().zip(states.iter_mut()) {
+                for m in string.regex.find_iter(&buffer) {
+                    let start = self.base + m.start() as u64;
+                    if m.is_empty() || m.end() <= tail_len || start < state.next_start {
+                        continue;
+                    }
+                    state.count += 1;
+                    if state.offsets.len() < MAX_OFFSETS {
+                        state.offsets.push(start);
+                    }
+                    state.next_start = self.base + m.end() as u64;
+                }
+            }
+        }
+
+        let keep = self.overlap.min(buffer.len());
+        let drop = buffer.len() - keep;
+        self.base += drop as u64;
+        buffer.drain(..drop);
+        self.tail = buffer;
+    }
+
+    /// Evaluate the rules once the whole file has been fed.
+    pub fn finish(self, filesize: u64) -> Vec<RuleMatch> {
+        self.rules
+            .rules
+            .iter()
+            .zip(self.strings)
+            .filter_map(|(rule, states)| {
+                let counts: Vec<u64> = states.iter().map(|s| s.count).collect();
+                if !rule.condition.eval(&counts, filesize) {
+                    return None;
+                }
+                Some(RuleMatch {
+                    rule: rule.name.clone(),
+                    tags: rule.tags.clone(),
+                    strings: rule
+                        .strings
+                        .iter()
+                        .zip(states)
+                        .filter(|(_, state)| state.count > 0)
+                        .map(|(string, state)| StringMatch {
+                            id: format!("${}", string.id),
+                            count: state.count,
+                            offsets: state.offsets,
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Writer adapter scanning everything written through it.
+pub struct ScanWriter<'r, W> {
+    inner: W,
+    scanner: Option<Scanner<'r>>,
+    size: u64,
+}
+
+impl<'r, W: Write> ScanWriter<'r, W> {
+    pub fn new(inner: W, rules: Option<&'r RuleSet>) -> Self {
+        Self {
+            inner,
+            scanner: rules.filter(|r| !r.is_empty()).map(RuleSet::scanner),
+            size: 0,
+        }
+    }
+
+    /// Inner writer and matching rules, `None` when no rules were given.
+    pub fn finish(self) -> (W, Option<Vec<RuleMatch>>) {
+        let size = self.size;
+        (self.inner, self.scanner.map(|s| s.finish(size)))
+    }
+}
+
+impl<W: Write> Write for ScanWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(ref mut scanner) = self.scanner {
+            scanner.feed(&buf[..written]);
+        }
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Matches of a string, with the first offsets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StringMatch {
+    pub id: String,
+    pub count: u64,
+    pub offsets: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    pub rule: String,
+    pub tags: Vec<String>,
+    pub strings: Vec<StringMatch>,
+}
+
+/// A collected file matching one or more rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanFinding {
+    pub source_file: String,
+    pub destination_file: String,
+    pub matches: Vec<RuleMatch>,
+}
+
+impl ScanFinding {
+    /// Finding of a manifest row, without string details (resumed rows).
+    pub fn from_item(item: &CsvLogItem) -> Self {
+        Self {
+            source_file: item.source_file.clone(),
+            destination_file: item.destination_file.clone(),
+            matches: item
+                .rule_names()
+                .map(|rule| RuleMatch {
+                    rule: rule.to_string(),
+                    tags: Vec::new(),
+                    strings: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Scan report written as `Collector_scan.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub generated_at: String,
+    pub rules_loaded: usize,
+    pub files_scanned: u64,
+    pub findings: Vec<ScanFinding>,
+}
+
+impl ScanReport {
+    pub fn new(rules: &RuleSet, files_scanned: u64, findings: Vec<ScanFinding>) -> Self {
+        Self {
+            generated_at: Utc::now().to_rfc3339(),
+            rules_loaded: rules.len(),
+            files_scanned,
+            findings,
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> RuleSet {
+        let mut set = RuleSet::default();
+        set.add_source(Path::new("test.yar"), source).unwrap();
+        set
+    }
+
+    fn scan(set: &RuleSet, chunks: &[&[u8]]) -> Vec<RuleMatch> {
+        let mut writer = ScanWriter::new(Vec::new(), Some(set));
+        for chunk in chunks {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().1.unwrap()
+    }
+
+    #[test]
+    fn test_matches_across_chunks() {
+        let set = rules(
+            r#"rule Beacon { strings: $a = "beacon" $h = { 6B 65 79 } condition: #a == 2 and $h }"#,
+        );
+
+        let matches = scan(&set, &[b"xxbea", b"con-beac", b"on ke", b"y"]);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule, "Beacon");
+        assert_eq!(matches[0].strings[0].offsets, vec![2, 9]);
+        assert_eq!(matches[0].strings[1].offsets, vec![16]);
+
+        // Same data in one chunk gives the same offsets
+        assert_eq!(scan(&set, &[b"xxbeacon-beacon key"]), matches);
+        assert!(scan(&set, &[b"xxbeacon key"]).is_empty());
+    }
+
+    #[test]
+    fn test_regex_and_conditions() {
+        let set = rules(
+            r#"
+            rule Url { strings: $u = /https?:\/\/[a-z]+/ condition: $u }
+            rule Small { condition: filesize < 10 }
+            rule NoSecrets { strings: $p = "password" nocase condition: none of them }
+            "#,
+        );
+
+        let names = |m: Vec<RuleMatch>| m.into_iter().map(|m| m.rule).collect::<Vec<_>>();
+        assert_eq!(
+            names(scan(&set, &[b"go to http://exa", b"mple now"])),
+            vec!["Url", "NoSecrets"]
+        );
+        assert_eq!(names(scan(&set, &[b"PassWord"])), vec!["Small"]);
+    }
+
+    #[test]
+    fn test_size_filter_and_duplicates() {
+        let set = rules("rule A { condition: true }").with_size_filter(Some(100));
+        assert!(set.filters(101));
+        assert!(!set.filters(100));
+
+        let mut set = set;
+        let err = set
+            .add_source(Path::new("other.yar"), "rule A { condition: false }")
+            .unwrap_err();
+        assert!(err.to_string().contains("duplicate rule A"));
+    }
+}
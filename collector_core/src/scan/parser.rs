@@ -0,0 +1,768 @@
+//! Parser for the supported YARA subset.
+
+use regex::bytes::RegexBuilder;
+
+use super::rule::{Cmp, Condition, Quantifier, Rule, StringDef};
+
+/// Parse error with the line it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: String,
+}
+
+type ParseResult<T> = std::result::Result<T, ParseError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    /// `$name`, possibly ending with `*` in string sets
+    StringId(String),
+    /// `#name`
+    CountId(String),
+    Number(u64),
+    Text(Vec<u8>),
+    Regex {
+        pattern: String,
+        flags: String,
+    },
+    Hex(String),
+    Cmp(Cmp),
+    Sym(char),
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error<T>(&self, reason: impl Into<String>) -> ParseResult<T> {
+        Err(ParseError {
+            line: self.line,
+            reason: reason.into(),
+        })
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_blank(&mut self) -> ParseResult<()> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    match ahead.next() {
+                        Some('/') => while !matches!(self.bump(), Some('\n') | None) {},
+                        Some('*') => {
+                            self.bump();
+                            self.bump();
+                            let mut last = ' ';
+                            loop {
+                                match self.bump() {
+                                    Some('/') if last == '*' => break,
+                                    Some(c) => last = c,
+                                    None => return self.error("unterminated comment"),
+                                }
+                            }
+                        }
+                        _ => return Ok(()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    fn text(&mut self) -> ParseResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(bytes),
+                Some('\\') => match self.bump() {
+                    Some('"') => bytes.push(b'"'),
+                    Some('\\') => bytes.push(b'\\'),
+                    Some('n') => bytes.push(b'\n'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('t') => bytes.push(b'\t'),
+                    Some('x') => {
+                        let hex: String =
+                            [self.bump(), self.bump()].into_iter().flatten().collect();
+                        match u8::from_str_radix(&hex, 16) {
+                            Ok(byte) => bytes.push(byte),
+                            Err(_) => return self.error(format!("invalid escape \\x{}", hex)),
+                        }
+                    }
+                    other => {
+                        return self.error(format!("invalid escape \\{}", other.unwrap_or(' ')));
+                    }
+                },
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => {
+                    let mut buf = [0u8; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+    }
+
+    fn regex(&mut self) -> ParseResult<Token> {
+        let mut pattern = String::new();
+        loop {
+            match self.bump() {
+                Some('/') => break,
+                Some('\\') => {
+                    match self.bump() {
+                        // `\/` only escapes the delimiter
+                        Some('/') => pattern.push('/'),
+                        Some(c) => {
+                            pattern.push('\\');
+                            pattern.push(c);
+                        }
+                        None => return self.error("unterminated regex"),
+                    }
+                }
+                Some('\n') | None => return self.error("unterminated regex"),
+                Some(c) => pattern.push(c),
+            }
+        }
+        let mut flags = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c != 'i' && c != 's' {
+                break;
+            }
+            flags.push(c);
+            self.bump();
+        }
+        Ok(Token::Regex { pattern, flags })
+    }
+
+    fn hex(&mut self) -> ParseResult<String> {
+        let mut content = String::new();
+        loop {
+            match self.bump() {
+                Some('}') => return Ok(content),
+                Some(c) => content.push(c),
+                None => return self.error("unterminated hex string"),
+            }
+        }
+    }
+
+    fn number(&mut self) -> ParseResult<u64> {
+        let word = self.word();
+        let (digits, multiplier) = if let Some(d) = word.strip_suffix("KB") {
+            (d, 1024)
+        } else if let Some(d) = word.strip_suffix("MB") {
+            (d, 1024 * 1024)
+        } else {
+            (word.as_str(), 1)
+        };
+        let value = match digits.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => digits.parse(),
+        };
+        match value {
+            Ok(value) => Ok(value * multiplier),
+            Err(_) => self.error(format!("invalid number '{}'", word)),
+        }
+    }
+
+    fn tokens(mut self) -> ParseResult<Vec<(Token, usize)>> {
+        let mut tokens: Vec<(Token, usize)> = Vec::new();
+
+        loop {
+            self.skip_blank()?;
+            let line = self.line;
+            let Some(&c) = self.chars.peek() else {
+                return Ok(tokens);
+            };
+            let after_assign = matches!(tokens.last(), Some((Token::Sym('='), _)));
+
+            let token = match c {
+                '"' => {
+                    self.bump();
+                    Token::Text(self.text()?)
+                }
+                '/' if after_assign => {
+                    self.bump();
+                    self.regex()?
+                }
+                '{' if after_assign => {
+                    self.bump();
+                    Token::Hex(self.hex()?)
+                }
+                '$' | '#' => {
+                    self.bump();
+                    let mut name = self.word();
+                    if c == '$' && self.chars.peek() == Some(&'*') {
+                        self.bump();
+                        name.push('*');
+                    }
+                    if name.is_empty() {
+                        return self.error("anonymous strings are not supported");
+                    }
+                    if c == '$' {
+                        Token::StringId(name)
+                    } else {
+                        Token::CountId(name)
+                    }
+                }
+                '0'..='9' => Token::Number(self.number()?),
+                c if c.is_ascii_alphabetic() || c == '_' => Token::Ident(self.word()),
+                '=' | '!' | '<' | '>' => {
+                    self.bump();
+                    let equals = self.chars.peek() == Some(&'=');
+                    if equals {
+                        self.bump();
+                    }
+                    match (c, equals) {
+                        ('=', true) => Token::Cmp(Cmp::Eq),
+                        ('=', false) => Token::Sym('='),
+                        ('!', true) => Token::Cmp(Cmp::Ne),
+                        ('<', false) => Token::Cmp(Cmp::Lt),
+                        ('<', true) => Token::Cmp(Cmp::Le),
+                        ('>', false) => Token::Cmp(Cmp::Gt),
+                        ('>', true) => Token::Cmp(Cmp::Ge),
+                        _ => return self.error(format!("unexpected '{}'", c)),
+                    }
+                }
+                '{' | '}' | '(' | ')' | ':' | ',' => {
+                    self.bump();
+                    Token::Sym(c)
+                }
+                other => return self.error(format!("unexpected '{}'", other)),
+            };
+            tokens.push((token, line));
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error<T>(&self, reason: impl Into<String>) -> ParseResult<T> {
+        Err(ParseError {
+            line: self.line(),
+            reason: reason.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn is_ident(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(w)) if w == word)
+    }
+
+    fn expect_sym(&mut self, sym: char) -> ParseResult<()> {
+        match self.next() {
+            Some(Token::Sym(c)) if c == sym => Ok(()),
+            _ => {
+                self.position -= 1;
+                self.error(format!("expected '{}'", sym))
+            }
+        }
+    }
+
+    fn expect_ident(&mut self) -> ParseResult<String> {
+        match self.next() {
+            Some(Token::Ident(word)) => Ok(word),
+            _ => {
+                self.position -= 1;
+                self.error("expected an identifier")
+            }
+        }
+    }
+
+    fn rules(&mut self) -> ParseResult<Vec<Rule>> {
+        let mut rules = Vec::new();
+        while self.peek().is_some() {
+            match self.expect_ident()?.as_str() {
+                "rule" => rules.push(self.rule()?),
+                "import" | "include" => {
+                    self.position -= 1;
+                    return self.error("modules and includes are not supported");
+                }
+                other => {
+                    self.position -= 1;
+                    return self.error(format!("'{}' rules are not supported", other));
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    fn rule(&mut self) -> ParseResult<Rule> {
+        let name = self.expect_ident()?;
+        let mut tags = Vec::new();
+        if self.peek() == Some(&Token::Sym(':')) {
+            self.next();
+            while let Some(Token::Ident(tag)) = self.peek() {
+                tags.push(tag.clone());
+                self.next();
+            }
+        }
+        self.expect_sym('{')?;
+
+        let mut meta = Vec::new();
+        if self.is_ident("meta") {
+            self.next();
+            self.expect_sym(':')?;
+            while let Some(Token::Ident(key)) = self.peek().cloned() {
+                if key == "strings" || key == "condition" {
+                    break;
+                }
+                self.next();
+                self.expect_sym('=')?;
+                let value = match self.next() {
+                    Some(Token::Text(text)) => String::from_utf8_lossy(&text).to_string(),
+                    Some(Token::Number(n)) => n.to_string(),
+                    Some(Token::Ident(word)) if word == "true" || word == "false" => word,
+                    _ => {
+                        self.position -= 1;
+                        return self.error("expected a meta value");
+                    }
+                };
+                meta.push((key, value));
+            }
+        }
+
+        let mut strings = Vec::new();
+        if self.is_ident("strings") {
+            self.next();
+            self.expect_sym(':')?;
+            while let Some(Token::StringId(id)) = self.peek().cloned() {
+                self.next();
+                if strings.iter().any(|s: &StringDef| s.id == id) {
+                    return self.error(format!("duplicate string ${}", id));
+                }
+                self.expect_sym('=')?;
+                strings.push(self.string(id)?);
+            }
+        }
+
+        if !self.is_ident("condition") {
+            return self.error("expected 'condition'");
+        }
+        self.next();
+        self.expect_sym(':')?;
+        let condition = self.or(&strings)?;
+        self.expect_sym('}')?;
+
+        Ok(Rule {
+            name,
+            tags,
+            meta,
+            strings,
+            condition,
+        })
+    }
+
+    fn modifiers(&mut self, allowed: &[&str]) -> ParseResult<Vec<String>> {
+        let mut modifiers = Vec::new();
+        while let Some(Token::Ident(word)) = self.peek().cloned() {
+            if !allowed.contains(&word.as_str()) {
+                if matches!(
+                    word.as_str(),
+                    "fullword" | "private" | "xor" | "base64" | "base64wide"
+                ) {
+                    return self.error(format!("modifier '{}' is not supported", word));
+                }
+                break;
+            }
+            self.next();
+            modifiers.push(word);
+        }
+        Ok(modifiers)
+    }
+
+    fn string(&mut self, id: String) -> ParseResult<StringDef> {
+        let (pattern, max_len, nocase) = match self.next() {
+            Some(Token::Text(text)) => {
+                if text.is_empty() {
+                    return self.error("empty string");
+                }
+                let modifiers = self.modifiers(&["nocase", "wide", "ascii"])?;
+                let has = |m: &str| modifiers.iter().any(|x| x == m);
+                let wide = has("wide");
+                let ascii = has("ascii") || !wide;
+
+                let narrow: String = text.iter().map(|&b| escape_byte(b)).collect();
+                let widened: String = text
+                    .iter()
+                    .map(|&b| format!("{}\\x00", escape_byte(b)))
+                    .collect();
+                let pattern = match (ascii, wide) {
+                    (true, true) => format!("(?:{}|{})", narrow, widened),
+                    (false, true) => widened,
+                    _ => narrow,
+                };
+                let len = if wide { text.len() * 2 } else { text.len() };
+                (pattern, Some(len), has("nocase"))
+            }
+            Some(Token::Hex(content)) => {
+                let (pattern, len) = match hex_pattern(&content) {
+                    Ok(hex) => hex,
+                    Err(reason) => return self.error(reason),
+                };
+                (pattern, len, false)
+            }
+            Some(Token::Regex { pattern, flags }) => {
+                let modifiers = self.modifiers(&["nocase", "ascii"])?;
+                let nocase = flags.contains('i') || modifiers.iter().any(|m| m == "nocase");
+                let pattern = if flags.contains('s') {
+                    format!("(?s){}", pattern)
+                } else {
+                    pattern
+                };
+                (pattern, None, nocase)
+            }
+            _ => {
+                self.position -= 1;
+                return self.error("expected a text, hex or regex string");
+            }
+        };
+
+        let regex = RegexBuilder::new(&pattern)
+            .unicode(false)
+            .case_insensitive(nocase)
+            .build();
+        match regex {
+            Ok(regex) => Ok(StringDef { id, regex, max_len }),
+            Err(e) => self.error(format!("invalid string ${}: {}", id, e)),
+        }
+    }
+
+    fn or(&mut self, strings: &[StringDef]) -> ParseResult<Condition> {
+        let mut terms = vec![self.and(strings)?];
+        while self.is_ident("or") {
+            self.next();
+            terms.push(self.and(strings)?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Condition::Or(terms)
+        })
+    }
+
+    fn and(&mut self, strings: &[StringDef]) -> ParseResult<Condition> {
+        let mut terms = vec![self.not(strings)?];
+        while self.is_ident("and") {
+            self.next();
+            terms.push(self.not(strings)?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Condition::And(terms)
+        })
+    }
+
+    fn not(&mut self, strings: &[StringDef]) -> ParseResult<Condition> {
+        if self.is_ident("not") {
+            self.next();
+            return Ok(Condition::Not(Box::new(self.not(strings)?)));
+        }
+        self.primary(strings)
+    }
+
+    fn comparison(&mut self) -> ParseResult<(Cmp, u64)> {
+        match (self.next(), self.next()) {
+            (Some(Token::Cmp(cmp)), Some(Token::Number(value))) => Ok((cmp, value)),
+            _ => {
+                self.position -= 2;
+                self.error("expected a comparison with a number")
+            }
+        }
+    }
+
+    /// Index of the string `id`, just read from the tokens.
+    fn string_index(&self, strings: &[StringDef], id: &str) -> ParseResult<usize> {
+        match strings.iter().position(|s| s.id == id) {
+            Some(index) => Ok(index),
+            None => Err(ParseError {
+                line: self.tokens[self.position - 1].1,
+                reason: format!("undefined string ${}", id),
+            }),
+        }
+    }
+
+    fn string_set(&mut self, strings: &[StringDef]) -> ParseResult<Vec<usize>> {
+        if self.is_ident("them") {
+            self.next();
+            return Ok((0..strings.len()).collect());
+        }
+
+        self.expect_sym('(')?;
+        let mut set = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::StringId(id)) => match id.strip_suffix('*') {
+                    Some(prefix) => set.extend(
+                        strings
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, s)| s.id.starts_with(prefix))
+                            .map(|(i, _)| i),
+                    ),
+                    None => set.push(self.string_index(strings, &id)?),
+                },
+                _ => {
+                    self.position -= 1;
+                    return self.error("expected a string identifier");
+                }
+            }
+            match self.next() {
+                Some(Token::Sym(',')) => continue,
+                Some(Token::Sym(')')) => break,
+                _ => {
+                    self.position -= 1;
+                    return self.error("expected ',' or ')'");
+                }
+            }
+        }
+
+        set.sort_unstable();
+        set.dedup();
+        if set.is_empty() {
+            return self.error("string set matches no string");
+        }
+        Ok(set)
+    }
+
+    fn primary(&mut self, strings: &[StringDef]) -> ParseResult<Condition> {
+        let quantifier = match self.next() {
+            Some(Token::Sym('(')) => {
+                let inner = self.or(strings)?;
+                self.expect_sym(')')?;
+                return Ok(inner);
+            }
+            Some(Token::StringId(id)) => {
+                return Ok(Condition::Matched(self.string_index(strings, &id)?));
+            }
+            Some(Token::CountId(id)) => {
+                let index = self.string_index(strings, &id)?;
+                let (cmp, value) = self.comparison()?;
+                return Ok(Condition::Count(index, cmp, value));
+            }
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => return Ok(Condition::Bool(true)),
+                "false" => return Ok(Condition::Bool(false)),
+                "filesize" => {
+                    let (cmp, value) = self.comparison()?;
+                    return Ok(Condition::Filesize(cmp, value));
+                }
+                "any" => Quantifier::Any,
+                "all" => Quantifier::All,
+                "none" => Quantifier::None,
+                other => {
+                    self.position -= 1;
+                    return self.error(format!("unsupported condition '{}'", other));
+                }
+            },
+            Some(Token::Number(n)) => Quantifier::AtLeast(n),
+            _ => {
+                self.position -= 1;
+                return self.error("expected a condition");
+            }
+        };
+
+        if !self.is_ident("of") {
+            return self.error("expected 'of'");
+        }
+        self.next();
+        Ok(Condition::Of(quantifier, self.string_set(strings)?))
+    }
+}
+
+fn escape_byte(byte: u8) -> String {
+    if byte.is_ascii_alphanumeric() {
+        (byte as char).to_string()
+    } else {
+        format!("\\x{:02X}", byte)
+    }
+}
+
+/// Regex of a hex string and its length when it has no jumps or
+/// alternatives.
+fn hex_pattern(content: &str) -> std::result::Result<(String, Option<usize>), String> {
+    let mut pattern = String::new();
+    let mut len = 0usize;
+    let mut fixed = true;
+    let mut chars = content.chars().filter(|c| !c.is_whitespace()).peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                fixed = false;
+                pattern.push_str("(?:");
+            }
+            '|' | ')' => pattern.push(c),
+            '[' => {
+                fixed = false;
+                let mut jump = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    jump.push(c);
+                }
+                let range = match jump.split_once('-') {
+                    None => format!("{{{}}}", jump),
+                    Some(("", "")) => "*?".to_string(),
+                    Some((min, "")) => format!("{{{},}}", min),
+                    Some((min, max)) => format!("{{{},{}}}", min, max),
+                };
+                if !range
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || "{},*?".contains(c))
+                {
+                    return Err(format!("invalid jump [{}]", jump));
+                }
+                pattern.push_str(&format!("(?s:.){}", range));
+            }
+            high => {
+                let Some(low) = chars.next() else {
+                    return Err("odd number of hex digits".into());
+                };
+                let digit = |c: char| c.to_digit(16).map(|d| d as u8);
+                let byte = match (high, low, digit(high), digit(low)) {
+                    ('?', '?', _, _) => "(?s:.)".to_string(),
+                    (_, '?', Some(h), _) => {
+                        format!("[\\x{:02X}-\\x{:02X}]", h << 4, (h << 4) | 0xF)
+                    }
+                    ('?', _, _, Some(l)) => {
+                        let class: String = (0..16u8)
+                            .map(|h| format!("\\x{:02X}", (h << 4) | l))
+                            .collect();
+                        format!("[{}]", class)
+                    }
+                    (_, _, Some(h), Some(l)) => format!("\\x{:02X}", (h << 4) | l),
+                    _ => return Err(format!("invalid hex byte '{}{}'", high, low)),
+                };
+                pattern.push_str(&byte);
+                len += 1;
+            }
+        }
+    }
+
+    if pattern.is_empty() {
+        return Err("empty hex string".into());
+    }
+    Ok((pattern, fixed.then_some(len)))
+}
+
+/// Parse the rules of a source file.
+pub fn parse_rules(source: &str) -> ParseResult<Vec<Rule>> {
+    let tokens = Lexer {
+        chars: source.chars().peekable(),
+        line: 1,
+    }
+    .tokens()?;
+
+    Parser {
+        tokens,
+        position: 0,
+    }
+    .rules()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rules = parse_rules(
+            r#"
+            // Sample rule
+            rule Dropper : malware loader {
+                meta:
+                    author = "ir team"
+                    score = 80
+                strings:
+                    $mz = { 4D 5A ?? [2-4] 9? }
+                    $s1 = "evil\x00" nocase wide ascii
+                    $re = /https?:\/\/[a-z]+\.example/i
+                condition:
+                    ($mz and #s1 >= 1) or (2 of ($s*, $re) and filesize < 1MB) or not any of them
+            }
+            rule Always { condition: true }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+        let rule = &rules[0];
+        assert_eq!(rule.name, "Dropper");
+        assert_eq!(rule.tags, vec!["malware", "loader"]);
+        assert_eq!(rule.meta[1], ("score".to_string(), "80".to_string()));
+        assert_eq!(rule.strings[0].max_len, None);
+        assert_eq!(rule.strings[1].max_len, Some(10));
+        assert!(
+            rule.strings[1]
+                .regex
+                .is_match(b"E\x00v\x00I\x00l\x00\x00\x00")
+        );
+        assert!(rule.strings[2].regex.is_match(b"HTTPS://host.example"));
+        assert!(rule.strings[0].regex.is_match(b"MZ\x01\x02\x03\x95"));
+        assert!(!rule.strings[0].regex.is_match(b"MZ\x01\x02\x03\xA5"));
+        assert!(matches!(rule.condition, Condition::Or(ref terms) if terms.len() == 3));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_rules("import \"pe\"\nrule A { condition: true }").unwrap_err();
+        assert_eq!(error.line, 1);
+
+        let error =
+            parse_rules("rule A {\n strings:\n $a = \"x\"\n condition:\n $b\n}").unwrap_err();
+        assert_eq!(error.line, 5);
+        assert!(error.reason.contains("$b"));
+
+        assert!(parse_rules("rule A { strings: $a = \"x\" fullword condition: $a }").is_err());
+        assert!(parse_rules("rule A { strings: $a = { 4D 5 } condition: $a }").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use regex::bytes::Regex;
+
+/// A compiled rule.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub meta: Vec<(String, String)>,
+    pub strings: Vec<StringDef>,
+    pub condition: Condition,
+}
+
+/// A rule string compiled to a byte regex.
+#[derive(Debug, Clone)]
+pub struct StringDef {
+    /// Identifier without the leading `$`
+    pub id: String,
+    pub regex: Regex,
+    /// Longest possible match, `None` for regexes and hex strings with jumps
+    pub max_len: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    pub fn eval(self, left: u64, right: u64) -> bool {
+        match self {
+            Cmp::Eq => left == right,
+            Cmp::Ne => left != right,
+            Cmp::Lt => left < right,
+            Cmp::Le => left <= right,
+            Cmp::Gt => left > right,
+            Cmp::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantifier {
+    Any,
+    All,
+    None,
+    AtLeast(u64),
+}
+
+/// Rule condition, strings referenced by their index in [`Rule::strings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Bool(bool),
+    Matched(usize),
+    Count(usize, Cmp, u64),
+    Filesize(Cmp, u64),
+    Of(Quantifier, Vec<usize>),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
+impl Condition {
+    /// Evaluate with the match count of every string of the rule.
+    pub fn eval(&self, counts: &[u64], filesize: u64) -> bool {
+        match self {
+            Condition::Bool(value) => *value,
+            Condition::Matched(index) => counts[*index] > 0,
+            Condition::Count(index, cmp, value) => cmp.eval(counts[*index], *value),
+            Condition::Filesize(cmp, value) => cmp.eval(filesize, *value),
+            Condition::Of(quantifier, set) => {
+                let matched = set.iter().filter(|&&i| counts[i] > 0).count() as u64;
+                match quantifier {
+                    Quantifier::Any => matched > 0,
+                    Quantifier::All => matched == set.len() as u64,
+                    Quantifier::None => matched == 0,
+                    Quantifier::AtLeast(n) => matched >= *n,
+                }
+            }
+            Condition::Not(inner) => !inner.eval(counts, filesize),
+            Condition::And(terms) => terms.iter().all(|c| c.eval(counts, filesize)),
+            Condition::Or(terms) => terms.iter().any(|c| c.eval(counts, filesize)),
+        }
+    }
+}
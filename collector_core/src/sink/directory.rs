@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CollectorError, Result};
+use crate::sink::{EntryMetadata, OutputSink, entry_name, no_entry_error};
+use crate::writer::Writer;
+
+/// Source permissions, ownership and extended attributes of the staged
+/// copies, one JSON line per entry, read back when the folder is archived.
+pub const METADATA_NAME: &str = "Collector_metadata.jsonl";
+
+/// Unix metadata of one staged entry, as recorded in [`METADATA_NAME`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordedMetadata {
+    entry: String,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    /// Names with hex encoded values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    xattrs: Vec<(String, String)>,
+}
+
+impl RecordedMetadata {
+    fn new(entry: String, metadata: &EntryMetadata) -> Option<Self> {
+        if metadata.mode.is_none()
+            && metadata.uid.is_none()
+            && metadata.gid.is_none()
+            && metadata.xattrs.is_empty()
+        {
+            return None;
+        }
+        Some(Self {
+            entry,
+            mode: metadata.mode,
+            uid: metadata.uid,
+            gid: metadata.gid,
+            xattrs: metadata
+                .xattrs
+                .iter()
+                .map(|(name, value)| (name.clone(), hex::encode(value)))
+                .collect(),
+        })
+    }
+}
+
+struct OpenEntry {
+    path: PathBuf,
+    file: File,
+    metadata: EntryMetadata,
+}
+
+/// Plain directory layout: `<destination>/Collector_<hostname>/<source path>`.
+///
+/// Copies are private to the collecting user (0600 files, 0700 folders on
+/// Unix) and carry no extended attributes; the source values go to
+/// [`METADATA_NAME`] instead.
+pub struct DirectorySink {
+    writer: Writer,
+    current: Option<OpenEntry>,
+    recorded: Option<File>,
+}
+
+impl DirectorySink {
+    pub fn new(writer: Writer) -> Self {
+        Self {
+            writer,
+            current: None,
+            recorded: None,
+        }
+    }
+
+    pub fn writer(&self) -> &Writer {
+        &self.writer
+    }
+
+    fn close_current(&mut self) -> Result<Option<OpenEntry>> {
+        let Some(mut entry) = self.current.take() else {
+            return Ok(None);
+        };
+
+        entry.file.flush().map_err(|e| CollectorError::FileWrite {
+            path: entry.path.clone(),
+            source: e,
+        })?;
+
+        Ok(Some(entry))
+    }
+
+    /// Append the source metadata of `entry` to [`METADATA_NAME`]
+    fn record(&mut self, entry: &str, metadata: &EntryMetadata) -> Result<()> {
+        let Some(recorded) = RecordedMetadata::new(entry.to_string(), metadata) else {
+            return Ok(());
+        };
+        let path = self.writer.get_file_path(METADATA_NAME);
+        let write_error = |e| CollectorError::FileWrite {
+            path: path.clone(),
+            source: e,
+        };
+
+        if self.recorded.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(write_error)?;
+            self.recorded = Some(file);
+        }
+        let mut line =
+            serde_json::to_vec(&recorded).map_err(|e| CollectorError::CsvError(e.to_string()))?;
+        line.push(b'\n');
+        if let Some(ref mut file) = self.recorded {
+            file.write_all(&line).map_err(write_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Source metadata recorded for the entries of the staging folder at `root`,
+/// by entry name. Only mode, owner and extended attributes are set.
+pub(crate) fn read_recorded_metadata(root: &Path) -> Result<HashMap<String, EntryMetadata>> {
+    let path = root.join(METADATA_NAME);
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(CollectorError::FileRead { path, source: e }),
+    };
+
+    // Later lines win: a resumed collection may have copied an entry again
+    Ok(data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RecordedMetadata>(line).ok())
+        .map(|recorded| {
+            let metadata = EntryMetadata {
+                mode: recorded.mode,
+                uid: recorded.uid,
+                gid: recorded.gid,
+                xattrs: recorded
+                    .xattrs
+                    .into_iter()
+                    .filter_map(|(name, value)| Some((name, hex::decode(value).ok()?)))
+                    .collect(),
+                ..Default::default()
+            };
+            (recorded.entry, metadata)
+        })
+        .collect())
+}
+
+/// Create the folders of a staged copy, private to the collecting user
+fn create_private_dirs(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(path)
+}
+
+/// Create a staged copy, private to the collecting user
+fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+impl Write for DirectorySink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.current {
+            Some(ref mut entry) => entry.file.write(buf),
+            None => Err(no_entry_error()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current {
+            Some(ref mut entry) => entry.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl OutputSink for DirectorySink {
+    fn start_entry(&mut self, path: &str, metadata: &EntryMetadata) -> Result<()> {
+        self.finish_entry()?;
+
+        let name = entry_name(path);
+        let file_path = self.writer.get_file_path(&name);
+        if let Some(parent) = file_path.parent() {
+            create_private_dirs(parent).map_err(|e| CollectorError::DirectoryCreate {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        let file = create_private_file(&file_path).map_err(|e| CollectorError::FileWrite {
+            path: file_path.clone(),
+            source: e,
+        })?;
+        self.record(&name, metadata)?;
+
+        self.current = Some(OpenEntry {
+            path: file_path,
+            file,
+            metadata: metadata.clone(),
+        });
+        Ok(())
+    }
+
+    fn finish_entry(&mut self) -> Result<()> {
+        let Some(entry) = self.close_current()? else {
+            return Ok(());
+        };
+        drop(entry.file);
+
+        // Keep the source timestamps on the copy so later archiving preserves them
+        if let Some(mtime) = entry.metadata.modified {
+            let atime = entry.metadata.accessed.unwrap_or(mtime);
+            if let Err(e) = filetime::set_file_times(&entry.path, atime, mtime) {
+                log::debug!("Failed to set times on {}: {}", entry.path.display(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn abort_entry(&mut self) -> Result<bool> {
+        if let Some(entry) = self.close_current()? {
+            drop(entry.file);
+            let _ = fs::remove_file(&entry.path);
+        }
+        Ok(false)
+    }
+
+    fn can_rewind(&self) -> bool {
+        true
+    }
+
+    fn local_root(&self) -> Option<PathBuf> {
+        Some(self.writer.full_destination().to_path_buf())
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<Vec<PathBuf>> {
+        self.finish_entry()?;
+        Ok(vec![self.writer.full_destination().to_path_buf()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_directory_sink_writes_entry() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        let mut sink = DirectorySink::new(writer);
+
+        sink.start_entry("/var/log/test.log", &EntryMetadata::default())
+            .unwrap();
+        sink.write_all(b"content").unwrap();
+        sink.finish_entry().unwrap();
+
+        let written = temp_dir.path().join("Test/var/log/test.log");
+        assert_eq!(fs::read(written).unwrap(), b"content");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_sink_private_copies() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        let mut sink = DirectorySink::new(writer);
+        let metadata = EntryMetadata {
+            mode: Some(0o4755),
+            uid: Some(4242),
+            gid: Some(4343),
+            xattrs: vec![("user.origin".to_string(), b"host".to_vec())],
+            ..Default::default()
+        };
+
+        sink.start_entry("/usr/bin/ping", &metadata).unwrap();
+        sink.write_all(b"ELF").unwrap();
+        sink.finish_entry().unwrap();
+
+        let written = temp_dir.path().join("Test/usr/bin/ping");
+        let staged = fs::metadata(&written).unwrap();
+        assert_eq!(staged.permissions().mode() & 0o7777, 0o600);
+        assert_eq!(staged.uid(), nix::unistd::getuid().as_raw());
+        let folder = fs::metadata(written.parent().unwrap()).unwrap();
+        assert_eq!(folder.permissions().mode() & 0o777, 0o700);
+        assert_eq!(xattr::list(&written).unwrap().count(), 0);
+
+        let recorded = read_recorded_metadata(&temp_dir.path().join("Test")).unwrap();
+        assert_eq!(recorded["usr/bin/ping"].mode, Some(0o4755));
+        assert_eq!(recorded["usr/bin/ping"].uid, Some(4242));
+        assert_eq!(recorded["usr/bin/ping"].xattrs, metadata.xattrs);
+    }
+
+    #[test]
+    fn test_directory_sink_abort_removes_file() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        let mut sink = DirectorySink::new(writer);
+
+        sink.start_entry("partial.bin", &EntryMetadata::default())
+            .unwrap();
+        sink.write_all(b"half").unwrap();
+        assert!(!sink.abort_entry().unwrap());
+
+        assert!(!temp_dir.path().join("Test/partial.bin").exists());
+    }
+
+    #[test]
+    fn test_directory_sink_write_without_entry() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        let mut sink = DirectorySink::new(writer);
+
+        assert!(sink.write_all(b"data").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! Public-key encryption of archive output with age (X25519 recipients).
+//!
+//! The archive is encrypted to every configured recipient, so whoever runs the
+//! collection cannot read it back; only holders of a matching identity can
+//! (`age -d -i key.txt Collector_<host>.zip.age`).
+
+use std::io::Write;
+use std::str::FromStr;
+
+use age::stream::StreamWriter;
+use age::x25519::Recipient;
+
+use crate::error::{CollectorError, Result};
+
+/// Extension appended to encrypted archives.
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+/// Parse age X25519 public keys (`age1...`).
+pub fn parse_recipients<S: AsRef<str>>(recipients: &[S]) -> Result<Vec<Recipient>> {
+    recipients
+        .iter()
+        .map(|r| {
+            let r = r.as_ref().trim();
+            Recipient::from_str(r).map_err(|e| CollectorError::InvalidRecipient {
+                recipient: r.to_string(),
+                reason: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Wrap `output` so everything written is encrypted to `recipients`.
+pub fn encrypt_to<W: Write>(output: W, recipients: &[Recipient]) -> Result<StreamWriter<W>> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| CollectorError::Encryption(e.to_string()))?;
+
+    encryptor
+        .wrap_output(output)
+        .map_err(|e| CollectorError::Encryption(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let identity = age::x25519::Identity::generate();
+        let recipients = parse_recipients(&[identity.to_public().to_string()]).unwrap();
+
+        let mut writer = encrypt_to(Vec::new(), &recipients).unwrap();
+        writer.write_all(b"evidence").unwrap();
+        let encrypted = writer.finish().unwrap();
+        assert!(!encrypted.windows(8).any(|w| w == b"evidence"));
+
+        let decryptor = age::Decryptor::new(encrypted.as_slice()).unwrap();
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .unwrap();
+        let mut plain = String::new();
+        reader.read_to_string(&mut plain).unwrap();
+        assert_eq!(plain, "evidence");
+    }
+
+    #[test]
+    fn test_parse_invalid_recipient() {
+        assert!(parse_recipients(&["not-a-key"]).is_err());
+    }
+}
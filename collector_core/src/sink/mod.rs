@@ -0,0 +1,573 @@
+mod directory;
+mod encrypt;
+mod output;
+mod stream;
+mod tar_archive;
+mod zip_archive;
+
+use std::fmt;
+use std::fs::Metadata;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::error::{CollectorError, Result};
+use crate::utils::{FILE_BUFFER_SIZE, normalize_path};
+
+pub(crate) use directory::read_recorded_metadata;
+pub use directory::{DirectorySink, METADATA_NAME};
+pub use encrypt::{ENCRYPTED_EXTENSION, encrypt_to, parse_recipients};
+pub use output::{ArchiveFile, ArchiveOutput, VolumeInfo, VolumeWriter};
+pub use stream::{STREAM_MAGIC, STREAM_VERSION, StreamEntry, StreamReader, StreamSink};
+pub use tar_archive::{TarCompression, TarSink};
+pub use zip_archive::ZipSink;
+
+/// Archive container produced by `Writer::create_archive` and direct collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz", alias = "tgz")]
+    TarGz,
+    #[serde(rename = "tar.zst", alias = "tzst")]
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 4] = [
+        ArchiveFormat::Zip,
+        ArchiveFormat::Tar,
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarZst,
+    ];
+
+    /// Format of an archive file, from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let name = path.as_ref().file_name()?.to_string_lossy().to_lowercase();
+        // Longest extensions first so `.tar.gz` is not taken for `.gz`
+        [
+            ArchiveFormat::TarGz,
+            ArchiveFormat::TarZst,
+            ArchiveFormat::Tar,
+            ArchiveFormat::Zip,
+        ]
+        .into_iter()
+        .find(|format| name.ends_with(&format!(".{}", format.extension())))
+    }
+
+    /// File extension, also used as the format name on the command line.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarZst => "tar.zst",
+        }
+    }
+
+    /// Compression applied to the tar stream, `None` for ZIP.
+    pub fn tar_compression(&self) -> Option<TarCompression> {
+        match self {
+            ArchiveFormat::Zip => None,
+            ArchiveFormat::Tar => Some(TarCompression::None),
+            ArchiveFormat::TarGz => Some(TarCompression::Gzip),
+            ArchiveFormat::TarZst => Some(TarCompression::Zstd),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "tar" => Ok(ArchiveFormat::Tar),
+            "tar.gz" | "tgz" => Ok(ArchiveFormat::TarGz),
+            "tar.zst" | "tzst" => Ok(ArchiveFormat::TarZst),
+            other => Err(format!(
+                "unknown archive format '{}' (expected zip, tar, tar.gz or tar.zst)",
+                other
+            )),
+        }
+    }
+}
+
+/// Settings shared by every archive sink.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    pub format: ArchiveFormat,
+    pub password: Option<String>,
+    /// Split the archive into volumes of this many bytes.
+    pub volume_size: Option<u64>,
+    /// age X25519 public keys the archive is encrypted to.
+    pub recipients: Vec<String>,
+}
+
+impl ArchiveOptions {
+    pub fn with_format(mut self, format: ArchiveFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password.filter(|p| !p.is_empty());
+        self
+    }
+
+    pub fn with_volume_size(mut self, volume_size: Option<u64>) -> Self {
+        self.volume_size = volume_size.filter(|size| *size > 0);
+        self
+    }
+
+    pub fn with_recipients(mut self, recipients: Vec<String>) -> Self {
+        self.recipients = recipients
+            .into_iter()
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .collect();
+        self
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        !self.recipients.is_empty()
+    }
+
+    /// Extension of the produced archive, e.g. `tar.zst` or `zip.age`.
+    pub fn extension(&self) -> String {
+        if self.is_encrypted() {
+            format!("{}.{}", self.format.extension(), ENCRYPTED_EXTENSION)
+        } else {
+            self.format.extension().to_string()
+        }
+    }
+}
+
+/// Source metadata attached to every entry written to a sink.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub size: u64,
+    pub modified: Option<FileTime>,
+    pub accessed: Option<FileTime>,
+    pub created: Option<FileTime>,
+    pub changed: Option<FileTime>,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub inode: Option<u64>,
+    /// Extended attributes as (name, value), read by `from_path` on Unix.
+    pub xattrs: Vec<(String, Vec<u8>)>,
+}
+
+impl EntryMetadata {
+    /// Read metadata from a path, falling back to empty metadata on failure
+    /// (raw NTFS extractions may not be able to stat the source).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let Ok(metadata) = std::fs::metadata(path.as_ref()) else {
+            return Self::default();
+        };
+        let mut entry = Self::from_metadata(&metadata);
+
+        #[cfg(unix)]
+        if let Ok(names) = xattr::list(path.as_ref()) {
+            entry.xattrs = names
+                .filter_map(|name| {
+                    let value = xattr::get(path.as_ref(), &name).ok()??;
+                    Some((name.to_string_lossy().to_string(), value))
+                })
+                .collect();
+        }
+
+        entry
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let mut entry = Self {
+            size: metadata.len(),
+            modified: Some(FileTime::from_last_modification_time(metadata)),
+            accessed: Some(FileTime::from_last_access_time(metadata)),
+            created: FileTime::from_creation_time(metadata),
+            ..Default::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            entry.changed = Some(FileTime::from_unix_time(
+                metadata.ctime(),
+                metadata.ctime_nsec() as u32,
+            ));
+            entry.mode = Some(metadata.mode());
+            entry.uid = Some(metadata.uid());
+            entry.gid = Some(metadata.gid());
+            entry.inode = Some(metadata.ino());
+        }
+
+        entry
+    }
+
+    /// Metadata for a generated entry (manifest, reports) of the given size.
+    pub fn generated(size: u64) -> Self {
+        let now = FileTime::now();
+        Self {
+            size,
+            modified: Some(now),
+            accessed: Some(now),
+            mode: Some(0o644),
+            ..Default::default()
+        }
+    }
+}
+
+/// Destination of collected files.
+///
+/// Entries are written sequentially: `start_entry`, any number of writes
+/// through the `Write` implementation, then `finish_entry`. Writing while no
+/// entry is open is an error.
+pub trait OutputSink: Write + Send {
+    /// Open a new entry at `path`, relative to the collection root.
+    fn start_entry(&mut self, path: &str, metadata: &EntryMetadata) -> Result<()>;
+
+    /// Close the current entry.
+    fn finish_entry(&mut self) -> Result<()>;
+
+    /// Drop the current entry after a failed extraction. Sinks that cannot
+    /// rewind keep the partial data and return `true`.
+    fn abort_entry(&mut self) -> Result<bool> {
+        self.finish_entry()?;
+        Ok(true)
+    }
+
+    /// Whether `abort_entry` leaves nothing of the current entry behind.
+    fn can_rewind(&self) -> bool {
+        false
+    }
+
+    /// Whether entries hold exactly the size given to `start_entry`, as in
+    /// formats writing it in a header before the content: shorter content is
+    /// zero-padded and longer content cut.
+    fn fixed_size(&self) -> bool {
+        false
+    }
+
+    /// Local directory holding the entries, if the sink writes plain files.
+    fn local_root(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Flush everything and return the paths produced on disk.
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>>;
+
+    /// Write a whole entry from memory.
+    fn add_bytes(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        self.start_entry(path, &EntryMetadata::generated(data.len() as u64))?;
+        self.write_all(data)
+            .map_err(|e| CollectorError::FileWrite {
+                path: path.into(),
+                source: e,
+            })?;
+        self.finish_entry()
+    }
+
+    /// Copy a file from disk into a new entry.
+    fn add_file(&mut self, path: &str, source: &Path) -> Result<()> {
+        self.add_file_with_metadata(path, source, &EntryMetadata::from_path(source))
+    }
+
+    /// Copy a file from disk into a new entry described by `metadata`.
+    fn add_file_with_metadata(
+        &mut self,
+        path: &str,
+        source: &Path,
+        metadata: &EntryMetadata,
+    ) -> Result<()> {
+        let mut file = std::fs::File::open(source).map_err(|e| CollectorError::FileRead {
+            path: source.to_path_buf(),
+            source: e,
+        })?;
+
+        self.start_entry(path, metadata)?;
+        copy_buffered(&mut file, self).map_err(|e| CollectorError::FileRead {
+            path: source.to_path_buf(),
+            source: e,
+        })?;
+        self.finish_entry()
+    }
+}
+
+/// Entry name used inside sinks: normalized, relative, `/` separated.
+pub fn entry_name(path: &str) -> String {
+    let normalized = normalize_path(path);
+    Path::new(&normalized)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Writer adapter computing the SHA1 of everything written through it.
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha1,
+    bytes_written: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha1::new(),
+            bytes_written: 0,
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Hex encoded SHA1 of the written data.
+    pub fn hex_digest(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.bytes_written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// How the content written to a fixed-size entry differed from its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resized {
+    /// The source shrank: this many zero bytes were added
+    Padded(u64),
+    /// The source grew: this many bytes were left out
+    Truncated(u64),
+}
+
+impl Resized {
+    /// Manifest status of the copy
+    pub(crate) fn status(&self) -> String {
+        match self {
+            Resized::Padded(bytes) => {
+                format!("padded: source shrank by {} bytes while copied", bytes)
+            }
+            Resized::Truncated(bytes) => {
+                format!("truncated: source grew by {} bytes while copied", bytes)
+            }
+        }
+    }
+}
+
+/// Writer adapter holding content to the size announced for a fixed-size
+/// entry, so what is hashed is what the sink stores. Without a size it
+/// passes everything through.
+pub(crate) struct FixedSizeWriter<W> {
+    inner: W,
+    remaining: Option<u64>,
+    dropped: u64,
+}
+
+impl<W: Write> FixedSizeWriter<W> {
+    pub(crate) fn new(inner: W, size: Option<u64>) -> Self {
+        Self {
+            inner,
+            remaining: size,
+            dropped: 0,
+        }
+    }
+
+    /// Zero-pad missing content, returning how the content was resized,
+    /// if it was
+    pub(crate) fn finish(&mut self) -> io::Result<Option<Resized>> {
+        match self.remaining {
+            Some(missing) if missing > 0 => {
+                io::copy(&mut io::repeat(0).take(missing), &mut self.inner)?;
+                self.remaining = Some(0);
+                Ok(Some(Resized::Padded(missing)))
+            }
+            _ if self.dropped > 0 => Ok(Some(Resized::Truncated(self.dropped))),
+            _ => Ok(None),
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for FixedSizeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(remaining) = self.remaining else {
+            return self.inner.write(buf);
+        };
+        let room = remaining.min(buf.len() as u64) as usize;
+        if room == 0 {
+            self.dropped += buf.len() as u64;
+            return Ok(buf.len());
+        }
+        let written = self.inner.write(&buf[..room])?;
+        self.remaining = Some(remaining - written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// SHA1 and size of a file read through the filesystem.
+pub fn hash_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    hash_reader(&mut file)
+}
+
+/// SHA1 and size of everything read from `reader`.
+pub(crate) fn hash_reader<R: io::Read + ?Sized>(reader: &mut R) -> io::Result<(String, u64)> {
+    let mut hasher = HashingWriter::new(io::sink());
+    let size = copy_buffered(reader, &mut hasher)?;
+    Ok((hasher.hex_digest(), size))
+}
+
+pub(crate) fn copy_buffered<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+where
+    R: io::Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buffer = vec![0u8; FILE_BUFFER_SIZE];
+    let mut total = 0u64;
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        total += bytes_read as u64;
+    }
+
+    Ok(total)
+}
+
+pub(crate) fn no_entry_error() -> io::Error {
+    io::Error::other("no entry is open on the output sink")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_name_unix() {
+        assert_eq!(entry_name("/var/log/syslog"), "var/log/syslog");
+        assert_eq!(entry_name("var//log/./syslog"), "var/log/syslog");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_entry_name_windows() {
+        assert_eq!(
+            entry_name("C:\\Windows\\System32\\config\\SAM"),
+            "C/Windows/System32/config/SAM"
+        );
+    }
+
+    #[test]
+    fn test_hashing_writer() {
+        let mut writer = HashingWriter::new(Vec::new());
+        writer.write_all(b"abc").unwrap();
+
+        assert_eq!(writer.bytes_written(), 3);
+        assert_eq!(
+            writer.hex_digest(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn test_fixed_size_writer() {
+        let mut writer = FixedSizeWriter::new(Vec::new(), Some(4));
+        writer.write_all(b"abcdef").unwrap();
+        assert_eq!(writer.finish().unwrap(), Some(Resized::Truncated(2)));
+        assert_eq!(writer.into_inner(), b"abcd");
+
+        let mut writer = FixedSizeWriter::new(Vec::new(), Some(4));
+        writer.write_all(b"ab").unwrap();
+        assert_eq!(writer.finish().unwrap(), Some(Resized::Padded(2)));
+        assert_eq!(writer.into_inner(), b"ab\0\0");
+
+        let mut writer = FixedSizeWriter::new(Vec::new(), None);
+        writer.write_all(b"abcdef").unwrap();
+        assert_eq!(writer.finish().unwrap(), None);
+        assert_eq!(writer.into_inner(), b"abcdef");
+    }
+
+    #[test]
+    fn test_entry_metadata_from_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let metadata = EntryMetadata::from_path(&path);
+        assert_eq!(metadata.size, 5);
+        assert!(metadata.modified.is_some());
+    }
+
+    #[test]
+    fn test_archive_format_from_path() {
+        assert_eq!(
+            ArchiveFormat::from_path("out/Collector_host.tar.zst"),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path("Collector_host.ZIP"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(ArchiveFormat::from_path("Collector_host.zip.age"), None);
+    }
+
+    #[test]
+    fn test_archive_format_parse() {
+        assert_eq!("zip".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Zip));
+        assert_eq!("TGZ".parse::<ArchiveFormat>(), Ok(ArchiveFormat::TarGz));
+        assert_eq!(
+            "tar.zst".parse::<ArchiveFormat>(),
+            Ok(ArchiveFormat::TarZst)
+        );
+        assert!("rar".parse::<ArchiveFormat>().is_err());
+
+        for format in ArchiveFormat::ALL {
+            assert_eq!(format.to_string().parse::<ArchiveFormat>(), Ok(format));
+        }
+    }
+
+    #[test]
+    fn test_entry_metadata_missing_path() {
+        let metadata = EntryMetadata::from_path("/does/not/exist");
+        assert_eq!(metadata, EntryMetadata::default());
+    }
+}
@@ -0,0 +1,328 @@
+//! Files an archive sink writes its bytes to: a single archive file, or the
+//! same byte stream cut into fixed-size numbered volumes.
+//!
+//! Volumes are plain byte splits (`Collector_<host>.zip.001`, `.002`, ...),
+//! so concatenating them in order yields the original archive. The index
+//! `<archive>.index.csv` lists every volume with its size and SHA1, followed by
+//! a row named after the archive itself holding the total size and the SHA1 of
+//! the reassembled archive.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use age::stream::StreamWriter;
+use age::x25519::Recipient;
+use sha1::{Digest, Sha1};
+
+use crate::error::{CollectorError, Result};
+use crate::sink::encrypt::encrypt_to;
+
+/// Byte destination of an archive sink.
+pub trait ArchiveOutput: Write + Send {
+    /// Flush and close the output, returning the files written on disk.
+    fn close(self) -> Result<Vec<PathBuf>>
+    where
+        Self: Sized;
+}
+
+impl ArchiveOutput for Vec<u8> {
+    fn close(self) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Archive written to disk, whole or split into volumes, optionally
+/// encrypted before it reaches the disk.
+pub enum ArchiveFile {
+    Single {
+        path: PathBuf,
+        file: BufWriter<File>,
+    },
+    Volumes(Box<VolumeWriter>),
+    Encrypted(Box<StreamWriter<ArchiveFile>>),
+}
+
+impl ArchiveFile {
+    /// Create the archive at `path`, split in volumes of `volume_size` bytes
+    /// when set.
+    pub fn create<P: AsRef<Path>>(path: P, volume_size: Option<u64>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        match volume_size {
+            Some(size) => {
+                let volumes = VolumeWriter::new(path, size)?;
+                Ok(ArchiveFile::Volumes(Box::new(volumes)))
+            }
+            None => {
+                let file = create_file(&path)?;
+                Ok(ArchiveFile::Single {
+                    path,
+                    file: BufWriter::new(file),
+                })
+            }
+        }
+    }
+
+    /// Encrypt everything written from now on to `recipients`.
+    pub fn encrypt(self, recipients: &[Recipient]) -> Result<Self> {
+        Ok(ArchiveFile::Encrypted(Box::new(encrypt_to(
+            self, recipients,
+        )?)))
+    }
+}
+
+impl Write for ArchiveFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ArchiveFile::Single { file, .. } => file.write(buf),
+            ArchiveFile::Volumes(volumes) => volumes.write(buf),
+            ArchiveFile::Encrypted(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ArchiveFile::Single { file, .. } => file.flush(),
+            ArchiveFile::Volumes(volumes) => volumes.flush(),
+            ArchiveFile::Encrypted(stream) => stream.flush(),
+        }
+    }
+}
+
+impl ArchiveOutput for ArchiveFile {
+    fn close(self) -> Result<Vec<PathBuf>> {
+        match self {
+            ArchiveFile::Single { path, mut file } => {
+                file.flush().map_err(|e| CollectorError::FileWrite {
+                    path: path.clone(),
+                    source: e,
+                })?;
+                Ok(vec![path])
+            }
+            ArchiveFile::Volumes(volumes) => volumes.close(),
+            ArchiveFile::Encrypted(stream) => stream.finish()?.close(),
+        }
+    }
+}
+
+/// One finished volume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha1: String,
+}
+
+struct OpenVolume {
+    path: PathBuf,
+    file: BufWriter<File>,
+    hasher: Sha1,
+    size: u64,
+}
+
+/// Writer cutting its input into numbered volumes of at most `volume_size`
+/// bytes.
+pub struct VolumeWriter {
+    archive_path: PathBuf,
+    volume_size: u64,
+    current: Option<OpenVolume>,
+    volumes: Vec<VolumeInfo>,
+    total_hasher: Sha1,
+    total_size: u64,
+}
+
+impl VolumeWriter {
+    pub fn new<P: Into<PathBuf>>(archive_path: P, volume_size: u64) -> Result<Self> {
+        if volume_size == 0 {
+            return Err(CollectorError::Config(
+                "Volume size must be greater than zero".into(),
+            ));
+        }
+
+        Ok(Self {
+            archive_path: archive_path.into(),
+            volume_size,
+            current: None,
+            volumes: Vec::new(),
+            total_hasher: Sha1::new(),
+            total_size: 0,
+        })
+    }
+
+    /// Path of the volume with the given 1-based number.
+    pub fn volume_path(archive_path: &Path, number: usize) -> PathBuf {
+        let mut name = archive_path.as_os_str().to_os_string();
+        name.push(format!(".{:03}", number));
+        PathBuf::from(name)
+    }
+
+    /// Path of the index listing the volumes of `archive_path`.
+    pub fn index_path(archive_path: &Path) -> PathBuf {
+        let mut name = archive_path.as_os_str().to_os_string();
+        name.push(".index.csv");
+        PathBuf::from(name)
+    }
+
+    pub fn volumes(&self) -> &[VolumeInfo] {
+        &self.volumes
+    }
+
+    fn open_next(&mut self) -> io::Result<&mut OpenVolume> {
+        let path = Self::volume_path(&self.archive_path, self.volumes.len() + 1);
+        let file = File::create(&path)?;
+
+        Ok(self.current.insert(OpenVolume {
+            path,
+            file: BufWriter::new(file),
+            hasher: Sha1::new(),
+            size: 0,
+        }))
+    }
+
+    fn close_current(&mut self) -> io::Result<()> {
+        if let Some(mut volume) = self.current.take() {
+            volume.file.flush()?;
+            self.volumes.push(VolumeInfo {
+                path: volume.path,
+                size: volume.size,
+                sha1: hex::encode(volume.hasher.finalize()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Close the last volume and write the index.
+    pub fn close(mut self) -> Result<Vec<PathBuf>> {
+        if self.current.is_none() && self.volumes.is_empty() {
+            // An empty archive still gets its first volume
+            self.open_next()?;
+        }
+        self.close_current()?;
+
+        let index_path = Self::index_path(&self.archive_path);
+        let archive_name = file_name(&self.archive_path);
+        let mut index = String::from("volume,size,sha1\n");
+        for volume in &self.volumes {
+            index.push_str(&format!(
+                "{},{},{}\n",
+                file_name(&volume.path),
+                volume.size,
+                volume.sha1
+            ));
+        }
+        index.push_str(&format!(
+            "{},{},{}\n",
+            archive_name,
+            self.total_size,
+            hex::encode(self.total_hasher.finalize())
+        ));
+
+        std::fs::write(&index_path, index).map_err(|e| CollectorError::FileWrite {
+            path: index_path.clone(),
+            source: e,
+        })?;
+
+        log::info!("Split {} into {} volumes", archive_name, self.volumes.len());
+
+        let mut paths: Vec<PathBuf> = self.volumes.into_iter().map(|v| v.path).collect();
+        paths.push(index_path);
+        Ok(paths)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|v| v.size >= self.volume_size)
+        {
+            self.close_current()?;
+        }
+
+        let volume_size = self.volume_size;
+        let volume = match self.current {
+            Some(ref mut volume) => volume,
+            None => self.open_next()?,
+        };
+
+        let room = (volume_size - volume.size).min(buf.len() as u64) as usize;
+        let written = volume.file.write(&buf[..room])?;
+        volume.hasher.update(&buf[..written]);
+        volume.size += written as u64;
+
+        self.total_hasher.update(&buf[..written]);
+        self.total_size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current {
+            Some(ref mut volume) => volume.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn create_file(path: &Path) -> Result<File> {
+    File::create(path).map_err(|e| CollectorError::FileWrite {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_writer_split() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("out.zip");
+
+        let mut writer = VolumeWriter::new(&archive, 4).unwrap();
+        writer.write_all(b"0123456789").unwrap();
+        let paths = writer.close().unwrap();
+
+        assert_eq!(paths.len(), 4);
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"0123");
+        assert_eq!(std::fs::read(&paths[2]).unwrap(), b"89");
+        assert!(paths[2].to_string_lossy().ends_with("out.zip.003"));
+
+        let index = std::fs::read_to_string(&paths[3]).unwrap();
+        let lines: Vec<&str> = index.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("out.zip.001,4,"));
+        assert!(lines[4].starts_with("out.zip,10,"));
+        assert!(lines[4].ends_with(&hex::encode(Sha1::digest(b"0123456789"))));
+    }
+
+    #[test]
+    fn test_volume_writer_exact_boundary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("out.tar");
+
+        let mut writer = VolumeWriter::new(&archive, 5).unwrap();
+        writer.write_all(b"01234").unwrap();
+        let paths = writer.close().unwrap();
+
+        // No empty trailing volume
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[test]
+    fn test_volume_writer_rejects_zero() {
+        assert!(VolumeWriter::new("out.zip", 0).is_err());
+    }
+}
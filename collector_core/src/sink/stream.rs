@@ -0,0 +1,323 @@
+//! Minimal framed stream for piping a collection to another process or host.
+//!
+//! Layout (integers are big-endian):
+//!
+//! ```text
+//! "CLSTREAM" u8:version
+//! per entry:  u8:1  u32:name_len name  u64:size  i64:mtime  u32:mode
+//!             { u32:chunk_len chunk }*  u32:0
+//! end:        u8:0
+//! ```
+//!
+//! The announced size is the source size; the chunks carry what was actually read.
+//! `mtime` is in Unix seconds and `mode` holds the Unix permission bits. A
+//! stream is read back entry by entry with [`StreamReader`].
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use crate::error::{CollectorError, Result};
+use crate::sink::{EntryMetadata, OutputSink, entry_name, no_entry_error};
+
+pub const STREAM_MAGIC: &[u8; 8] = b"CLSTREAM";
+pub const STREAM_VERSION: u8 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_ENTRY: u8 = 1;
+
+/// Sink writing entries to any `Write` as a framed stream.
+pub struct StreamSink<W: Write + Send> {
+    inner: Option<W>,
+    in_entry: bool,
+}
+
+impl<W: Write + Send> StreamSink<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(STREAM_MAGIC)?;
+        inner.write_all(&[STREAM_VERSION])?;
+
+        Ok(Self {
+            inner: Some(inner),
+            in_entry: false,
+        })
+    }
+
+    fn inner_mut(&mut self) -> io::Result<&mut W> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| io::Error::other("stream already finalized"))
+    }
+
+    /// Terminate the stream and return the inner writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.finish_entry()?;
+        let mut inner = self
+            .inner
+            .take()
+            .ok_or_else(|| CollectorError::CollectionFailed("Stream already finalized".into()))?;
+        inner.write_all(&[TAG_END])?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write + Send> Write for StreamSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.in_entry {
+            return Err(no_entry_error());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(u32::MAX as usize);
+        let inner = self.inner_mut()?;
+        inner.write_all(&(len as u32).to_be_bytes())?;
+        inner.write_all(&buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputSink for StreamSink<W> {
+    fn start_entry(&mut self, path: &str, metadata: &EntryMetadata) -> Result<()> {
+        self.finish_entry()?;
+
+        let name = entry_name(path);
+        let mtime = metadata.modified.map(|t| t.unix_seconds()).unwrap_or(0);
+        let mode = metadata.mode.unwrap_or(0o644);
+
+        let inner = self.inner_mut()?;
+        inner.write_all(&[TAG_ENTRY])?;
+        inner.write_all(&(name.len() as u32).to_be_bytes())?;
+        inner.write_all(name.as_bytes())?;
+        inner.write_all(&metadata.size.to_be_bytes())?;
+        inner.write_all(&mtime.to_be_bytes())?;
+        inner.write_all(&mode.to_be_bytes())?;
+
+        self.in_entry = true;
+        Ok(())
+    }
+
+    fn finish_entry(&mut self) -> Result<()> {
+        if self.in_entry {
+            self.inner_mut()?.write_all(&0u32.to_be_bytes())?;
+            self.in_entry = false;
+        }
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>> {
+        self.into_inner()?;
+        Ok(Vec::new())
+    }
+}
+
+/// Header of an entry read back from a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    pub name: String,
+    /// Size of the source when the entry was started.
+    pub size: u64,
+    pub mtime: i64,
+    pub mode: u32,
+}
+
+/// Decoder of a stream written by [`StreamSink`].
+///
+/// `next_entry` returns the header of each entry, and reading from the
+/// `StreamReader` returns its content until the end of the entry.
+pub struct StreamReader<R: Read> {
+    inner: R,
+    /// Bytes left in the current chunk.
+    chunk_left: u32,
+    in_entry: bool,
+    finished: bool,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Check the stream magic and version.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != STREAM_MAGIC {
+            return Err(invalid_stream("not a collector stream").into());
+        }
+        let version = read_u8(&mut inner)?;
+        if version != STREAM_VERSION {
+            return Err(invalid_stream(format!("unsupported stream version {}", version)).into());
+        }
+
+        Ok(Self {
+            inner,
+            chunk_left: 0,
+            in_entry: false,
+            finished: false,
+        })
+    }
+
+    /// Skip what is left of the current entry and read the next header,
+    /// `None` at the end of the stream.
+    pub fn next_entry(&mut self) -> Result<Option<StreamEntry>> {
+        if self.in_entry {
+            io::copy(self, &mut io::sink())?;
+        }
+        if self.finished {
+            return Ok(None);
+        }
+
+        match read_u8(&mut self.inner)? {
+            TAG_END => {
+                self.finished = true;
+                Ok(None)
+            }
+            TAG_ENTRY => {
+                let name_len = read_u32(&mut self.inner)? as usize;
+                let mut name = vec![0u8; name_len];
+                self.inner.read_exact(&mut name)?;
+                let name = String::from_utf8(name)
+                    .map_err(|_| invalid_stream("entry name is not UTF-8"))?;
+
+                let mut size = [0u8; 8];
+                self.inner.read_exact(&mut size)?;
+                let mut mtime = [0u8; 8];
+                self.inner.read_exact(&mut mtime)?;
+                let mode = read_u32(&mut self.inner)?;
+
+                self.in_entry = true;
+                self.chunk_left = 0;
+                Ok(Some(StreamEntry {
+                    name,
+                    size: u64::from_be_bytes(size),
+                    mtime: i64::from_be_bytes(mtime),
+                    mode,
+                }))
+            }
+            tag => Err(invalid_stream(format!("unknown tag {}", tag)).into()),
+        }
+    }
+}
+
+impl<R: Read> Read for StreamReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.in_entry || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.chunk_left == 0 {
+            self.chunk_left = read_u32(&mut self.inner)?;
+            if self.chunk_left == 0 {
+                self.in_entry = false;
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.chunk_left as usize);
+        let read = self.inner.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.chunk_left -= read as u32;
+        Ok(read)
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn invalid_stream<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_sink_framing() {
+        let mut sink = StreamSink::new(Vec::new()).unwrap();
+        sink.add_bytes("a.txt", b"abc").unwrap();
+        let data = sink.into_inner().unwrap();
+
+        assert!(data.starts_with(STREAM_MAGIC));
+        assert_eq!(data[8], STREAM_VERSION);
+        assert_eq!(data[9], TAG_ENTRY);
+        assert_eq!(&data[10..14], &5u32.to_be_bytes());
+        assert_eq!(&data[14..19], b"a.txt");
+        assert!(data.ends_with(&[0, 0, 0, 3, b'a', b'b', b'c', 0, 0, 0, 0, TAG_END]));
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let metadata = EntryMetadata {
+            size: 11,
+            modified: Some(filetime::FileTime::from_unix_time(1_700_000_000, 0)),
+            mode: Some(0o600),
+            ..Default::default()
+        };
+
+        let mut sink = StreamSink::new(Vec::new()).unwrap();
+        sink.start_entry("/var/log/auth.log", &metadata).unwrap();
+        sink.write_all(b"sshd ").unwrap();
+        sink.write_all(b"login").unwrap();
+        sink.finish_entry().unwrap();
+        sink.add_bytes("skipped", b"not read").unwrap();
+        sink.add_bytes("empty", b"").unwrap();
+        let data = sink.into_inner().unwrap();
+
+        let mut reader = StreamReader::new(data.as_slice()).unwrap();
+        let entry = reader.next_entry().unwrap().unwrap();
+        assert_eq!(
+            entry,
+            StreamEntry {
+                name: "var/log/auth.log".to_string(),
+                size: 11,
+                mtime: 1_700_000_000,
+                mode: 0o600,
+            }
+        );
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "sshd login");
+
+        assert_eq!(reader.next_entry().unwrap().unwrap().name, "skipped");
+        let empty = reader.next_entry().unwrap().unwrap();
+        assert_eq!(empty.name, "empty");
+        assert_eq!(empty.size, 0);
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stream_reader_rejects_other_data() {
+        assert!(StreamReader::new(&b"PK\x03\x04 not a stream"[..]).is_err());
+
+        let mut data = STREAM_MAGIC.to_vec();
+        data.extend_from_slice(&[STREAM_VERSION, TAG_ENTRY, 0, 0]);
+        let mut reader = StreamReader::new(data.as_slice()).unwrap();
+        assert!(reader.next_entry().is_err());
+    }
+
+    #[test]
+    fn test_stream_sink_write_without_entry() {
+        let mut sink = StreamSink::new(Vec::new()).unwrap();
+        assert!(sink.write_all(b"data").is_err());
+    }
+}
@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use filetime::FileTime;
+use flate2::write::GzEncoder;
+use tar::{EntryType, Header};
+
+use crate::error::{CollectorError, Result};
+use crate::sink::{
+    ArchiveFile, ArchiveOutput, EntryMetadata, OutputSink, entry_name, no_entry_error,
+};
+
+/// Largest value that fits the octal uid/gid fields of a ustar header.
+const USTAR_MAX_ID: u32 = 0o7777777;
+
+const ZSTD_LEVEL: i32 = 3;
+
+const BLOCK_SIZE: usize = 512;
+
+/// Compression applied on top of the tar stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TarCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(inner: W, compression: TarCompression) -> io::Result<Self> {
+        Ok(match compression {
+            TarCompression::None => Encoder::Plain(inner),
+            TarCompression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(inner, flate2::Compression::default()))
+            }
+            TarCompression::Zstd => Encoder::Zstd(zstd::Encoder::new(inner, ZSTD_LEVEL)?),
+        })
+    }
+
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(inner) => Ok(inner),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(inner) => inner.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(inner) => inner.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Entry whose header is written, receiving its content.
+struct OpenEntry {
+    name: String,
+    /// Size announced in the header
+    size: u64,
+    written: u64,
+    /// Content past the announced size, left out
+    dropped: u64,
+}
+
+/// POSIX (pax) tar archive writer, optionally gzip or zstd compressed.
+///
+/// Entries are streamed: the header announces the size given to
+/// `start_entry`, shorter content is zero-padded and longer content cut, so
+/// nothing is buffered on disk. Long paths, sub-second timestamps, large ids
+/// and extended attributes are stored in a pax extended header preceding the
+/// entry.
+pub struct TarSink<W: Write + Send> {
+    encoder: Option<Encoder<W>>,
+    current: Option<OpenEntry>,
+    user_names: HashMap<u32, Option<String>>,
+    group_names: HashMap<u32, Option<String>>,
+}
+
+impl TarSink<ArchiveFile> {
+    /// Create a tar archive file at `path`, split in volumes when
+    /// `volume_size` is set.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        compression: TarCompression,
+        volume_size: Option<u64>,
+    ) -> Result<Self> {
+        Self::compressed(ArchiveFile::create(path, volume_size)?, compression)
+    }
+}
+
+impl<W: Write + Send> TarSink<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_encoder(Encoder::Plain(inner))
+    }
+
+    pub fn compressed(inner: W, compression: TarCompression) -> Result<Self> {
+        Ok(Self::with_encoder(Encoder::new(inner, compression)?))
+    }
+
+    fn with_encoder(encoder: Encoder<W>) -> Self {
+        Self {
+            encoder: Some(encoder),
+            current: None,
+            user_names: HashMap::new(),
+            group_names: HashMap::new(),
+        }
+    }
+
+    fn encoder_mut(&mut self) -> Result<&mut Encoder<W>> {
+        self.encoder
+            .as_mut()
+            .ok_or_else(|| CollectorError::CollectionFailed("Tar archive already finalized".into()))
+    }
+
+    /// Build the ustar header of an entry and the pax records for whatever
+    /// does not fit in it.
+    fn entry_header(
+        &mut self,
+        name: &str,
+        metadata: &EntryMetadata,
+        size: u64,
+    ) -> Result<(Header, Vec<u8>)> {
+        let mut header = Header::new_ustar();
+        let mut pax = Vec::new();
+
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(size);
+        header.set_mode(metadata.mode.map(|m| m & 0o7777).unwrap_or(0o644));
+
+        if header.set_path(name).is_err() {
+            header.set_path(ustar_fallback_name(name))?;
+            pax_record(&mut pax, "path", name.as_bytes());
+        }
+
+        let mtime = metadata.modified.unwrap_or_else(FileTime::zero);
+        header.set_mtime(mtime.unix_seconds().max(0) as u64);
+        for (key, time) in [
+            ("mtime", metadata.modified),
+            ("atime", metadata.accessed),
+            ("ctime", metadata.changed),
+        ] {
+            if let Some(time) = time {
+                pax_record(&mut pax, key, pax_time(time).as_bytes());
+            }
+        }
+
+        let uid = metadata.uid.unwrap_or(0);
+        let gid = metadata.gid.unwrap_or(0);
+        for (key, id) in [("uid", uid), ("gid", gid)] {
+            if id > USTAR_MAX_ID {
+                pax_record(&mut pax, key, id.to_string().as_bytes());
+            }
+        }
+        header.set_uid(uid.min(USTAR_MAX_ID) as u64);
+        header.set_gid(gid.min(USTAR_MAX_ID) as u64);
+
+        if let Some(user) = metadata.uid.and_then(|uid| self.user_name(uid))
+            && header.set_username(&user).is_err()
+        {
+            pax_record(&mut pax, "uname", user.as_bytes());
+        }
+        if let Some(group) = metadata.gid.and_then(|gid| self.group_name(gid))
+            && header.set_groupname(&group).is_err()
+        {
+            pax_record(&mut pax, "gname", group.as_bytes());
+        }
+
+        for (key, value) in &metadata.xattrs {
+            pax_record(&mut pax, &format!("SCHILY.xattr.{}", key), value);
+        }
+
+        header.set_cksum();
+        Ok((header, pax))
+    }
+
+    fn user_name(&mut self, uid: u32) -> Option<String> {
+        self.user_names
+            .entry(uid)
+            .or_insert_with(|| lookup_user(uid))
+            .clone()
+    }
+
+    fn group_name(&mut self, gid: u32) -> Option<String> {
+        self.group_names
+            .entry(gid)
+            .or_insert_with(|| lookup_group(gid))
+            .clone()
+    }
+}
+
+impl<W: ArchiveOutput> TarSink<W> {
+    /// Finish the archive and return the inner writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.finish_entry()?;
+        let mut encoder = self.encoder.take().ok_or_else(|| {
+            CollectorError::CollectionFailed("Tar archive already finalized".into())
+        })?;
+        // Two empty blocks end the archive
+        encoder.write_all(&[0u8; 2 * BLOCK_SIZE])?;
+        let mut inner = encoder.finish()?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write + Send> Write for TarSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (Some(entry), Some(encoder)) = (self.current.as_mut(), self.encoder.as_mut()) else {
+            return Err(no_entry_error());
+        };
+
+        let room = (entry.size - entry.written).min(buf.len() as u64) as usize;
+        if room == 0 {
+            // The header is written: content past its size cannot be stored
+            entry.dropped += buf.len() as u64;
+            return Ok(buf.len());
+        }
+        let written = encoder.write(&buf[..room])?;
+        entry.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.encoder {
+            Some(ref mut encoder) => encoder.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: ArchiveOutput> OutputSink for TarSink<W> {
+    fn start_entry(&mut self, path: &str, metadata: &EntryMetadata) -> Result<()> {
+        self.finish_entry()?;
+
+        let name = entry_name(path);
+        let (header, pax) = self.entry_header(&name, metadata, metadata.size)?;
+        let encoder = self.encoder_mut()?;
+
+        if !pax.is_empty() {
+            let mut pax_header = Header::new_ustar();
+            pax_header.set_entry_type(EntryType::XHeader);
+            pax_header.set_path(pax_header_name(&name))?;
+            pax_header.set_size(pax.len() as u64);
+            pax_header.set_mode(0o644);
+            pax_header.set_mtime(header.mtime().unwrap_or(0));
+            pax_header.set_cksum();
+            encoder.write_all(pax_header.as_bytes())?;
+            encoder.write_all(&pax)?;
+            pad_block(encoder, pax.len() as u64)?;
+        }
+        encoder.write_all(header.as_bytes())?;
+
+        self.current = Some(OpenEntry {
+            name,
+            size: metadata.size,
+            written: 0,
+            dropped: 0,
+        });
+        Ok(())
+    }
+
+    fn finish_entry(&mut self) -> Result<()> {
+        let Some(entry) = self.current.take() else {
+            return Ok(());
+        };
+        let encoder = self.encoder_mut()?;
+
+        let missing = entry.size - entry.written;
+        if missing > 0 {
+            log::warn!(
+                "{} is {} bytes shorter than its tar header, zero-padded",
+                entry.name,
+                missing
+            );
+            io::copy(&mut io::repeat(0).take(missing), encoder)?;
+        }
+        if entry.dropped > 0 {
+            log::warn!(
+                "{} is {} bytes longer than its tar header, cut",
+                entry.name,
+                entry.dropped
+            );
+        }
+        pad_block(encoder, entry.size)?;
+        Ok(())
+    }
+
+    fn fixed_size(&self) -> bool {
+        true
+    }
+
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>> {
+        let paths = self.into_inner()?.close()?;
+
+        if let Some(path) = paths.first() {
+            log::info!("Created tar archive: {}", path.display());
+        }
+        Ok(paths)
+    }
+}
+
+/// Zero-fill the last block of `len` bytes of data.
+fn pad_block<W: Write>(out: &mut W, len: u64) -> io::Result<()> {
+    let partial = (len % BLOCK_SIZE as u64) as usize;
+    if partial > 0 {
+        out.write_all(&[0u8; BLOCK_SIZE][partial..])?;
+    }
+    Ok(())
+}
+
+/// Append a `"<len> <key>=<value>\n"` pax record, where `len` counts itself.
+fn pax_record(out: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let body = key.len() + value.len() + 3;
+    let mut len = body + body.to_string().len();
+    while len != body + len.to_string().len() {
+        len = body + len.to_string().len();
+    }
+
+    out.extend_from_slice(format!("{} {}=", len, key).as_bytes());
+    out.extend_from_slice(value);
+    out.push(b'\n');
+}
+
+/// Timestamp as pax decimal seconds with nanosecond precision.
+fn pax_time(time: FileTime) -> String {
+    let seconds = time.unix_seconds();
+    let nanos = time.nanoseconds();
+
+    if seconds < 0 && nanos > 0 {
+        format!("-{}.{:09}", -(seconds + 1), 1_000_000_000 - nanos)
+    } else {
+        format!("{}.{:09}", seconds, nanos)
+    }
+}
+
+/// Name stored in the ustar header when the real path only fits in pax.
+fn ustar_fallback_name(name: &str) -> String {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    truncate_bytes(file_name, 99).to_string()
+}
+
+fn pax_header_name(name: &str) -> String {
+    format!(
+        "PaxHeaders/{}",
+        truncate_bytes(&ustar_fallback_name(name), 80)
+    )
+}
+
+fn truncate_bytes(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(unix)]
+fn lookup_user(uid: u32) -> Option<String> {
+    nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid))
+        .ok()
+        .flatten()
+        .map(|user| user.name)
+}
+
+#[cfg(unix)]
+fn lookup_group(gid: u32) -> Option<String> {
+    nix::unistd::Group::from_gid(nix::unistd::Gid::from_raw(gid))
+        .ok()
+        .flatten()
+        .map(|group| group.name)
+}
+
+#[cfg(not(unix))]
+fn lookup_user(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn lookup_group(_gid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tar::Archive;
+
+    #[test]
+    fn test_tar_sink_roundtrip() {
+        let mut sink = TarSink::new(Vec::new());
+        sink.start_entry("/var/log/auth.log", &EntryMetadata::generated(13))
+            .unwrap();
+        sink.write_all(b"sshd accepted").unwrap();
+        sink.finish_entry().unwrap();
+
+        let data = sink.into_inner().unwrap();
+        let mut archive = Archive::new(data.as_slice());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+
+        assert_eq!(entry.path().unwrap().to_string_lossy(), "var/log/auth.log");
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "sshd accepted");
+    }
+
+    #[test]
+    fn test_tar_sink_fits_content_to_header() {
+        let mut sink = TarSink::new(Vec::new());
+        sink.start_entry("shrunk", &EntryMetadata::generated(8))
+            .unwrap();
+        sink.write_all(b"half").unwrap();
+        sink.start_entry("grown", &EntryMetadata::generated(4))
+            .unwrap();
+        sink.write_all(b"appended").unwrap();
+        sink.add_bytes("kept", b"ok").unwrap();
+
+        let data = sink.into_inner().unwrap();
+        let mut archive = Archive::new(data.as_slice());
+        let entries: Vec<(String, Vec<u8>)> = archive
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let mut e = e.unwrap();
+                let mut content = Vec::new();
+                e.read_to_end(&mut content).unwrap();
+                (e.path().unwrap().to_string_lossy().to_string(), content)
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("shrunk".to_string(), b"half\0\0\0\0".to_vec()),
+                ("grown".to_string(), b"appe".to_vec()),
+                ("kept".to_string(), b"ok".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tar_sink_pax_headers() {
+        let long_path = format!("{}/evidence.log", "nested".repeat(60));
+        let metadata = EntryMetadata {
+            modified: Some(FileTime::from_unix_time(1_700_000_000, 123_456_789)),
+            uid: Some(4_000_000),
+            gid: Some(1000),
+            xattrs: vec![("user.origin".to_string(), b"host-a".to_vec())],
+            ..Default::default()
+        };
+
+        let metadata = EntryMetadata {
+            size: 4,
+            ..metadata
+        };
+        let mut sink = TarSink::new(Vec::new());
+        sink.start_entry(&long_path, &metadata).unwrap();
+        sink.write_all(b"data").unwrap();
+        sink.finish_entry().unwrap();
+
+        let data = sink.into_inner().unwrap();
+        let mut archive = Archive::new(data.as_slice());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+
+        assert_eq!(entry.path().unwrap().to_string_lossy(), long_path);
+        assert_eq!(entry.header().mtime().unwrap(), 1_700_000_000);
+
+        let records: HashMap<String, Vec<u8>> = entry
+            .pax_extensions()
+            .unwrap()
+            .unwrap()
+            .map(|r| {
+                let r = r.unwrap();
+                (r.key().unwrap().to_string(), r.value_bytes().to_vec())
+            })
+            .collect();
+        assert_eq!(records["mtime"], b"1700000000.123456789");
+        assert_eq!(records["uid"], b"4000000");
+        assert_eq!(records["SCHILY.xattr.user.origin"], b"host-a");
+    }
+
+    #[test]
+    fn test_tar_sink_compressed() {
+        for compression in [TarCompression::Gzip, TarCompression::Zstd] {
+            let mut sink = TarSink::compressed(Vec::new(), compression).unwrap();
+            sink.add_bytes("file.txt", b"compressed").unwrap();
+            let data = sink.into_inner().unwrap();
+
+            let decoded: Box<dyn Read> = match compression {
+                TarCompression::Gzip => Box::new(flate2::read::GzDecoder::new(data.as_slice())),
+                _ => Box::new(zstd::Decoder::new(data.as_slice()).unwrap()),
+            };
+            let mut archive = Archive::new(decoded);
+            let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+
+            assert_eq!(content, "compressed");
+        }
+    }
+
+    #[test]
+    fn test_pax_record_length() {
+        let mut out = Vec::new();
+        pax_record(&mut out, "path", b"abc");
+        assert_eq!(out, b"12 path=abc\n");
+
+        // Length crossing a digit boundary must count its own extra digit
+        let mut out = Vec::new();
+        pax_record(&mut out, "path", &[b'a'; 91]);
+        assert_eq!(out.len(), 101);
+        assert!(out.starts_with(b"101 "));
+    }
+
+    #[test]
+    fn test_pax_time_negative() {
+        assert_eq!(
+            pax_time(FileTime::from_unix_time(-2, 500_000_000)),
+            "-1.500000000"
+        );
+        assert_eq!(pax_time(FileTime::from_unix_time(5, 0)), "5.000000000");
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime as ChronoDateTime, Datelike, Timelike};
+use filetime::FileTime;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{AesMode, CompressionMethod, DateTime, ZipWriter};
+
+use crate::error::{CollectorError, Result};
+use crate::sink::{
+    ArchiveFile, ArchiveOutput, EntryMetadata, OutputSink, entry_name, no_entry_error,
+};
+
+/// ZIP archive written as a stream, optionally AES-256 encrypted.
+///
+/// The archive never seeks, so it can target any writer (file, pipe, socket).
+pub struct ZipSink<W: Write + Send> {
+    zip: Option<ZipWriter<StreamWriter<W>>>,
+    password: Option<String>,
+    in_entry: bool,
+}
+
+impl ZipSink<ArchiveFile> {
+    /// Create a ZIP archive file at `path`, split in volumes when
+    /// `volume_size` is set.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        password: Option<String>,
+        volume_size: Option<u64>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            ArchiveFile::create(path, volume_size).map_err(|e| CollectorError::ZipCreation {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })?;
+
+        Ok(Self::new(file, password))
+    }
+}
+
+impl<W: Write + Send> ZipSink<W> {
+    pub fn new(inner: W, password: Option<String>) -> Self {
+        Self {
+            zip: Some(ZipWriter::new_stream(inner)),
+            password: password.filter(|p| !p.is_empty()),
+            in_entry: false,
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.password.is_some()
+    }
+
+    fn zip_mut(&mut self) -> Result<&mut ZipWriter<StreamWriter<W>>> {
+        self.zip
+            .as_mut()
+            .ok_or_else(|| CollectorError::CollectionFailed("ZIP archive already finalized".into()))
+    }
+
+    /// Finish the archive and return the inner writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        let zip = self.zip.take().ok_or_else(|| {
+            CollectorError::CollectionFailed("ZIP archive already finalized".into())
+        })?;
+        let mut inner = zip.finish()?.into_inner();
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write + Send> Write for ZipSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match (self.in_entry, self.zip.as_mut()) {
+            (true, Some(zip)) => zip.write(buf),
+            _ => Err(no_entry_error()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.zip.as_mut() {
+            Some(zip) => zip.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: ArchiveOutput> OutputSink for ZipSink<W> {
+    fn start_entry(&mut self, path: &str, metadata: &EntryMetadata) -> Result<()> {
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(metadata.mode.map(|m| m & 0o777).unwrap_or(0o644))
+            // Streaming archives cannot go back and widen headers, and a size
+            // of 0 may mean the source could not be stat'ed (raw NTFS reads)
+            .large_file(metadata.size == 0 || metadata.size >= u32::MAX as u64);
+
+        if let Some(time) = metadata.modified.and_then(zip_datetime) {
+            options = options.last_modified_time(time);
+        }
+
+        let name = entry_name(path);
+        let password = self.password.clone();
+        let zip = self.zip_mut()?;
+
+        match password {
+            Some(ref pwd) => {
+                zip.start_file(name, options.with_aes_encryption(AesMode::Aes256, pwd))?
+            }
+            None => zip.start_file(name, options)?,
+        }
+
+        self.in_entry = true;
+        Ok(())
+    }
+
+    fn finish_entry(&mut self) -> Result<()> {
+        // The ZIP writer closes an entry when the next one starts or on finish
+        self.in_entry = false;
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>> {
+        let paths = self.into_inner()?.close()?;
+
+        if let Some(path) = paths.first() {
+            log::info!("Created ZIP: {}", path.display());
+        }
+        Ok(paths)
+    }
+}
+
+fn zip_datetime(time: FileTime) -> Option<DateTime> {
+    let dt = ChronoDateTime::from_timestamp(time.unix_seconds(), 0)?.naive_utc();
+    DateTime::from_date_and_time(
+        u16::try_from(dt.year()).ok()?,
+        dt.month() as u8,
+        dt.day() as u8,
+        dt.hour() as u8,
+        dt.minute() as u8,
+        dt.second() as u8,
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn test_zip_sink_roundtrip() {
+        let mut sink = ZipSink::new(Vec::new(), None);
+        sink.start_entry("/etc/hosts", &EntryMetadata::default())
+            .unwrap();
+        sink.write_all(b"127.0.0.1 localhost").unwrap();
+        sink.finish_entry().unwrap();
+
+        let data = sink.into_inner().unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("etc/hosts")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert_eq!(content, "127.0.0.1 localhost");
+    }
+
+    #[test]
+    fn test_zip_sink_unknown_size_is_zip64() {
+        let mut sink = ZipSink::new(Vec::new(), None);
+        sink.start_entry("$MFT", &EntryMetadata::default()).unwrap();
+        sink.write_all(b"FILE0").unwrap();
+        sink.finish_entry().unwrap();
+
+        let data = sink.into_inner().unwrap();
+        // Local header "version needed to extract" is 4.5 for zip64
+        assert_eq!(u16::from_le_bytes([data[4], data[5]]), 45);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        let entry = archive.by_name("$MFT").unwrap();
+        assert_eq!(entry.size(), 5);
+    }
+
+    #[test]
+    fn test_zip_sink_encrypted() {
+        let mut sink = ZipSink::new(Vec::new(), Some("secret".to_string()));
+        assert!(sink.is_encrypted());
+        sink.add_bytes("file.txt", b"hidden").unwrap();
+
+        let data = sink.into_inner().unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        assert!(archive.by_name("file.txt").is_err());
+
+        let mut content = String::new();
+        archive
+            .by_name_decrypt("file.txt", b"secret")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "hidden");
+    }
+
+    #[test]
+    fn test_zip_datetime() {
+        let time = FileTime::from_unix_time(1_700_000_000, 0);
+        assert!(zip_datetime(time).is_some());
+        assert!(zip_datetime(FileTime::zero()).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! Machine-readable summary of a collection run, for orchestration tools.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CollectorError, EXIT_CANCELLED, EXIT_PARTIAL, EXIT_SUCCESS, Result};
+use crate::failure::FailureReport;
+use crate::platform::CollectionStats;
+use crate::sink::hash_file;
+
+/// Outcome of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// Every file was collected
+    Complete,
+    /// The run finished, but some files could not be collected
+    Partial,
+    /// The run was cancelled; the output holds the files done so far
+    Cancelled,
+    /// The run stopped on an error
+    #[default]
+    Failed,
+}
+
+/// Collection folder, archive or archive volume produced by a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    /// Size and SHA1 of files; `None` for the collection folder
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+}
+
+impl OutputFile {
+    /// Describe an output, hashing it when it is a file.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(Self {
+                path: path.to_path_buf(),
+                size: None,
+                sha1: None,
+            });
+        }
+
+        let (sha1, size) = hash_file(path).map_err(|e| CollectorError::FileRead {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: Some(size),
+            sha1: Some(sha1),
+        })
+    }
+}
+
+/// Summary of a run, filled as it goes and closed with [`RunSummary::finish`].
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub tool: String,
+    pub version: String,
+    pub status: RunStatus,
+    pub exit_code: i32,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub duration_ms: u64,
+    /// Options the run was started with, secrets left out
+    pub parameters: serde_json::Value,
+    /// Artifacts collected, with groups such as `All` expanded
+    pub resources: Vec<String>,
+    pub resource_set_sha256: Option<String>,
+    pub patterns: usize,
+    pub stats: CollectionStats,
+    pub outputs: Vec<OutputFile>,
+    pub warnings: Vec<String>,
+    pub failures: FailureReport,
+    pub cancelled: bool,
+    pub error: Option<CollectorError>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl RunSummary {
+    pub fn new(parameters: serde_json::Value) -> Self {
+        Self {
+            tool: "collector".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            status: RunStatus::default(),
+            exit_code: EXIT_SUCCESS,
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+            duration_ms: 0,
+            parameters,
+            resources: Vec::new(),
+            resource_set_sha256: None,
+            patterns: 0,
+            stats: CollectionStats::default(),
+            outputs: Vec::new(),
+            warnings: Vec::new(),
+            failures: FailureReport::new(&[]),
+            cancelled: false,
+            error: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Close the summary with the outcome of the run and return its exit code.
+    pub fn finish(&mut self, outcome: Result<()>) -> i32 {
+        self.finished_at = Some(Utc::now().to_rfc3339());
+        self.duration_ms = self.started.elapsed().as_millis() as u64;
+        (self.status, self.exit_code) = match outcome {
+            Ok(()) if self.cancelled => (RunStatus::Cancelled, EXIT_CANCELLED),
+            Ok(()) if self.failures.files_failed > 0 => (RunStatus::Partial, EXIT_PARTIAL),
+            Ok(()) => (RunStatus::Complete, EXIT_SUCCESS),
+            Err(e) => {
+                let status = match e {
+                    CollectorError::Cancelled => RunStatus::Cancelled,
+                    _ => RunStatus::Failed,
+                };
+                let exit_code = e.exit_code();
+                self.error = Some(e);
+                (status, exit_code)
+            }
+        };
+        self.exit_code
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failure::{ExtractionMethod, FileFailure};
+
+    #[test]
+    fn test_run_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("Collector.zip");
+        std::fs::write(&archive, b"abc").unwrap();
+
+        let mut summary = RunSummary::new(serde_json::json!({"source": "/"}));
+        summary.outputs.push(OutputFile::new(dir.path()).unwrap());
+        summary.outputs.push(OutputFile::new(&archive).unwrap());
+        assert_eq!(summary.finish(Ok(())), EXIT_SUCCESS);
+        assert_eq!(summary.status, RunStatus::Complete);
+
+        let failure = FileFailure::new(
+            "/var/log/a".to_string(),
+            None,
+            &CollectorError::PathNotFound(PathBuf::from("/var/log/a")),
+            vec![ExtractionMethod::Filesystem],
+        );
+        summary.failures = FailureReport::new(&[failure]);
+        assert_eq!(summary.finish(Ok(())), EXIT_PARTIAL);
+
+        let json: serde_json::Value = serde_json::from_slice(&summary.to_json()).unwrap();
+        assert_eq!(json["status"], "partial");
+        assert_eq!(json["exit_code"], 3);
+        assert_eq!(json["parameters"]["source"], "/");
+        assert_eq!(json["outputs"][0]["sha1"], serde_json::Value::Null);
+        assert_eq!(
+            json["outputs"][1]["sha1"],
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(json["failures"]["files_failed"], 1);
+
+        summary.cancelled = true;
+        assert_eq!(summary.finish(Ok(())), EXIT_CANCELLED);
+        assert_eq!(summary.status, RunStatus::Cancelled);
+
+        summary.cancelled = false;
+        assert_eq!(
+            summary.finish(Err(CollectorError::Cancelled)),
+            EXIT_CANCELLED
+        );
+        assert_eq!(summary.status, RunStatus::Cancelled);
+
+        assert_eq!(summary.finish(Err(CollectorError::NoFilesMatched)), 11);
+        let json: serde_json::Value = serde_json::from_slice(&summary.to_json()).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["error"]["code"], 1102);
+    }
+}
@@ -0,0 +1,464 @@
+//! Rate limits and process priority, to keep collections on busy hosts from
+//! hurting the services they run.
+
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Idle time never builds up more than this much credit.
+const MAX_BURST: Duration = Duration::from_secs(1);
+/// Smallest read or write of a throttled stream.
+const MIN_CHUNK: usize = 4096;
+
+/// How fast a collection may read, and at which priority it runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThrottleOptions {
+    /// Bytes read per second, copies and hashing together
+    pub bytes_per_sec: Option<u64>,
+    /// Files started per second
+    pub files_per_sec: Option<f64>,
+    /// Lowest best-effort I/O priority (Linux ioprio, Windows background mode)
+    pub low_io_priority: bool,
+    /// CPU niceness, from -20 (highest) to 19 (lowest)
+    pub nice: Option<i32>,
+}
+
+impl ThrottleOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_bytes_per_sec(mut self, rate: Option<u64>) -> Self {
+        self.bytes_per_sec = rate.filter(|rate| *rate > 0);
+        self
+    }
+
+    pub fn with_files_per_sec(mut self, rate: Option<f64>) -> Self {
+        self.files_per_sec = rate.filter(|rate| *rate > 0.0);
+        self
+    }
+
+    pub fn with_low_io_priority(mut self, enabled: bool) -> Self {
+        self.low_io_priority = enabled;
+        self
+    }
+
+    pub fn with_nice(mut self, nice: Option<i32>) -> Self {
+        self.nice = nice.map(|nice| nice.clamp(-20, 19));
+        self
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.bytes_per_sec.is_some() || self.files_per_sec.is_some()
+    }
+
+    fn changes_priority(&self) -> bool {
+        self.low_io_priority || self.nice.is_some()
+    }
+}
+
+/// Spaces out amounts so they average `rate` per second.
+#[derive(Debug)]
+struct Pacer {
+    rate: f64,
+    /// When everything consumed so far is paid for
+    next: Instant,
+}
+
+impl Pacer {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            next: Instant::now(),
+        }
+    }
+
+    /// Time to wait for what was consumed before, then book `amount`
+    fn delay(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        if let Some(earliest) = now.checked_sub(MAX_BURST)
+            && self.next < earliest
+        {
+            self.next = earliest;
+        }
+        let delay = self.next.saturating_duration_since(now);
+        self.next += Duration::from_secs_f64(amount / self.rate);
+        delay
+    }
+}
+
+/// Rate limits shared by every read of one collection.
+#[derive(Debug, Default)]
+pub(crate) struct Throttle {
+    options: ThrottleOptions,
+    bytes: Option<Mutex<Pacer>>,
+    files: Option<Mutex<Pacer>>,
+    /// Whether failing to change the priority was logged already
+    priority_warned: AtomicBool,
+}
+
+impl Throttle {
+    pub(crate) fn new(options: ThrottleOptions) -> Self {
+        Self {
+            bytes: options
+                .bytes_per_sec
+                .map(|rate| Mutex::new(Pacer::new(rate as f64))),
+            files: options
+                .files_per_sec
+                .map(|rate| Mutex::new(Pacer::new(rate))),
+            options,
+            priority_warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Apply the I/O priority and niceness to the current thread, a blocking
+    /// pool thread reading the source, until the guard is dropped
+    pub(crate) fn lower_priority(&self) -> PriorityGuard {
+        if !self.options.changes_priority() {
+            return PriorityGuard(None);
+        }
+        let mut saved = SavedPriority::default();
+        if let Err(e) = set_priority(self.options.low_io_priority, self.options.nice, &mut saved)
+            && !self.priority_warned.swap(true, Ordering::Relaxed)
+        {
+            log::warn!("Failed to lower the collection priority: {}", e);
+        }
+        PriorityGuard(Some(saved))
+    }
+
+    /// Largest read or write: about a tenth of a second of the byte rate,
+    /// so pauses stay short enough to cancel
+    fn chunk(&self, len: usize) -> usize {
+        match self.options.bytes_per_sec {
+            Some(rate) => len.min((rate as usize / 10).max(MIN_CHUNK)),
+            None => len,
+        }
+    }
+
+    /// Block until `bytes` more may be read
+    fn wait_bytes(&self, bytes: usize) {
+        let Some(ref pacer) = self.bytes else {
+            return;
+        };
+        let delay = pacer
+            .lock()
+            .map(|mut p| p.delay(bytes as f64))
+            .unwrap_or_default();
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Wait until the next file may be started
+    pub(crate) async fn wait_file(&self) {
+        let Some(ref pacer) = self.files else {
+            return;
+        };
+        let delay = pacer.lock().map(|mut p| p.delay(1.0)).unwrap_or_default();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Priority of the current thread, restored when dropped.
+pub(crate) struct PriorityGuard(Option<SavedPriority>);
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        if let Some(saved) = self.0.take()
+            && let Err(e) = restore_priority(saved)
+        {
+            log::warn!("Failed to restore the thread priority: {}", e);
+        }
+    }
+}
+
+/// Reader or writer held to the byte rate of a [`Throttle`]. Pauses block the
+/// thread, so it runs on the blocking pool, never on a runtime worker.
+pub(crate) struct Throttled<'t, T> {
+    inner: T,
+    throttle: &'t Throttle,
+}
+
+impl<'t, T> Throttled<'t, T> {
+    pub(crate) fn new(inner: T, throttle: &'t Throttle) -> Self {
+        Self { inner, throttle }
+    }
+}
+
+impl<T: Read> Read for Throttled<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.throttle.chunk(buf.len());
+        let read = self.inner.read(&mut buf[..len])?;
+        self.throttle.wait_bytes(read);
+        Ok(read)
+    }
+}
+
+impl<T: Write> Write for Throttled<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.throttle.chunk(buf.len());
+        let written = self.inner.write(&buf[..len])?;
+        self.throttle.wait_bytes(written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Priorities of a thread before [`set_priority`] changed them.
+#[derive(Debug, Default)]
+struct SavedPriority {
+    nice: Option<i32>,
+    io: Option<i32>,
+}
+
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: nix::libc::c_int = 1;
+
+#[cfg(unix)]
+fn current_nice() -> io::Result<i32> {
+    use nix::errno::Errno;
+    use nix::libc;
+
+    // -1 is a valid niceness too, told apart by errno
+    Errno::clear();
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    if nice == -1 && Errno::last_raw() != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(nice)
+}
+
+#[cfg(unix)]
+fn set_nice(nice: i32) -> io::Result<()> {
+    use nix::libc;
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Both priorities are per thread on Linux, the calling one being `0`.
+#[cfg(target_os = "linux")]
+fn set_priority(low_io: bool, nice: Option<i32>, saved: &mut SavedPriority) -> io::Result<()> {
+    use nix::libc;
+
+    const IOPRIO_CLASS_BE: libc::c_int = 2;
+    const IOPRIO_LOWEST: libc::c_int = (IOPRIO_CLASS_BE << 13) | 7;
+
+    if let Some(nice) = nice {
+        let current = current_nice()?;
+        set_nice(nice)?;
+        saved.nice = Some(current);
+    }
+    if low_io {
+        let current = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0) };
+        if current < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        set_io_priority(IOPRIO_LOWEST)?;
+        saved.io = Some(current as libc::c_int);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_io_priority(priority: nix::libc::c_int) -> io::Result<()> {
+    use nix::libc;
+
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Raising the priority back needs the privileges a collection runs with.
+#[cfg(target_os = "linux")]
+fn restore_priority(saved: SavedPriority) -> io::Result<()> {
+    if let Some(io) = saved.io {
+        set_io_priority(io)?;
+    }
+    if let Some(nice) = saved.nice {
+        set_nice(nice)?;
+    }
+    Ok(())
+}
+
+/// Niceness is per process here: the collection runs one copy at a time, so
+/// it is lowered for the copy only.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_priority(low_io: bool, nice: Option<i32>, saved: &mut SavedPriority) -> io::Result<()> {
+    if low_io {
+        log::warn!("Low I/O priority is only supported on Linux and Windows");
+    }
+    if let Some(nice) = nice {
+        let current = current_nice()?;
+        set_nice(nice)?;
+        saved.nice = Some(current);
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn restore_priority(saved: SavedPriority) -> io::Result<()> {
+    match saved.nice {
+        Some(nice) => set_nice(nice),
+        None => Ok(()),
+    }
+}
+
+/// Thread background mode lowers the I/O, memory and CPU priorities together.
+#[cfg(target_os = "windows")]
+fn set_priority(low_io: bool, nice: Option<i32>, saved: &mut SavedPriority) -> io::Result<()> {
+    use winapi::um::processthreadsapi::{GetCurrentThread, GetThreadPriority, SetThreadPriority};
+    use winapi::um::winbase::{
+        THREAD_MODE_BACKGROUND_BEGIN, THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_BELOW_NORMAL,
+        THREAD_PRIORITY_ERROR_RETURN, THREAD_PRIORITY_IDLE, THREAD_PRIORITY_NORMAL,
+    };
+
+    let thread = unsafe { GetCurrentThread() };
+    if low_io {
+        if unsafe { SetThreadPriority(thread, THREAD_MODE_BACKGROUND_BEGIN as i32) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        saved.io = Some(THREAD_MODE_BACKGROUND_BEGIN as i32);
+    } else if let Some(nice) = nice {
+        let current = unsafe { GetThreadPriority(thread) };
+        if current == THREAD_PRIORITY_ERROR_RETURN as i32 {
+            return Err(io::Error::last_os_error());
+        }
+        let priority = match nice {
+            10.. => THREAD_PRIORITY_IDLE,
+            1..=9 => THREAD_PRIORITY_BELOW_NORMAL,
+            0 => THREAD_PRIORITY_NORMAL,
+            _ => THREAD_PRIORITY_ABOVE_NORMAL,
+        };
+        if unsafe { SetThreadPriority(thread, priority as i32) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        saved.nice = Some(current);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn restore_priority(saved: SavedPriority) -> io::Result<()> {
+    use winapi::um::processthreadsapi::{GetCurrentThread, SetThreadPriority};
+    use winapi::um::winbase::THREAD_MODE_BACKGROUND_END;
+
+    let thread = unsafe { GetCurrentThread() };
+    let priority = match (saved.io, saved.nice) {
+        (Some(_), _) => THREAD_MODE_BACKGROUND_END as i32,
+        (None, Some(nice)) => nice,
+        (None, None) => return Ok(()),
+    };
+    if unsafe { SetThreadPriority(thread, priority) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn set_priority(_low_io: bool, _nice: Option<i32>, _saved: &mut SavedPriority) -> io::Result<()> {
+    log::warn!("Process priority is not supported on this platform");
+    Ok(())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn restore_priority(_saved: SavedPriority) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer_delay() {
+        let mut pacer = Pacer::new(1000.0);
+        assert_eq!(pacer.delay(100.0), Duration::ZERO);
+        let delay = pacer.delay(1000.0);
+        assert!(delay > Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        let delay = pacer.delay(1.0);
+        assert!(delay > Duration::from_millis(1000) && delay <= Duration::from_millis(1100));
+    }
+
+    #[test]
+    fn test_throttled_copy() {
+        let throttle = Throttle::new(ThrottleOptions::new().with_bytes_per_sec(Some(200_000)));
+        let data = vec![7u8; 60_000];
+        let mut output = Vec::new();
+
+        let timer = Instant::now();
+        let mut reader = Throttled::new(data.as_slice(), &throttle);
+        io::copy(&mut reader, &mut output).unwrap();
+        assert_eq!(output, data);
+        // Three chunks of 20 KB at 200 KB/s
+        assert!(timer.elapsed() >= Duration::from_millis(150));
+    }
+
+    /// Writer taking at most 100 bytes at a time
+    struct ShortWriter;
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len().min(100))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_throttled_write_books_bytes_written() {
+        let throttle = Throttle::new(ThrottleOptions::new().with_bytes_per_sec(Some(1000)));
+        let mut writer = Throttled::new(ShortWriter, &throttle);
+
+        // Booking the 4 KB chunk offered instead would pause for 4 seconds
+        let timer = Instant::now();
+        assert_eq!(writer.write(&[7u8; 8192]).unwrap(), 100);
+        assert_eq!(writer.write(&[7u8; 8192]).unwrap(), 100);
+        let elapsed = timer.elapsed();
+        assert!(elapsed >= Duration::from_millis(90) && elapsed < Duration::from_secs(1));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_priority_lowered_for_current_thread() {
+        let before = current_nice().unwrap();
+        let throttle = Throttle::new(ThrottleOptions::new().with_nice(Some(before + 5)));
+
+        std::thread::spawn(move || {
+            let guard = throttle.lower_priority();
+            assert_eq!(current_nice().unwrap(), before + 5);
+            drop(guard);
+            // Raising it back needs privileges
+            if nix::unistd::geteuid().is_root() {
+                assert_eq!(current_nice().unwrap(), before);
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(current_nice().unwrap(), before);
+    }
+
+    #[test]
+    fn test_throttle_options() {
+        let options = ThrottleOptions::new()
+            .with_bytes_per_sec(Some(0))
+            .with_files_per_sec(Some(2.5))
+            .with_nice(Some(40));
+        assert_eq!(options.bytes_per_sec, None);
+        assert_eq!(options.files_per_sec, Some(2.5));
+        assert_eq!(options.nice, Some(19));
+        assert!(options.is_limited());
+        assert!(!ThrottleOptions::new().is_limited());
+    }
+}
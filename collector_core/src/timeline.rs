@@ -0,0 +1,258 @@
+//! Timeline exports of the source metadata of collected files: a TSK 3.x
+//! bodyfile for `mactime` and a JSONL file for Timesketch.
+
+use std::io::{self, Write};
+
+use chrono::DateTime;
+use filetime::FileTime;
+use serde::Serialize;
+
+use crate::csv::CsvLogItem;
+use crate::dfxml::ByteRun;
+use crate::sink::EntryMetadata;
+
+/// Source metadata of one manifest row.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub source_file: String,
+    pub destination_file: String,
+    pub hash_sha1: String,
+    pub inode: String,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub size: u64,
+    pub accessed: Option<FileTime>,
+    pub modified: Option<FileTime>,
+    pub changed: Option<FileTime>,
+    pub created: Option<FileTime>,
+    /// Location on the volume, for files read through the raw NTFS parser
+    pub byte_runs: Vec<ByteRun>,
+}
+
+impl TimelineEntry {
+    /// Entry of a row, with the metadata read before the file was opened.
+    pub fn new(item: &CsvLogItem, metadata: &EntryMetadata) -> Self {
+        Self {
+            source_file: item.source_file.clone(),
+            destination_file: copy_of(item),
+            hash_sha1: item.hash_sha1.clone(),
+            inode: item.inode.clone(),
+            mode: metadata.mode,
+            uid: metadata.uid,
+            gid: metadata.gid,
+            size: item.file_size,
+            accessed: metadata.accessed,
+            modified: metadata.modified,
+            changed: metadata.changed,
+            created: metadata.created,
+            byte_runs: Vec::new(),
+        }
+    }
+
+    /// Entry rebuilt from a manifest row alone (resumed collections), with
+    /// only the modification and access times.
+    pub fn from_item(item: &CsvLogItem) -> Self {
+        Self {
+            source_file: item.source_file.clone(),
+            destination_file: copy_of(item),
+            hash_sha1: item.hash_sha1.clone(),
+            inode: item.inode.clone(),
+            size: item.file_size,
+            accessed: parse_filetime(&item.access_time),
+            modified: parse_filetime(&item.modified_time),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_byte_runs(mut self, byte_runs: Vec<ByteRun>) -> Self {
+        self.byte_runs = byte_runs;
+        self
+    }
+
+    /// `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime`. The MD5
+    /// field is `0` since the manifest records SHA1 only.
+    pub fn bodyfile_line(&self) -> String {
+        let time = |t: Option<FileTime>| t.map_or(0, |t| t.unix_seconds());
+        format!(
+            "0|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            escape_name(&self.source_file),
+            if self.inode.is_empty() {
+                "0"
+            } else {
+                &self.inode
+            },
+            mode_string(self.mode),
+            self.uid.unwrap_or(0),
+            self.gid.unwrap_or(0),
+            self.size,
+            time(self.accessed),
+            time(self.modified),
+            time(self.changed),
+            time(self.created),
+        )
+    }
+
+    /// One Timesketch event per known timestamp.
+    pub fn events(&self) -> Vec<TimesketchEvent<'_>> {
+        [
+            (self.modified, "Content Modification Time"),
+            (self.accessed, "Last Access Time"),
+            (self.changed, "Metadata Modification Time"),
+            (self.created, "Creation Time"),
+        ]
+        .into_iter()
+        .filter_map(|(time, desc)| {
+            let time = time?;
+            let datetime = DateTime::from_timestamp(time.unix_seconds(), time.nanoseconds())?;
+            Some(TimesketchEvent {
+                message: format!("{} {}", desc, self.source_file),
+                datetime: datetime.to_rfc3339(),
+                timestamp: datetime.timestamp_micros(),
+                timestamp_desc: desc,
+                source_file: &self.source_file,
+                destination_file: &self.destination_file,
+                hash_sha1: &self.hash_sha1,
+                inode: &self.inode,
+                file_size: self.size,
+                data_type: "fs:stat",
+            })
+        })
+        .collect()
+    }
+}
+
+/// Destination of a row, empty unless the copy is complete
+fn copy_of(item: &CsvLogItem) -> String {
+    if item.is_copied() {
+        item.destination_file.clone()
+    } else {
+        String::new()
+    }
+}
+
+/// An event of the Timesketch JSONL import format.
+#[derive(Debug, Serialize)]
+pub struct TimesketchEvent<'a> {
+    pub message: String,
+    pub datetime: String,
+    /// Microseconds since the epoch
+    pub timestamp: i64,
+    pub timestamp_desc: &'static str,
+    pub source_file: &'a str,
+    pub destination_file: &'a str,
+    pub hash_sha1: &'a str,
+    pub inode: &'a str,
+    pub file_size: u64,
+    pub data_type: &'static str,
+}
+
+/// Append the bodyfile line of an entry.
+pub fn write_bodyfile_line<W: Write>(out: &mut W, entry: &TimelineEntry) -> io::Result<()> {
+    writeln!(out, "{}", entry.bodyfile_line())
+}
+
+/// Append the Timesketch JSONL events of an entry.
+pub fn write_timesketch_events<W: Write>(out: &mut W, entry: &TimelineEntry) -> io::Result<()> {
+    for event in entry.events() {
+        serde_json::to_writer(&mut *out, &event)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Parse a manifest time (`FileTime` display, `<seconds>.<nanos>s`).
+fn parse_filetime(value: &str) -> Option<FileTime> {
+    let (seconds, nanos) = value.strip_suffix('s')?.split_once('.')?;
+    let seconds: i64 = seconds.parse().ok()?;
+    let nanos: u32 = nanos.parse().ok()?;
+
+    // Windows file times count from 1601
+    #[cfg(windows)]
+    let seconds = seconds - 11_644_473_600;
+
+    Some(FileTime::from_unix_time(seconds, nanos))
+}
+
+/// `|` separates fields, so it is written as `\x7c` in names, like newlines.
+fn escape_name(name: &str) -> String {
+    name.replace('|', "\\x7c").replace('\n', "\\x0a")
+}
+
+/// TSK mode string, e.g. `r/rrw-r--r--`. Files without Unix mode (Windows)
+/// are shown as regular files with all permissions.
+fn mode_string(mode: Option<u32>) -> String {
+    let Some(mode) = mode else {
+        return "r/rrwxrwxrwx".to_string();
+    };
+
+    let kind = match mode & 0o170000 {
+        0o100000 => 'r',
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut text = format!("{}/{}", kind, kind);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bodyfile_line() {
+        let item = CsvLogItem::with_paths("/var/log/a|b.log", "var/log/a|b.log")
+            .with_hash("ab".repeat(20))
+            .with_size(42)
+            .with_inode(Some(1234));
+        let metadata = EntryMetadata {
+            mode: Some(0o100640),
+            uid: Some(0),
+            gid: Some(4),
+            accessed: Some(FileTime::from_unix_time(1_700_000_300, 0)),
+            modified: Some(FileTime::from_unix_time(1_700_000_200, 5)),
+            changed: Some(FileTime::from_unix_time(1_700_000_100, 0)),
+            ..Default::default()
+        };
+
+        let entry = TimelineEntry::new(&item, &metadata);
+        assert_eq!(
+            entry.bodyfile_line(),
+            "0|/var/log/a\\x7cb.log|1234|r/rrw-r-----|0|4|42|1700000300|1700000200|1700000100|0"
+        );
+
+        let events = entry.events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].timestamp_desc, "Content Modification Time");
+        assert_eq!(events[0].timestamp, 1_700_000_200_000_000);
+        assert_eq!(events[0].datetime, "2023-11-14T22:16:40.000000005+00:00");
+    }
+
+    #[test]
+    fn test_entry_from_manifest_row() {
+        let mut item = CsvLogItem::with_paths("/etc/hosts", "etc/hosts");
+        item.modified_time = FileTime::from_unix_time(1_600_000_000, 250).to_string();
+        item.access_time = "not a time".to_string();
+
+        let entry = TimelineEntry::from_item(&item);
+        assert_eq!(
+            entry.modified.map(|t| t.unix_seconds()),
+            Some(1_600_000_000)
+        );
+        assert_eq!(entry.accessed, None);
+        let mut events = Vec::new();
+        write_timesketch_events(&mut events, &entry).unwrap();
+        assert_eq!(events.split(|&b| b == b'\n').count(), 2);
+    }
+}
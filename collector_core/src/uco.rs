@@ -0,0 +1,408 @@
+//! CASE/UCO JSON-LD provenance of a collection: the investigative action,
+//! the tool, the examiner, the source host, and every source file with the
+//! copy made of it, built from the case metadata and the manifest rows.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::case::CaseMetadata;
+use crate::dfxml::iso_time;
+use crate::timeline::TimelineEntry;
+
+/// Identifiers of the nodes the rows refer to, chosen before the first row
+/// is written.
+pub struct UcoIds {
+    tool: String,
+    host: String,
+}
+
+impl UcoIds {
+    pub fn new() -> Self {
+        Self {
+            tool: node_id("tool"),
+            host: node_id("device"),
+        }
+    }
+}
+
+impl Default for UcoIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// JSON-LD document of entries held in memory.
+pub fn document(case: &CaseMetadata, entries: &[TimelineEntry]) -> Vec<u8> {
+    let ids = UcoIds::new();
+    let mut rows = Vec::new();
+    for entry in entries {
+        write_entry(&mut rows, &ids, entry).expect("writing to a Vec cannot fail");
+    }
+    let mut out = Vec::new();
+    write_document(&mut out, case, &ids, || Ok(rows.as_slice()))
+        .expect("writing to a Vec cannot fail");
+    out
+}
+
+/// Append the nodes of an entry as one JSON array line: the source file and
+/// its relationship to the host, then for a copied file the copy and its
+/// relationship to the source.
+pub fn write_entry<W: Write>(out: &mut W, ids: &UcoIds, entry: &TimelineEntry) -> io::Result<()> {
+    let source_id = node_id("file");
+    let mut nodes = vec![
+        file_object(&source_id, entry, &entry.source_file, true),
+        relationship(&source_id, &ids.host, "Contained_Within"),
+    ];
+    if !entry.destination_file.is_empty() {
+        let copy_id = node_id("file");
+        nodes.push(file_object(&copy_id, entry, &entry.destination_file, false));
+        nodes.push(relationship(&copy_id, &source_id, "Copied_From"));
+    }
+    serde_json::to_writer(&mut *out, &nodes)?;
+    out.write_all(b"\n")
+}
+
+/// Write the document around the entry lines of [`write_entry`]. `rows`
+/// reopens those lines for each pass over them, so they are never all held
+/// in memory.
+pub fn write_document<W, R>(
+    out: &mut W,
+    case: &CaseMetadata,
+    ids: &UcoIds,
+    rows: impl Fn() -> io::Result<R>,
+) -> io::Result<()>
+where
+    W: Write,
+    R: BufRead,
+{
+    let mut head = vec![
+        json!({
+            "@id": ids.tool,
+            "@type": "uco-tool:Tool",
+            "uco-core:name": case.tool.name,
+            "uco-tool:version": case.tool.version,
+            "uco-tool:toolType": "Collection",
+        }),
+        json!({
+            "@id": ids.host,
+            "@type": "uco-observable:Device",
+            "uco-core:hasFacet": [{
+                "@id": node_id("computer-specification-facet"),
+                "@type": "uco-observable:ComputerSpecificationFacet",
+                "uco-observable:hostname": case.host.hostname,
+                "uco-observable:cpuFamily": case.host.cpu_arch,
+            }],
+            "uco-core:description": case.host.os_version,
+        }),
+    ];
+    let performer_id = case.case.examiner.as_ref().map(|examiner| {
+        let id = node_id("identity");
+        head.push(json!({
+            "@id": id,
+            "@type": "uco-identity:Identity",
+            "uco-core:name": examiner,
+        }));
+        id
+    });
+
+    let mut record = json!({
+        "@id": node_id("provenance-record"),
+        "@type": "case-investigation:ProvenanceRecord",
+    });
+    if let Some(ref case_number) = case.case.case_number {
+        record["case-investigation:exhibitNumber"] = json!(case_number);
+    }
+    let mut action = json!({
+        "@id": node_id("investigative-action"),
+        "@type": "case-investigation:InvestigativeAction",
+        "uco-core:name": "collected",
+        "uco-action:startTime": datetime(&case.started_at),
+        "uco-action:instrument": { "@id": ids.tool },
+        "uco-action:environment": { "@id": ids.host },
+    });
+    if let Some(finished_at) = &case.finished_at {
+        action["uco-action:endTime"] = datetime(finished_at);
+    }
+    if let Some(ref id) = performer_id {
+        action["uco-action:performer"] = json!({ "@id": id });
+    }
+    let mut investigation = json!({
+        "@id": node_id("investigation"),
+        "@type": "case-investigation:Investigation",
+        "uco-core:name": case.case.case_number.as_deref().unwrap_or("collection"),
+    });
+    if let Some(ref notes) = case.case.notes {
+        investigation["uco-core:description"] = json!(notes);
+    }
+
+    out.write_all(b"{\n\"@context\": ")?;
+    serde_json::to_writer(&mut *out, &context())?;
+    out.write_all(b",\n\"@graph\": [\n")?;
+    for (i, node) in head.iter().enumerate() {
+        if i > 0 {
+            out.write_all(b",\n")?;
+        }
+        serde_json::to_writer(&mut *out, node)?;
+    }
+    // Entry lines are arrays of nodes: their content goes into the graph as is
+    for line in rows()?.lines() {
+        let line = line?;
+        let nodes = line.trim().trim_start_matches('[').trim_end_matches(']');
+        if !nodes.is_empty() {
+            out.write_all(b",\n")?;
+            out.write_all(nodes.as_bytes())?;
+        }
+    }
+
+    out.write_all(b",\n")?;
+    write_node(out, &record, &[("uco-core:object", None, copy)], &rows)?;
+    out.write_all(b",\n")?;
+    write_node(
+        out,
+        &action,
+        &[
+            ("uco-action:object", Some(ids.host.as_str()), source),
+            ("uco-action:result", None, copy),
+        ],
+        &rows,
+    )?;
+    out.write_all(b",\n")?;
+
+    // Every other node of the graph
+    let mut others: Vec<&str> = head
+        .iter()
+        .filter_map(|node| node["@id"].as_str())
+        .collect();
+    others.extend(record["@id"].as_str());
+    others.extend(action["@id"].as_str());
+    let mut text = serde_json::to_vec(&investigation)?;
+    text.pop();
+    out.write_all(&text)?;
+    out.write_all(b",\"uco-core:object\":[")?;
+    let mut first = true;
+    for id in others {
+        write_reference(out, id, &mut first)?;
+    }
+    for line in rows()?.lines() {
+        let nodes: Vec<Value> = serde_json::from_str(&line?)?;
+        for node in &nodes {
+            if let Some(id) = node["@id"].as_str() {
+                write_reference(out, id, &mut first)?;
+            }
+        }
+    }
+    out.write_all(b"]}\n]\n}\n")
+}
+
+/// Picks a node of an entry line: its source file or its copy.
+type Pick = fn(&[Value]) -> Option<&str>;
+
+fn source(nodes: &[Value]) -> Option<&str> {
+    nodes.first()?["@id"].as_str()
+}
+
+fn copy(nodes: &[Value]) -> Option<&str> {
+    nodes.get(2)?["@id"].as_str()
+}
+
+/// Write a node with lists of references: an optional first one, then the
+/// node picked from every entry line.
+fn write_node<W, R>(
+    out: &mut W,
+    node: &Value,
+    lists: &[(&str, Option<&str>, Pick)],
+    rows: &impl Fn() -> io::Result<R>,
+) -> io::Result<()>
+where
+    W: Write,
+    R: BufRead,
+{
+    // The node without its closing brace, so the lists can follow
+    let mut text = serde_json::to_vec(node)?;
+    text.pop();
+    out.write_all(&text)?;
+    for (property, lead, pick) in lists {
+        write!(out, ",\"{}\":[", property)?;
+        let mut first = true;
+        if let Some(id) = lead {
+            write_reference(out, id, &mut first)?;
+        }
+        for line in rows()?.lines() {
+            let nodes: Vec<Value> = serde_json::from_str(&line?)?;
+            if let Some(id) = pick(&nodes) {
+                write_reference(out, id, &mut first)?;
+            }
+        }
+        out.write_all(b"]")?;
+    }
+    out.write_all(b"}")
+}
+
+fn write_reference<W: Write>(out: &mut W, id: &str, first: &mut bool) -> io::Result<()> {
+    if !std::mem::take(first) {
+        out.write_all(b",")?;
+    }
+    serde_json::to_writer(&mut *out, &json!({ "@id": id }))?;
+    Ok(())
+}
+
+fn context() -> Value {
+    json!({
+        "kb": "http://example.org/kb/",
+        "case-investigation": "https://ontology.caseontology.org/case/investigation/",
+        "uco-action": "https://ontology.unifiedcyberontology.org/uco/action/",
+        "uco-core": "https://ontology.unifiedcyberontology.org/uco/core/",
+        "uco-identity": "https://ontology.unifiedcyberontology.org/uco/identity/",
+        "uco-observable": "https://ontology.unifiedcyberontology.org/uco/observable/",
+        "uco-tool": "https://ontology.unifiedcyberontology.org/uco/tool/",
+        "uco-types": "https://ontology.unifiedcyberontology.org/uco/types/",
+        "uco-vocabulary": "https://ontology.unifiedcyberontology.org/uco/vocabulary/",
+        "xsd": "http://www.w3.org/2001/XMLSchema#",
+    })
+}
+
+/// File observable with its path, size and SHA1. Source files also carry
+/// their timestamps.
+fn file_object(id: &str, entry: &TimelineEntry, path: &str, source: bool) -> Value {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let mut file_facet = json!({
+        "@id": node_id("file-facet"),
+        "@type": "uco-observable:FileFacet",
+        "uco-observable:fileName": name,
+        "uco-observable:filePath": path,
+        "uco-observable:isDirectory": false,
+        "uco-observable:sizeInBytes": entry.size,
+    });
+    if source {
+        for (property, time) in [
+            ("uco-observable:modifiedTime", entry.modified),
+            ("uco-observable:accessedTime", entry.accessed),
+            ("uco-observable:metadataChangeTime", entry.changed),
+            ("uco-observable:observableCreatedTime", entry.created),
+        ] {
+            if let Some(time) = time.and_then(iso_time) {
+                file_facet[property] = datetime(&time);
+            }
+        }
+    }
+
+    let mut facets = vec![file_facet];
+    if !entry.hash_sha1.is_empty() {
+        facets.push(json!({
+            "@id": node_id("content-data-facet"),
+            "@type": "uco-observable:ContentDataFacet",
+            "uco-observable:hash": [{
+                "@id": node_id("hash"),
+                "@type": "uco-types:Hash",
+                "uco-types:hashMethod": {
+                    "@type": "uco-vocabulary:HashNameVocab",
+                    "@value": "SHA1",
+                },
+                "uco-types:hashValue": {
+                    "@type": "xsd:hexBinary",
+                    "@value": entry.hash_sha1,
+                },
+            }],
+        }));
+    }
+
+    json!({
+        "@id": id,
+        "@type": "uco-observable:File",
+        "uco-core:hasFacet": facets,
+    })
+}
+
+fn relationship(source: &str, target: &str, kind: &str) -> Value {
+    json!({
+        "@id": node_id("relationship"),
+        "@type": "uco-core:Relationship",
+        "uco-core:source": { "@id": source },
+        "uco-core:target": { "@id": target },
+        "uco-core:kindOfRelationship": kind,
+        "uco-core:isDirectional": true,
+    })
+}
+
+fn node_id(kind: &str) -> String {
+    format!("kb:{}-{}", kind, Uuid::new_v4())
+}
+
+fn datetime(value: &str) -> Value {
+    json!({ "@type": "xsd:dateTime", "@value": value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::case::CaseInfo;
+    use filetime::FileTime;
+
+    #[test]
+    fn test_case_uco_document() {
+        let case = CaseMetadata::new(
+            CaseInfo::new()
+                .with_case_number(Some("2024-001".to_string()))
+                .with_examiner(Some("J. Doe".to_string())),
+        );
+        let copied = TimelineEntry {
+            source_file: "/etc/hosts".to_string(),
+            destination_file: "etc/hosts".to_string(),
+            hash_sha1: "ab".repeat(20),
+            size: 12,
+            modified: Some(FileTime::from_unix_time(1_700_000_000, 0)),
+            ..Default::default()
+        };
+        let listed = TimelineEntry {
+            source_file: "/etc/passwd".to_string(),
+            size: 40,
+            ..Default::default()
+        };
+
+        let bundle: Value = serde_json::from_slice(&document(&case, &[copied, listed])).unwrap();
+        let graph = bundle["@graph"].as_array().unwrap();
+        let of_type = |kind: &str| -> Vec<&Value> {
+            graph.iter().filter(|node| node["@type"] == kind).collect()
+        };
+
+        let investigation = of_type("case-investigation:Investigation");
+        assert_eq!(investigation[0]["uco-core:name"], "2024-001");
+        assert_eq!(
+            investigation[0]["uco-core:object"]
+                .as_array()
+                .unwrap()
+                .len(),
+            graph.len() - 1
+        );
+        assert_eq!(
+            of_type("uco-identity:Identity")[0]["uco-core:name"],
+            "J. Doe"
+        );
+
+        let files = of_type("uco-observable:File");
+        assert_eq!(files.len(), 3);
+        let facet = &files[0]["uco-core:hasFacet"][0];
+        assert_eq!(facet["uco-observable:fileName"], "hosts");
+        assert_eq!(
+            facet["uco-observable:modifiedTime"]["@value"],
+            "2023-11-14T22:13:20+00:00"
+        );
+        let hash = &files[1]["uco-core:hasFacet"][1]["uco-observable:hash"][0];
+        assert_eq!(hash["uco-types:hashValue"]["@value"], "ab".repeat(20));
+
+        let action = &of_type("case-investigation:InvestigativeAction")[0];
+        assert_eq!(action["uco-action:object"].as_array().unwrap().len(), 3);
+        assert_eq!(action["uco-action:result"][0]["@id"], files[1]["@id"]);
+        assert!(action["uco-action:performer"]["@id"].is_string());
+
+        let relationships = of_type("uco-core:Relationship");
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(
+            relationships[1]["uco-core:kindOfRelationship"],
+            "Copied_From"
+        );
+        assert_eq!(relationships[1]["uco-core:target"]["@id"], files[0]["@id"]);
+    }
+}
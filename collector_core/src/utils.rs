@@ -128,7 +128,6 @@ pub fn is_admin() -> bool {
     }
 }
 
-
 #[cfg(target_os = "linux")]
 pub fn is_admin() -> bool {
     use nix::unistd::Uid;
@@ -154,6 +153,33 @@ pub fn normalize_path(path: &str) -> String {
         .to_string()
 }
 
+/// Parse a byte size such as `4096`, `700M` or `4G` (binary units, `B` and
+/// `iB` suffixes accepted).
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let upper = text.to_ascii_uppercase();
+    let digits_end = upper
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(digits_end);
+
+    let multiplier: u64 = match unit.trim().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(CollectorError::Config(format!("Invalid size: '{}'", text))),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|n| *n > 0)
+        .ok_or_else(|| CollectorError::Config(format!("Invalid size: '{}'", text)))
+}
+
 pub fn truncate_text(text: &str, max_len: usize) -> String {
     if text.len() > max_len {
         format!("{}...", &text[..max_len])
@@ -214,6 +240,17 @@ mod tests {
         assert_eq!(normalize_path("/var/log"), "var/log");
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("700M").unwrap(), 700 * 1024 * 1024);
+        assert_eq!(parse_size("4GiB").unwrap(), 4 << 30);
+        assert_eq!(parse_size("2 kb").unwrap(), 2048);
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+
     #[test]
     fn test_truncate_text() {
         assert_eq!(truncate_text("Hello World", 5), "Hello...");
@@ -0,0 +1,522 @@
+//! Re-check a collection after transfer: recompute the hash of every file in
+//! an output folder or archive and compare it with `Collector_copy.csv`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::VerifyingKey;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::csv::read_manifest_items;
+use crate::csv::seal::{ManifestSeal, ManifestVerification, verify_manifest};
+use crate::error::{CollectorError, Result};
+use crate::platform::{
+    BODYFILE_NAME, CASE_METADATA_NAME, CASE_UCO_NAME, DFXML_NAME, FAILURES_NAME, FINDINGS_NAME,
+    JSONL_MANIFEST_NAME, MANIFEST_NAME, SCAN_FINDINGS_NAME, SEAL_NAME, SQLITE_MANIFEST_NAME,
+    TIMELINE_NAME,
+};
+use crate::sink::{ArchiveFormat, METADATA_NAME, entry_name};
+use crate::utils::FILE_BUFFER_SIZE;
+
+/// Reports written next to the manifest without a manifest row, checked
+/// against the digests in the seal
+const REPORT_NAMES: &[&str] = &[
+    CASE_METADATA_NAME,
+    FINDINGS_NAME,
+    SCAN_FINDINGS_NAME,
+    BODYFILE_NAME,
+    TIMELINE_NAME,
+    DFXML_NAME,
+    CASE_UCO_NAME,
+    JSONL_MANIFEST_NAME,
+    SQLITE_MANIFEST_NAME,
+    FAILURES_NAME,
+    METADATA_NAME,
+];
+
+/// Hash and size of a file found in the collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryDigest {
+    pub sha1: String,
+    pub size: u64,
+}
+
+/// Every file of a collection, keyed by entry name.
+#[derive(Debug, Default)]
+pub struct CollectionContents {
+    pub entries: BTreeMap<String, EntryDigest>,
+    /// SHA256 of the reports next to the manifest, by name
+    pub reports: BTreeMap<String, String>,
+    pub manifest: Option<Vec<u8>>,
+    pub seal: Option<Vec<u8>>,
+}
+
+impl CollectionContents {
+    /// Read a collection folder (the one holding `Collector_copy.csv`) or a
+    /// ZIP / tar archive.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        if path.is_dir() {
+            return Self::read_directory(path);
+        }
+        if !path.exists() {
+            return Err(CollectorError::PathNotFound(path.to_path_buf()));
+        }
+
+        match ArchiveFormat::from_path(path) {
+            Some(ArchiveFormat::Zip) => Self::read_zip(path),
+            Some(format) => Self::read_tar(path, format),
+            None => Err(CollectorError::UnsupportedCollection(path.to_path_buf())),
+        }
+    }
+
+    fn read_directory(root: &Path) -> Result<Self> {
+        let mut contents = Self::default();
+
+        for entry in WalkDir::new(root).sort_by_file_name() {
+            let entry = entry.map_err(|e| CollectorError::Io(e.into()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let name = entry_name(&relative.to_string_lossy());
+            let file = File::open(entry.path()).map_err(|e| CollectorError::FileRead {
+                path: entry.path().to_path_buf(),
+                source: e,
+            })?;
+            contents
+                .add(name, file)
+                .map_err(|e| CollectorError::FileRead {
+                    path: entry.path().to_path_buf(),
+                    source: e,
+                })?;
+        }
+
+        Ok(contents)
+    }
+
+    fn read_zip(path: &Path) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(BufReader::new(open(path)?))?;
+        let mut contents = Self::default();
+
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry_name(entry.name());
+            contents.add(name, entry).map_err(|e| read_error(path, e))?;
+        }
+
+        Ok(contents)
+    }
+
+    fn read_tar(path: &Path, format: ArchiveFormat) -> Result<Self> {
+        let mut archive = tar::Archive::new(tar_reader(path, format)?);
+        let mut contents = Self::default();
+
+        for entry in archive.entries().map_err(|e| read_error(path, e))? {
+            let entry = entry.map_err(|e| read_error(path, e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry_name(
+                &entry
+                    .path()
+                    .map_err(|e| read_error(path, e))?
+                    .to_string_lossy(),
+            );
+            contents.add(name, entry).map_err(|e| read_error(path, e))?;
+        }
+
+        Ok(contents)
+    }
+
+    /// Hash one entry, keeping the manifest and seal contents aside.
+    fn add<R: Read>(&mut self, name: String, mut reader: R) -> io::Result<()> {
+        if name == MANIFEST_NAME || name == SEAL_NAME {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            if name == MANIFEST_NAME {
+                self.manifest = Some(data);
+            } else {
+                self.seal = Some(data);
+            }
+            return Ok(());
+        }
+        if REPORT_NAMES.contains(&name.as_str()) {
+            let (sha256, _) = digest::<Sha256, _>(reader)?;
+            self.reports.insert(name, sha256);
+            return Ok(());
+        }
+
+        let (sha1, size) = digest::<Sha1, _>(reader)?;
+        self.entries.insert(name, EntryDigest { sha1, size });
+        Ok(())
+    }
+}
+
+/// Hex digest and size of everything `reader` yields.
+fn digest<D: Digest, R: Read>(mut reader: R) -> io::Result<(String, u64)> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; FILE_BUFFER_SIZE];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Read only the manifest of a collection folder or archive. A path to a CSV
+/// file is read as the manifest itself.
+pub fn read_collection_manifest<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    read_collection_files(path, &[MANIFEST_NAME])?
+        .remove(MANIFEST_NAME)
+        .ok_or_else(|| CollectorError::ManifestVerification(format!("{} not found", MANIFEST_NAME)))
+}
+
+/// Read the files of a collection folder or archive with the given names,
+/// skipping the others. A path to a CSV file is read as the manifest itself,
+/// with no other file.
+pub fn read_collection_files<P: AsRef<Path>>(
+    path: P,
+    names: &[&str],
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let path = path.as_ref();
+    let read_file = |path: &Path| {
+        std::fs::read(path).map_err(|e| CollectorError::FileRead {
+            path: path.to_path_buf(),
+            source: e,
+        })
+    };
+    let mut files = BTreeMap::new();
+
+    if path.is_dir() {
+        for name in names {
+            let file = path.join(name);
+            if file.is_file() {
+                files.insert(name.to_string(), read_file(&file)?);
+            }
+        }
+        return Ok(files);
+    }
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    {
+        if names.contains(&MANIFEST_NAME) {
+            files.insert(MANIFEST_NAME.to_string(), read_file(path)?);
+        }
+        return Ok(files);
+    }
+
+    let format = ArchiveFormat::from_path(path)
+        .ok_or_else(|| CollectorError::UnsupportedCollection(path.to_path_buf()))?;
+
+    if format == ArchiveFormat::Zip {
+        let mut archive = zip::ZipArchive::new(BufReader::new(open(path)?))?;
+        for name in names {
+            let Ok(mut entry) = archive.by_name(name) else {
+                continue;
+            };
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| read_error(path, e))?;
+            files.insert(name.to_string(), data);
+        }
+        return Ok(files);
+    }
+
+    let mut archive = tar::Archive::new(tar_reader(path, format)?);
+    for entry in archive.entries().map_err(|e| read_error(path, e))? {
+        let mut entry = entry.map_err(|e| read_error(path, e))?;
+        let name = entry_name(
+            &entry
+                .path()
+                .map_err(|e| read_error(path, e))?
+                .to_string_lossy(),
+        );
+        if names.contains(&name.as_str()) {
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| read_error(path, e))?;
+            files.insert(name, data);
+            if files.len() == names.len() {
+                break;
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// File whose content no longer matches the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModifiedFile {
+    pub path: String,
+    pub expected_sha1: String,
+    pub actual_sha1: String,
+}
+
+/// Outcome of [`verify_collection`].
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub files_checked: u64,
+    pub matched: u64,
+    /// Listed in the manifest without being copied, e.g. unchanged files.
+    pub skipped: u64,
+    /// Listed in the manifest, absent from the collection.
+    pub missing: Vec<String>,
+    /// Present in the collection, absent from the manifest.
+    pub extra: Vec<String>,
+    pub modified: Vec<ModifiedFile>,
+    /// Reports, case metadata included, matching their sealed digest.
+    pub reports_matched: u64,
+    /// Reports whose content differs from their sealed digest.
+    pub modified_reports: Vec<String>,
+    /// Reports present without a sealed digest, so left unchecked.
+    pub unverified: Vec<String>,
+    /// Manifest chain and seal check, when the collection holds a seal.
+    pub manifest: Option<ManifestVerification>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.modified.is_empty()
+            && self.modified_reports.is_empty()
+            && self.manifest.as_ref().is_none_or(|m| m.is_valid())
+    }
+}
+
+/// Verify the collection at `path` against its manifest, and the manifest
+/// against its seal when there is one.
+pub async fn verify_collection<P: AsRef<Path>>(
+    path: P,
+    trusted_key: Option<&VerifyingKey>,
+) -> Result<VerifyReport> {
+    let path: PathBuf = path.as_ref().to_path_buf();
+    let contents = tokio::task::spawn_blocking(move || CollectionContents::read(&path))
+        .await
+        .map_err(|e| CollectorError::CollectionFailed(e.to_string()))??;
+
+    compare(contents, trusted_key).await
+}
+
+/// Compare hashed collection contents with the manifest they carry.
+pub async fn compare(
+    mut contents: CollectionContents,
+    trusted_key: Option<&VerifyingKey>,
+) -> Result<VerifyReport> {
+    let manifest = contents.manifest.take().ok_or_else(|| {
+        CollectorError::ManifestVerification(format!("{} not found", MANIFEST_NAME))
+    })?;
+
+    let seal = contents
+        .seal
+        .as_deref()
+        .map(ManifestSeal::from_json)
+        .transpose()?;
+    let mut report = VerifyReport {
+        manifest: match seal {
+            Some(ref seal) => Some(verify_manifest(&manifest, seal, trusted_key).await?),
+            None if trusted_key.is_some() => {
+                return Err(CollectorError::ManifestVerification(format!(
+                    "{} not found, cannot check the signature",
+                    SEAL_NAME
+                )));
+            }
+            None => None,
+        },
+        ..Default::default()
+    };
+
+    for item in read_manifest_items(&manifest).await? {
+        if !item.is_copied() {
+            // Failed rows may name the partial entry an archive kept
+            if !item.destination_file.is_empty() {
+                contents.entries.remove(&entry_name(&item.destination_file));
+            }
+            report.skipped += 1;
+            continue;
+        }
+        report.files_checked += 1;
+
+        let name = entry_name(&item.destination_file);
+        let expected_sha1 = item.hash_sha1;
+
+        match contents.entries.remove(&name) {
+            None => report.missing.push(name),
+            Some(actual) if !actual.sha1.eq_ignore_ascii_case(&expected_sha1) => {
+                report.modified.push(ModifiedFile {
+                    path: name,
+                    expected_sha1,
+                    actual_sha1: actual.sha1,
+                });
+            }
+            Some(_) => report.matched += 1,
+        }
+    }
+
+    report.extra = contents.entries.into_keys().collect();
+
+    let mut sealed = seal
+        .map(|seal| seal.metadata)
+        .map_or_else(BTreeMap::new, |metadata| {
+            let mut sealed = metadata.report_sha256;
+            if let Some(case) = metadata.case_metadata_sha256 {
+                sealed.insert(CASE_METADATA_NAME.to_string(), case);
+            }
+            sealed
+        });
+    for (name, sha256) in contents.reports {
+        match sealed.remove(&name) {
+            None => report.unverified.push(name),
+            Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+                report.modified_reports.push(name)
+            }
+            Some(_) => report.reports_matched += 1,
+        }
+    }
+    report.missing.extend(sealed.into_keys());
+
+    Ok(report)
+}
+
+fn open(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| CollectorError::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Decompressed tar stream of the archive at `path`.
+fn tar_reader(path: &Path, format: ArchiveFormat) -> Result<Box<dyn Read>> {
+    let file = BufReader::new(open(path)?);
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarZst => {
+            Box::new(zstd::Decoder::with_buffer(file).map_err(|e| read_error(path, e))?)
+        }
+        _ => Box::new(file),
+    })
+}
+
+fn read_error(path: &Path, source: io::Error) -> CollectorError {
+    CollectorError::FileRead {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::ArtifactCollector;
+    use crate::sink::ArchiveOptions;
+
+    async fn collect(temp_dir: &Path, options: Option<ArchiveOptions>) -> PathBuf {
+        let source = temp_dir.join("source");
+        let dest = temp_dir.join("dest");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.txt"), b"alpha").unwrap();
+        std::fs::write(source.join("b.txt"), b"beta").unwrap();
+        let patterns = vec![format!("{}/*.txt", source.display())];
+
+        match options {
+            Some(options) => {
+                let mut collector = ArtifactCollector::new_archive("/", &dest, patterns, options)
+                    .await
+                    .unwrap();
+                collector.collect().await.unwrap();
+                collector.finish().await.unwrap().remove(0)
+            }
+            None => {
+                let mut collector = ArtifactCollector::new("/", &dest, patterns).await.unwrap();
+                collector.collect().await.unwrap();
+                let root = collector.writer().full_destination().to_path_buf();
+                collector.finish().await.unwrap();
+                root
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = collect(temp_dir.path(), None).await;
+
+        let report = verify_collection(&root, None).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.matched, 2);
+        assert_eq!(report.reports_matched, 1);
+        assert_eq!(report.unverified, vec![METADATA_NAME.to_string()]);
+        assert!(report.manifest.unwrap().is_valid());
+
+        let collected = |name: &str| {
+            WalkDir::new(&root)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .find(|e| e.file_name() == name)
+                .unwrap()
+                .into_path()
+        };
+        std::fs::write(collected("a.txt"), b"tampered").unwrap();
+        std::fs::remove_file(collected("b.txt")).unwrap();
+        std::fs::write(root.join("planted.txt"), b"x").unwrap();
+        std::fs::write(root.join(CASE_METADATA_NAME), b"{}").unwrap();
+
+        let report = verify_collection(&root, None).await.unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.modified.len(), 1);
+        assert!(report.modified[0].path.ends_with("a.txt"));
+        assert_eq!(report.missing.len(), 1);
+        assert!(report.missing[0].ends_with("b.txt"));
+        assert_eq!(report.extra, vec!["planted.txt".to_string()]);
+        assert_eq!(
+            report.modified_reports,
+            vec![CASE_METADATA_NAME.to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_archives() {
+        for format in [ArchiveFormat::Zip, ArchiveFormat::TarZst] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let options = ArchiveOptions::default().with_format(format);
+            let archive = collect(temp_dir.path(), Some(options)).await;
+
+            let report = verify_collection(&archive, None).await.unwrap();
+            assert!(report.is_ok(), "{}", format);
+            assert_eq!(report.files_checked, 2);
+            assert_eq!(report.reports_matched, 1);
+            assert!(report.unverified.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_unknown_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("Collector_host.zip.age");
+        std::fs::write(&path, b"age").unwrap();
+
+        assert!(matches!(
+            verify_collection(&path, None).await,
+            Err(CollectorError::UnsupportedCollection(_))
+        ));
+    }
+}
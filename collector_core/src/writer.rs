@@ -1,17 +1,17 @@
-use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use sysinfo::System;
 use tokio::fs::{self, File, OpenOptions};
-use tokio::io::AsyncReadExt;
 use walkdir::WalkDir;
-use zip::{AesMode, ZipWriter, write::SimpleFileOptions};
 
 use crate::error::{CollectorError, Result};
+use crate::sink::{
+    ArchiveFile, ArchiveOptions, EntryMetadata, METADATA_NAME, OutputSink, TarSink, ZipSink,
+    entry_name, parse_recipients, read_recorded_metadata,
+};
 use crate::utils::{FormatSource, normalize_path};
 
-const ZIP_BUFFER_SIZE: usize = 4096;
-
+#[derive(Clone, Debug)]
 pub struct Writer {
     base_destination: FormatSource,
     full_destination: FormatSource,
@@ -117,35 +117,92 @@ impl Writer {
         Ok(())
     }
 
-    pub async fn create_archive(&self, password: Option<String>) -> Result<()> {
-        let zip_name = format!("Collector_{}.zip", self.hostname);
-        let zip_path = self.base_destination.join(&zip_name).to_path_buf();
+    /// Path of the archive produced next to the collection folder.
+    pub fn archive_path(&self, extension: &str) -> PathBuf {
+        self.base_destination
+            .join(format!("Collector_{}.{}", self.hostname, extension))
+            .to_path_buf()
+    }
+
+    /// Manifest location while an archive is being streamed; it is appended
+    /// to the archive as the last entry and then removed.
+    pub fn scratch_manifest_path(&self) -> PathBuf {
+        self.scratch_path("Collector_copy.csv")
+    }
+
+    /// Location of the document `name` while an archive is being streamed,
+    /// next to the scratch manifest.
+    pub fn scratch_path(&self, name: &str) -> PathBuf {
+        let name = name.strip_prefix("Collector_").unwrap_or(name);
+        self.base_destination
+            .join(format!(".Collector_{}_{}.part", self.hostname, name))
+            .to_path_buf()
+    }
 
-        let file = std::fs::File::create(&zip_path).map_err(|e| CollectorError::ZipCreation {
-            path: zip_path.clone(),
-            reason: e.to_string(),
+    /// Open the archive sink used for direct and post-collection archiving.
+    pub fn create_archive_sink(&self, options: &ArchiveOptions) -> Result<Box<dyn OutputSink>> {
+        let base = self.base_destination.to_path_buf();
+        std::fs::create_dir_all(&base).map_err(|e| CollectorError::DirectoryCreate {
+            path: base,
+            source: e,
         })?;
 
-        let mut zip = ZipWriter::new(file);
+        let compression = options.format.tar_compression();
+        if compression.is_some() && options.password.is_some() {
+            return Err(CollectorError::Config(format!(
+                "Password protection is only available for ZIP archives, not {}",
+                options.format
+            )));
+        }
 
-        let mut options = SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated)
-            .unix_permissions(0o644);
+        let recipients = parse_recipients(&options.recipients)?;
+        let mut file =
+            ArchiveFile::create(self.archive_path(&options.extension()), options.volume_size)?;
+        if !recipients.is_empty() {
+            file = file.encrypt(&recipients)?;
+        }
 
-        if let Some(ref pwd) = password {
-            options = options.with_aes_encryption(AesMode::Aes256, pwd);
+        match compression {
+            None => Ok(Box::new(ZipSink::new(file, options.password.clone()))),
+            Some(compression) => Ok(Box::new(TarSink::compressed(file, compression)?)),
         }
+    }
+
+    pub async fn create_archive(&self, options: &ArchiveOptions) -> Result<Vec<PathBuf>> {
+        self.create_archive_with_progress(options, |_, _, _| {})
+            .await
+    }
 
+    /// Archive the staging folder, calling `progress` with the 1-based
+    /// index, total and name of each entry before it is added.
+    pub async fn create_archive_with_progress<F>(
+        &self,
+        options: &ArchiveOptions,
+        mut progress: F,
+    ) -> Result<Vec<PathBuf>>
+    where
+        F: FnMut(u64, u64, &str),
+    {
+        let archive_path = self.archive_path(&options.extension());
+
+        let mut sink = self.create_archive_sink(options)?;
         let full_dest_path = self.full_destination.to_path_buf();
-        let mut buffer = vec![0u8; ZIP_BUFFER_SIZE];
 
+        let mut files = Vec::new();
         for entry in WalkDir::new(&full_dest_path) {
             let entry = entry.map_err(|e| CollectorError::ZipCreation {
-                path: zip_path.clone(),
+                path: archive_path.clone(),
                 reason: e.to_string(),
             })?;
 
-            let path = entry.path();
+            if entry.file_type().is_file() && entry.file_name() != METADATA_NAME {
+                files.push(entry.into_path());
+            }
+        }
+        let mut recorded = read_recorded_metadata(&full_dest_path)?;
+
+        let total = files.len() as u64;
+        for (index, path) in files.iter().enumerate() {
             let relative_path =
                 path.strip_prefix(&full_dest_path)
                     .map_err(|e| CollectorError::ZipCreation {
@@ -153,38 +210,22 @@ impl Writer {
                         reason: e.to_string(),
                     })?;
 
-            if path.is_file() {
-                zip.start_file_from_path(relative_path, options.clone())?;
-
-                let mut file =
-                    fs::File::open(path)
-                        .await
-                        .map_err(|e| CollectorError::FileRead {
-                            path: path.to_path_buf(),
-                            source: e,
-                        })?;
-
-                loop {
-                    let bytes_read =
-                        file.read(&mut buffer)
-                            .await
-                            .map_err(|e| CollectorError::FileRead {
-                                path: path.to_path_buf(),
-                                source: e,
-                            })?;
-
-                    if bytes_read == 0 {
-                        break;
-                    }
-
-                    zip.write_all(&buffer[..bytes_read])?;
-                }
-            } else if !relative_path.as_os_str().is_empty() {
-                zip.add_directory_from_path(relative_path, options.clone())?;
+            let name = relative_path.to_string_lossy();
+            progress(index as u64 + 1, total, &name);
+
+            // Staged copies belong to the collector: take mode, owner and
+            // extended attributes from the source
+            let mut metadata = EntryMetadata::from_path(path);
+            if let Some(source) = recorded.remove(&entry_name(&name)) {
+                metadata.mode = source.mode;
+                metadata.uid = source.uid;
+                metadata.gid = source.gid;
+                metadata.xattrs = source.xattrs;
             }
+            sink.add_file_with_metadata(&name, path, &metadata)?;
         }
 
-        zip.finish()?;
+        let outputs = sink.finalize()?;
 
         fs::remove_dir_all(&full_dest_path)
             .await
@@ -193,8 +234,7 @@ impl Writer {
                 source: e,
             })?;
 
-        log::info!("Created ZIP: {}", zip_path.display());
-        Ok(())
+        Ok(outputs)
     }
 
     pub fn csv_log_path(&self) -> PathBuf {
@@ -205,6 +245,7 @@ impl Writer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sink::{ArchiveFormat, VolumeWriter};
     use tempfile::tempdir;
 
     #[test]
@@ -260,6 +301,149 @@ mod tests {
         assert!(dir_path.is_dir());
     }
 
+    #[test]
+    fn test_archive_paths() {
+        let writer = Writer::with_folder_name("./output", "Test").unwrap();
+
+        assert!(writer.archive_path("zip").ends_with("Collector_Test.zip"));
+        assert!(
+            writer
+                .scratch_manifest_path()
+                .to_string_lossy()
+                .ends_with(".part")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_archive_removes_staging() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        writer.create_file("dir/file.txt").await.unwrap();
+
+        let mut entries = Vec::new();
+        let outputs = writer
+            .create_archive_with_progress(&ArchiveOptions::default(), |index, total, name| {
+                entries.push((index, total, name.replace('\\', "/")))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(entries, vec![(1, 1, "dir/file.txt".to_string())]);
+        assert_eq!(outputs, vec![writer.archive_path("zip")]);
+        assert!(writer.archive_path("zip").exists());
+        assert!(!writer.full_destination().exists());
+    }
+
+    #[tokio::test]
+    async fn test_create_tar_archive() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        writer.create_file("dir/file.txt").await.unwrap();
+
+        let options = ArchiveOptions::default().with_format(ArchiveFormat::TarZst);
+        writer.create_archive(&options).await.unwrap();
+
+        assert!(writer.archive_path("tar.zst").exists());
+        assert!(!writer.full_destination().exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tar_archive_takes_recorded_metadata() {
+        use crate::sink::DirectorySink;
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        let mut sink = DirectorySink::new(writer.clone());
+        let metadata = EntryMetadata {
+            mode: Some(0o4755),
+            uid: Some(4242),
+            gid: Some(4343),
+            xattrs: vec![("security.capability".to_string(), vec![1, 2, 3])],
+            ..Default::default()
+        };
+        sink.start_entry("/usr/bin/ping", &metadata).unwrap();
+        sink.write_all(b"ELF").unwrap();
+        sink.finish_entry().unwrap();
+
+        let options = ArchiveOptions::default().with_format(ArchiveFormat::Tar);
+        writer.create_archive(&options).await.unwrap();
+
+        let file = std::fs::File::open(writer.archive_path("tar")).unwrap();
+        let mut archive = tar::Archive::new(file);
+        let mut entries = archive.entries().unwrap();
+        let mut entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().to_string_lossy(), "usr/bin/ping");
+        assert_eq!(entry.header().mode().unwrap() & 0o7777, 0o4755);
+        assert_eq!(entry.header().uid().unwrap(), 4242);
+        assert_eq!(entry.header().gid().unwrap(), 4343);
+        let xattr = entry
+            .pax_extensions()
+            .unwrap()
+            .unwrap()
+            .filter_map(|ext| ext.ok())
+            .find(|ext| ext.key() == Ok("SCHILY.xattr.security.capability"))
+            .map(|ext| ext.value_bytes().to_vec());
+        assert_eq!(xattr, Some(vec![1, 2, 3]));
+        // The metadata file itself is not archived
+        assert!(entries.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_split_archive() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        writer.create_file("data.bin").await.unwrap();
+        std::fs::write(writer.get_file_path("data.bin"), [7u8; 4096]).unwrap();
+
+        let options = ArchiveOptions::default()
+            .with_format(ArchiveFormat::Tar)
+            .with_volume_size(Some(1024));
+        writer.create_archive(&options).await.unwrap();
+
+        let archive = writer.archive_path("tar");
+        assert!(!archive.exists());
+        assert!(VolumeWriter::volume_path(&archive, 1).exists());
+        assert!(VolumeWriter::volume_path(&archive, 5).exists());
+        assert!(VolumeWriter::index_path(&archive).exists());
+    }
+
+    #[tokio::test]
+    async fn test_create_encrypted_archive() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+        writer.create_file("file.txt").await.unwrap();
+
+        let identity = age::x25519::Identity::generate();
+        let options =
+            ArchiveOptions::default().with_recipients(vec![identity.to_public().to_string()]);
+        writer.create_archive(&options).await.unwrap();
+
+        let archive = writer.archive_path("zip.age");
+        let encrypted = std::fs::read(&archive).unwrap();
+        let decryptor = age::Decryptor::new(encrypted.as_slice()).unwrap();
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .unwrap();
+        let mut plain = Vec::new();
+        std::io::Read::read_to_end(&mut reader, &mut plain).unwrap();
+
+        let zip = zip::ZipArchive::new(std::io::Cursor::new(plain)).unwrap();
+        assert!(zip.file_names().any(|name| name == "file.txt"));
+    }
+
+    #[test]
+    fn test_tar_archive_rejects_password() {
+        let temp_dir = tempdir().unwrap();
+        let writer = Writer::with_folder_name(temp_dir.path(), "Test").unwrap();
+
+        let options = ArchiveOptions::default()
+            .with_format(ArchiveFormat::Tar)
+            .with_password(Some("secret".to_string()));
+        assert!(writer.create_archive_sink(&options).is_err());
+    }
+
     #[test]
     fn test_csv_log_path() {
         let writer = Writer::with_folder_name("./output", "Test").unwrap();
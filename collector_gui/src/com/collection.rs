@@ -1,12 +1,45 @@
+use collector_core::csv::seal::parse_signing_key;
+use collector_core::manifest::ManifestFormat;
 use collector_core::prelude::*;
-use std::sync::Arc;
+use collector_core::utils::parse_size;
 use tokio::sync::mpsc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CollectionProgress {
     pub current: u64,
     pub total: u64,
     pub current_file: String,
+    pub archiving: bool,
+    pub skipped: u64,
+    pub failed: u64,
+}
+
+impl CollectionProgress {
+    /// Update the progress with a collection event
+    pub fn apply(&mut self, event: &CollectionEvent) {
+        match event {
+            CollectionEvent::FileStarted {
+                index, total, path, ..
+            } => {
+                self.current = *index;
+                self.total = *total;
+                self.current_file = path.clone();
+            }
+            CollectionEvent::FileSkipped { .. } => self.skipped += 1,
+            CollectionEvent::FileFailed { .. } => self.failed += 1,
+            CollectionEvent::ArchiveProgress {
+                index,
+                total,
+                entry,
+            } => {
+                self.archiving = true;
+                self.current = *index;
+                self.total = *total;
+                self.current_file = entry.clone();
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -17,16 +50,108 @@ pub struct CollectionResult {
     // stats: Option<CollectionStats>,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct CollectionOptions {
+    pub source: String,
+    pub destination: String,
+    pub resources: Vec<String>,
+    pub resource_path: String,
+    pub vss_enabled: bool,
+    pub zip_enabled: bool,
+    pub zip_pass: Option<String>,
+    pub archive_format: ArchiveFormat,
+    pub volume_size: Option<String>,
+    pub recipients: Vec<String>,
+    pub direct_enabled: bool,
+    pub signing_key: Option<String>,
+    pub case: CaseInfo,
+    pub previous: Option<String>,
+    pub known_hashes: Vec<String>,
+    pub known_action: KnownAction,
+    pub iocs: Vec<String>,
+    pub rules: Vec<String>,
+    pub rules_only_above: Option<String>,
+    pub timeline: bool,
+    pub dfxml: bool,
+    pub case_uco: bool,
+    pub manifest_formats: Vec<ManifestFormat>,
+    pub inventory: Option<InventoryOptions>,
+    pub max_bytes_per_sec: Option<String>,
+    pub throttle: ThrottleOptions,
+    pub cancel: CancelToken,
+}
+
 pub async fn run_collection(
-    source: String,
-    destination: String,
-    resources: Vec<String>,
-    resource_path: String,
-    vss_enabled: bool,
-    zip_enabled: bool,
-    zip_pass: Option<String>,
-    progress_sender: mpsc::UnboundedSender<CollectionProgress>,
+    options: CollectionOptions,
+    event_sender: mpsc::UnboundedSender<CollectionEvent>,
 ) -> CollectionResult {
+    let CollectionOptions {
+        source,
+        destination,
+        resources,
+        resource_path,
+        vss_enabled,
+        zip_enabled,
+        zip_pass,
+        archive_format,
+        volume_size,
+        recipients,
+        direct_enabled,
+        signing_key,
+        case,
+        previous,
+        known_hashes,
+        known_action,
+        iocs,
+        rules,
+        rules_only_above,
+        timeline,
+        dfxml,
+        case_uco,
+        manifest_formats,
+        inventory,
+        max_bytes_per_sec,
+        throttle,
+        cancel,
+    } = options;
+    let case_metadata = CaseMetadata::new(case);
+
+    let volume_size = match volume_size.as_deref().map(parse_size).transpose() {
+        Ok(size) => size,
+        Err(e) => {
+            return CollectionResult {
+                success: false,
+                message: format!("Invalid volume size: {}", e),
+                // stats: None,
+            };
+        }
+    };
+    let archive_options = ArchiveOptions::default()
+        .with_format(archive_format)
+        .with_password(zip_pass)
+        .with_volume_size(volume_size)
+        .with_recipients(recipients);
+    if archive_options.is_encrypted() && !zip_enabled && !direct_enabled {
+        return CollectionResult {
+            success: false,
+            message: "Encryption recipients need an archive: enable direct archiving (or ZIP)"
+                .to_string(),
+            // stats: None,
+        };
+    }
+    let plaintext_staging = archive_options.is_encrypted() && !direct_enabled;
+
+    let signing_key = match signing_key.as_deref().map(parse_signing_key).transpose() {
+        Ok(key) => key,
+        Err(e) => {
+            return CollectionResult {
+                success: false,
+                message: format!("Invalid signing key: {}", e),
+                // stats: None,
+            };
+        }
+    };
+
     // Parse resources
     let mut parser = match ResourcesParser::new(&resource_path) {
         Ok(p) => p,
@@ -50,7 +175,7 @@ pub async fn run_collection(
         }
     };
 
-    let patterns = match parser.select_artifact(resources, &artifacts) {
+    let patterns = match parser.select_artifact(resources.clone(), &artifacts) {
         Ok(p) => p,
         Err(e) => {
             return CollectionResult {
@@ -61,49 +186,162 @@ pub async fn run_collection(
         }
     };
 
-    // Create collector
-    let mut collector = match ArtifactCollector::new(&source, &destination, patterns.clone()).await
-    {
-        Ok(c) => c,
+    let resource_set_hash = match parser.resource_set_hash().await {
+        Ok(hash) => hash,
         Err(e) => {
             return CollectionResult {
                 success: false,
-                message: format!("Failed to create collector: {}", e),
+                message: format!("Failed to hash resources: {}", e),
                 // stats: None,
             };
         }
     };
+    let case_metadata = case_metadata.with_resources(
+        parser.selected_artifacts().to_vec(),
+        Some(resource_set_hash),
+    );
+
+    let previous = match previous {
+        Some(ref path) => match PreviousCollection::load(path).await {
+            Ok(p) => Some(p),
+            Err(e) => {
+                return CollectionResult {
+                    success: false,
+                    message: format!("Failed to load previous collection: {}", e),
+                    // stats: None,
+                };
+            }
+        },
+        None => None,
+    };
+
+    let known = if known_hashes.is_empty() {
+        None
+    } else {
+        match KnownFiles::load(&known_hashes) {
+            Ok(known) => Some(known.with_action(known_action)),
+            Err(e) => {
+                return CollectionResult {
+                    success: false,
+                    message: format!("Failed to load known-good hashes: {}", e),
+                    // stats: None,
+                };
+            }
+        }
+    };
+
+    let iocs = if iocs.is_empty() {
+        None
+    } else {
+        match IocSet::load(&iocs) {
+            Ok(iocs) => Some(iocs),
+            Err(e) => {
+                return CollectionResult {
+                    success: false,
+                    message: format!("Failed to load indicators: {}", e),
+                    // stats: None,
+                };
+            }
+        }
+    };
 
-    // Collect with progress
-    let sender = Arc::new(progress_sender);
-    let stats = {
-        let sender = sender.clone();
-        match collector
-            .collect_with_progress(move |current, total, path| {
-                let _ = sender.send(CollectionProgress {
-                    current,
-                    total,
-                    current_file: path.to_string(),
-                });
-            })
-            .await
-        {
-            Ok(s) => s,
+    let rules = if rules.is_empty() {
+        None
+    } else {
+        let size_filter = match rules_only_above.as_deref().map(parse_size).transpose() {
+            Ok(size) => size,
+            Err(e) => {
+                return CollectionResult {
+                    success: false,
+                    message: format!("Invalid rule size filter: {}", e),
+                    // stats: None,
+                };
+            }
+        };
+        match RuleSet::load(&rules) {
+            Ok(rules) => Some(rules.with_size_filter(size_filter)),
             Err(e) => {
                 return CollectionResult {
                     success: false,
-                    message: format!("Collection failed: {}", e),
+                    message: format!("Failed to load scan rules: {}", e),
                     // stats: None,
                 };
             }
         }
     };
 
+    let throttle = match max_bytes_per_sec.as_deref().map(parse_size).transpose() {
+        Ok(rate) => throttle.with_bytes_per_sec(rate),
+        Err(e) => {
+            return CollectionResult {
+                success: false,
+                message: format!("Invalid read rate limit: {}", e),
+                // stats: None,
+            };
+        }
+    };
+
+    // Create collector
+    let collector = if direct_enabled {
+        ArtifactCollector::new_archive(
+            &source,
+            &destination,
+            patterns.clone(),
+            archive_options.clone(),
+        )
+        .await
+    } else {
+        ArtifactCollector::new(&source, &destination, patterns.clone()).await
+    };
+    let mut collector = match collector {
+        Ok(c) => c
+            .with_signing_key(signing_key)
+            .with_case_metadata(case_metadata)
+            .with_previous_collection(previous)
+            .with_known_files(known)
+            .with_ioc_set(iocs)
+            .with_rules(rules)
+            .with_timeline(timeline)
+            .with_dfxml(dfxml)
+            .with_case_uco(case_uco)
+            .with_manifest_formats(manifest_formats)
+            .with_inventory(inventory)
+            .with_throttle(throttle)
+            .with_cancel_token(cancel)
+            .with_event_handler(event_sender),
+        Err(e) => {
+            return CollectionResult {
+                success: false,
+                message: format!("Failed to create collector: {}", e),
+                // stats: None,
+            };
+        }
+    };
+
+    // Collect, progress goes through the event channel
+    let stats = match collector.collect().await {
+        Ok(s) => s,
+        Err(e) => {
+            return CollectionResult {
+                success: false,
+                message: format!("Collection failed: {}", e),
+                // stats: None,
+            };
+        }
+    };
+
+    let cancelled = collector.is_cancelled();
+
     // VSS collection (Windows only)
     #[cfg(target_os = "windows")]
-    if vss_enabled {
+    if vss_enabled && !cancelled {
         let mut vss_collector = VssCollector::new(&source, &destination, patterns);
-        if let Err(e) = vss_collector.collect_from_snapshots().await {
+        let vss_result = if direct_enabled {
+            vss_collector.collect_into(&mut collector).await
+        } else {
+            vss_collector.collect_from_snapshots().await
+        };
+        if let Err(e) = vss_result {
             return CollectionResult {
                 success: false,
                 message: format!("VSS collection failed: {}", e),
@@ -117,23 +355,85 @@ pub async fn run_collection(
     let _ = vss_enabled;
 
     // Zip if enabled
-    if zip_enabled {
-        if let Err(e) = collector.create_archive(zip_pass).await {
+    if direct_enabled {
+        if let Err(e) = collector.finish().await {
+            return CollectionResult {
+                success: false,
+                message: format!("Failed to finalize archive: {}", e),
+                // stats: Some(stats),
+            };
+        }
+    } else if zip_enabled {
+        if let Err(e) = collector.create_archive(&archive_options).await {
             return CollectionResult {
                 success: false,
                 message: format!("Failed to create archive: {}", e),
                 // stats: Some(stats),
             };
         }
+    } else if let Err(e) = collector.finish().await {
+        return CollectionResult {
+            success: false,
+            message: format!("Failed to seal manifest: {}", e),
+            // stats: Some(stats),
+        };
     }
 
-    CollectionResult {
-        success: true,
-        message: format!(
+    if cancelled {
+        return CollectionResult {
+            success: false,
+            message: format!(
+                "Collection cancelled: {} files ({}) sealed as a partial collection",
+                stats.files_collected + stats.files_inventoried,
+                format_bytes(stats.bytes_collected + stats.bytes_inventoried)
+            ),
+            // stats: Some(stats),
+        };
+    }
+
+    let mut message = if stats.files_inventoried > 0 {
+        format!(
+            "Inventory completed: {} files ({})",
+            stats.files_inventoried,
+            format_bytes(stats.bytes_inventoried)
+        )
+    } else {
+        format!(
             "Collection completed: {} files ({})",
             stats.files_collected,
             format_bytes(stats.bytes_collected)
-        ),
+        )
+    };
+    if stats.ioc_matches > 0 {
+        message.push_str(&format!(
+            ", {} files match indicators of compromise",
+            stats.ioc_matches
+        ));
+    }
+    if stats.rule_matches > 0 {
+        message.push_str(&format!(", {} files match scan rules", stats.rule_matches));
+    }
+    if stats.failed_extractions > 0 {
+        message.push_str(&format!(
+            ", {} files could not be collected (see Collector_failures.json)",
+            stats.failed_extractions
+        ));
+    }
+    if archive_options.is_encrypted() {
+        message.push_str(&format!(
+            ", archive encrypted to {} recipient(s)",
+            archive_options.recipients.len()
+        ));
+    }
+    if plaintext_staging {
+        message.push_str(
+            " (the plaintext staging copy was deleted but not wiped, use direct archiving to keep it off the disk)",
+        );
+    }
+
+    CollectionResult {
+        success: true,
+        message,
         // stats: Some(stats),
     }
 }
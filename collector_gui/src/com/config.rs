@@ -1,4 +1,7 @@
 use crate::utils::*;
+use collector_core::known::KnownAction;
+use collector_core::manifest::ManifestFormat;
+use collector_core::sink::ArchiveFormat;
 use serde::Deserialize;
 use std::fs;
 
@@ -11,6 +14,31 @@ pub(crate) struct Config {
     pub(crate) verbose: Option<bool>,
     pub(crate) zip: Option<bool>,
     pub(crate) zip_pass: Option<String>,
+    pub(crate) recipients: Option<Vec<String>>,
+    pub(crate) format: Option<ArchiveFormat>,
+    pub(crate) volume_size: Option<String>,
+    pub(crate) direct: Option<bool>,
+    pub(crate) signing_key: Option<String>,
+    pub(crate) previous: Option<String>,
+    pub(crate) known_hashes: Option<Vec<String>>,
+    pub(crate) iocs: Option<Vec<String>>,
+    pub(crate) rules: Option<Vec<String>>,
+    pub(crate) rules_only_above: Option<String>,
+    pub(crate) known_action: Option<KnownAction>,
+    pub(crate) timeline: Option<bool>,
+    pub(crate) dfxml: Option<bool>,
+    pub(crate) case_uco: Option<bool>,
+    pub(crate) manifest_formats: Option<Vec<ManifestFormat>>,
+    pub(crate) inventory: Option<bool>,
+    pub(crate) inventory_roots: Option<Vec<String>>,
+    pub(crate) inventory_hash: Option<bool>,
+    pub(crate) case_number: Option<String>,
+    pub(crate) examiner: Option<String>,
+    pub(crate) case_notes: Option<String>,
+    pub(crate) max_bytes_per_sec: Option<String>,
+    pub(crate) max_files_per_sec: Option<f64>,
+    pub(crate) low_io_priority: Option<bool>,
+    pub(crate) nice: Option<i32>,
     pub(crate) vss: Option<bool>,
     pub(crate) log: Option<bool>,
 }
@@ -59,6 +87,14 @@ impl Config {
             self.zip_pass = None;
         }
 
+        if self.format.is_none() {
+            self.format = Some(ArchiveFormat::default());
+        }
+
+        if self.direct.is_none() {
+            self.direct = Some(false);
+        }
+
         #[cfg(target_os = "windows")]
         if self.vss.is_none() {
             self.vss = Some(false);
@@ -83,6 +119,11 @@ pub(crate) struct AppData {
     pub(crate) verbose: Option<bool>,
     pub(crate) zip: bool,
     pub(crate) zip_pass: Option<String>,
+    pub(crate) format: ArchiveFormat,
+    pub(crate) volume_size: Option<String>,
+    pub(crate) direct: bool,
+    pub(crate) case_number: Option<String>,
+    pub(crate) examiner: Option<String>,
     pub(crate) vss: bool,
     pub(crate) log: Option<bool>,
 }
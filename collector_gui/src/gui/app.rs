@@ -1,5 +1,5 @@
 use crate::com::Resource;
-use crate::com::collection::CollectionProgress;
+use crate::com::collection::{CollectionOptions, CollectionProgress};
 use crate::com::config::AppData;
 use crate::com::{
     collection::run_collection, config::Config, filter_resources, get_categories, load_resources,
@@ -10,6 +10,11 @@ use crate::views::{
     view_footer, view_input_section, view_output_section, view_resource_modal,
     view_resources_section,
 };
+use collector_core::cancel::CancelToken;
+use collector_core::case::CaseInfo;
+use collector_core::platform::InventoryOptions;
+use collector_core::sink::ArchiveFormat;
+use collector_core::throttle::ThrottleOptions;
 use dark_light::Mode;
 use iced::widget::{column, container, row};
 use iced::{Element, Length, Subscription, Task, Theme};
@@ -37,6 +42,10 @@ pub struct CollectorApp {
     pub source_path: Option<PathBuf>,
     pub destination_path: Option<PathBuf>,
 
+    // Case
+    pub case_number: String,
+    pub examiner: String,
+
     // VSS
     pub vss_enabled: bool,
 
@@ -44,6 +53,9 @@ pub struct CollectorApp {
     pub zip_enabled: bool,
     pub zip_password_enabled: bool,
     pub zip_password: String,
+    pub archive_format: ArchiveFormat,
+    pub volume_size: String,
+    pub direct_enabled: bool,
 
     // Resources
     pub resources: Vec<Resource>,
@@ -68,6 +80,9 @@ pub struct CollectorApp {
 
     // Shared progress for subscription
     pub shared_progress: Arc<Mutex<Option<CollectionProgress>>>,
+
+    // Cancels the running collection
+    pub cancel_token: CancelToken,
 }
 
 impl CollectorApp {
@@ -128,6 +143,18 @@ impl CollectorApp {
                 Task::none()
             }
 
+            Message::CaseNumberChanged(case_number) => {
+                self.case_number = case_number.clone();
+                self.app_data.case_number = Some(case_number).filter(|s| !s.trim().is_empty());
+                Task::none()
+            }
+
+            Message::ExaminerChanged(examiner) => {
+                self.examiner = examiner.clone();
+                self.app_data.examiner = Some(examiner).filter(|s| !s.trim().is_empty());
+                Task::none()
+            }
+
             Message::ToggleVss(enabled) => {
                 self.vss_enabled = enabled;
                 self.app_data.vss = enabled;
@@ -139,10 +166,35 @@ impl CollectorApp {
                 self.app_data.zip = enabled;
                 if !enabled {
                     self.zip_password_enabled = false;
+                    self.direct_enabled = false;
+                    self.app_data.direct = false;
+                }
+                Task::none()
+            }
+
+            Message::ArchiveFormatSelected(format) => {
+                self.archive_format = format;
+                self.app_data.format = format;
+                if format != ArchiveFormat::Zip {
+                    self.zip_password_enabled = false;
+                    self.zip_password.clear();
+                    self.app_data.zip_pass = None;
                 }
                 Task::none()
             }
 
+            Message::VolumeSizeChanged(size) => {
+                self.volume_size = size.clone();
+                self.app_data.volume_size = Some(size).filter(|s| !s.trim().is_empty());
+                Task::none()
+            }
+
+            Message::ToggleDirect(enabled) => {
+                self.direct_enabled = enabled;
+                self.app_data.direct = enabled;
+                Task::none()
+            }
+
             Message::ToggleZipPassword(enabled) => {
                 self.zip_password_enabled = enabled;
                 if !enabled {
@@ -261,6 +313,7 @@ impl CollectorApp {
                 self.progress_current = 0;
                 self.progress_total = 0;
                 self.progress_file = String::new();
+                self.cancel_token = CancelToken::new();
 
                 let source = self
                     .source_path
@@ -274,41 +327,69 @@ impl CollectorApp {
                     .unwrap_or_default();
                 let resources = self.checked_resources.clone();
                 let resource_path = self.config.resource_path.clone().unwrap_or_default();
-                let vss_enabled = self.vss_enabled;
-                let zip_enabled = self.zip_enabled;
-                let zip_pass = if self.zip_password_enabled {
-                    Some(self.zip_password.clone())
-                } else {
-                    None
+                let options = CollectionOptions {
+                    source,
+                    destination,
+                    resources,
+                    resource_path,
+                    vss_enabled: self.vss_enabled,
+                    zip_enabled: self.zip_enabled,
+                    zip_pass: if self.zip_password_enabled
+                        && self.archive_format == ArchiveFormat::Zip
+                    {
+                        Some(self.zip_password.clone())
+                    } else {
+                        None
+                    },
+                    archive_format: self.archive_format,
+                    volume_size: Some(self.volume_size.clone()).filter(|s| !s.trim().is_empty()),
+                    recipients: self.config.recipients.clone().unwrap_or_default(),
+                    direct_enabled: self.zip_enabled && self.direct_enabled,
+                    signing_key: self.config.signing_key.clone(),
+                    case: CaseInfo::new()
+                        .with_case_number(Some(self.case_number.clone()))
+                        .with_examiner(Some(self.examiner.clone()))
+                        .with_notes(self.config.case_notes.clone()),
+                    previous: self.config.previous.clone(),
+                    known_hashes: self.config.known_hashes.clone().unwrap_or_default(),
+                    iocs: self.config.iocs.clone().unwrap_or_default(),
+                    rules: self.config.rules.clone().unwrap_or_default(),
+                    rules_only_above: self.config.rules_only_above.clone(),
+                    known_action: self.config.known_action.unwrap_or_default(),
+                    timeline: self.config.timeline.unwrap_or(false),
+                    dfxml: self.config.dfxml.unwrap_or(false),
+                    case_uco: self.config.case_uco.unwrap_or(false),
+                    manifest_formats: self.config.manifest_formats.clone().unwrap_or_default(),
+                    inventory: self.config.inventory.unwrap_or(false).then(|| {
+                        InventoryOptions::new()
+                            .with_roots(self.config.inventory_roots.clone().unwrap_or_default())
+                            .with_hash(self.config.inventory_hash.unwrap_or(false))
+                    }),
+                    max_bytes_per_sec: self.config.max_bytes_per_sec.clone(),
+                    throttle: ThrottleOptions::new()
+                        .with_files_per_sec(self.config.max_files_per_sec)
+                        .with_low_io_priority(self.config.low_io_priority.unwrap_or(false))
+                        .with_nice(self.config.nice),
+                    cancel: self.cancel_token.clone(),
                 };
 
                 // Create channel for progress
                 let (tx, mut rx) = mpsc::unbounded_channel();
                 let shared_progress = self.shared_progress.clone();
 
-                // Spawn task to read progress updates
+                // Spawn task to fold collection events into the progress
                 tokio::spawn(async move {
-                    while let Some(progress) = rx.recv().await {
+                    let mut progress = CollectionProgress::default();
+                    while let Some(event) = rx.recv().await {
+                        progress.apply(&event);
                         if let Ok(mut guard) = shared_progress.lock() {
-                            *guard = Some(progress);
+                            *guard = Some(progress.clone());
                         }
                     }
                 });
 
                 Task::perform(
-                    async move {
-                        run_collection(
-                            source,
-                            destination,
-                            resources,
-                            resource_path,
-                            vss_enabled,
-                            zip_enabled,
-                            zip_pass,
-                            tx,
-                        )
-                        .await
-                    },
+                    async move { run_collection(options, tx).await },
                     Message::CollectionCompleted,
                 )
             }
@@ -331,6 +412,15 @@ impl CollectorApp {
             //     );
             //     Task::none()
             // }
+            Message::CancelCollection => {
+                if self.collection_state == CollectionState::Collecting {
+                    self.cancel_token.cancel();
+                    self.collection_message =
+                        "Cancelling, sealing the files done so far...".to_string();
+                }
+                Task::none()
+            }
+
             Message::CollectionCompleted(result) => {
                 // Clear shared progress
                 if let Ok(mut guard) = self.shared_progress.lock() {
@@ -355,16 +445,29 @@ impl CollectorApp {
                     self.progress_total = progress.total;
                     self.progress_file = progress.current_file.clone();
 
-                    let percentage = if progress.total > 0 {
-                        (progress.current * 100) / progress.total
+                    let percentage = (progress.current * 100)
+                        .checked_div(progress.total)
+                        .unwrap_or(0);
+
+                    let phase = if self.cancel_token.is_cancelled() {
+                        "Cancelling"
+                    } else if progress.archiving {
+                        "Archiving"
                     } else {
-                        0
+                        "Collecting"
                     };
-
                     self.collection_message = format!(
-                        "Collecting... {}% ({}/{})",
-                        percentage, progress.current, progress.total
+                        "{}... {}% ({}/{})",
+                        phase, percentage, progress.current, progress.total
                     );
+                    if progress.skipped > 0 {
+                        self.collection_message
+                            .push_str(&format!(", {} skipped", progress.skipped));
+                    }
+                    if progress.failed > 0 {
+                        self.collection_message
+                            .push_str(&format!(", {} failed", progress.failed));
+                    }
                     // }
                 }
                 Task::none()
@@ -421,7 +524,12 @@ impl CollectorApp {
         let zip_enabled = config.zip.unwrap_or(false);
         let zip_password = config.zip_pass.clone().unwrap_or_default();
         let zip_password_enabled = !zip_password.is_empty();
+        let archive_format = config.format.unwrap_or_default();
+        let volume_size = config.volume_size.clone().unwrap_or_default();
+        let direct_enabled = config.direct.unwrap_or(false);
         let vss_enabled = config.vss.unwrap_or(false);
+        let case_number = config.case_number.clone().unwrap_or_default();
+        let examiner = config.examiner.clone().unwrap_or_default();
 
         Self {
             config,
@@ -429,10 +537,15 @@ impl CollectorApp {
             is_dark,
             source_path,
             destination_path,
+            case_number,
+            examiner,
             vss_enabled,
             zip_enabled,
             zip_password_enabled,
             zip_password,
+            archive_format,
+            volume_size,
+            direct_enabled,
             resources: Vec::new(),
             filtered_resources: Vec::new(),
             categories: vec!["All".to_string()],
@@ -447,6 +560,7 @@ impl CollectorApp {
             progress_total: 0,
             progress_file: String::new(),
             shared_progress: Arc::new(Mutex::new(None)),
+            cancel_token: CancelToken::new(),
         }
     }
 }
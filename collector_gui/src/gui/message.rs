@@ -1,5 +1,6 @@
 use crate::com::Resource;
 use crate::com::collection::CollectionResult;
+use collector_core::sink::ArchiveFormat;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -13,12 +14,20 @@ pub enum Message {
     SourceFolderSelected(Option<PathBuf>),
     DestinationFolderSelected(Option<PathBuf>),
 
+    // Case
+    CaseNumberChanged(String),
+    ExaminerChanged(String),
+
     // VSS
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     ToggleVss(bool),
 
     // Output options
     ToggleZip(bool),
     ToggleZipPassword(bool),
+    ArchiveFormatSelected(ArchiveFormat),
+    VolumeSizeChanged(String),
+    ToggleDirect(bool),
     ZipPasswordChanged(String),
 
     // Resources
@@ -32,6 +41,7 @@ pub enum Message {
 
     // Collection
     StartCollection,
+    CancelCollection,
     // TO REMOVE
     // #[allow(dead_code)]
     // CollectionProgress { current: u64, total: u64, file: String },
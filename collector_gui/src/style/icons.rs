@@ -19,6 +19,7 @@ pub const LOADER: &[u8] = include_icon!("loader");
 pub const FILE_ARCHIVE: &[u8] = include_icon!("file-archive");
 pub const LOCK: &[u8] = include_icon!("lock");
 pub const SHIELD_CHECK: &[u8] = include_icon!("shield-check");
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 pub const HARD_DRIVE: &[u8] = include_icon!("hard-drive");
 
 /// SVG widget for icon
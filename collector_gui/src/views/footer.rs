@@ -83,10 +83,30 @@ pub fn view_footer(app: &CollectorApp) -> Element<'_, Message> {
         .style(primary_button_style)
     };
 
-    row![status_container, theme_btn, progress_section, start_btn]
-        .spacing(8)
-        .padding([8, 0])
-        .align_y(Alignment::Center)
-        .width(Length::Fill)
+    let cancel_btn: Element<'_, Message> = if app.collection_state == CollectionState::Collecting {
+        button(
+            row![icon_button(icons::X), text(" Cancel").size(14)]
+                .spacing(8)
+                .align_y(Alignment::Center),
+        )
+        .on_press_maybe((!app.cancel_token.is_cancelled()).then_some(Message::CancelCollection))
+        .padding([10, 20])
+        .style(primary_button_style)
         .into()
+    } else {
+        Space::new().width(Length::Fixed(0.0)).into()
+    };
+
+    row![
+        status_container,
+        theme_btn,
+        progress_section,
+        start_btn,
+        cancel_btn
+    ]
+    .spacing(8)
+    .padding([8, 0])
+    .align_y(Alignment::Center)
+    .width(Length::Fill)
+    .into()
 }
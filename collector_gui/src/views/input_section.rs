@@ -1,4 +1,4 @@
-use iced::widget::{button, checkbox, column, container, row, scrollable, text, text_input};
+use iced::widget::{button, column, container, row, scrollable, text, text_input};
 use iced::{Alignment, Element, Length};
 
 use crate::gui::CollectorApp;
@@ -58,11 +58,35 @@ pub fn view_input_section(app: &CollectorApp) -> Element<'_, Message> {
         // .height(Length::Fixed(25.0))
         .align_y(Alignment::Center);
 
-    let mut content = column![source_row, dest_row].spacing(15);
+    // Case details
+    let case_row = row![
+        text("Case number:").size(13),
+        text_input("Optional", &app.case_number)
+            .on_input(Message::CaseNumberChanged)
+            .width(Length::Fill)
+            .padding([2, 8]),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    let examiner_row = row![
+        text("Examiner:").size(13),
+        text_input("Optional", &app.examiner)
+            .on_input(Message::ExaminerChanged)
+            .width(Length::Fill)
+            .padding([2, 8]),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
+    let mut content = column![source_row, dest_row, case_row, examiner_row].spacing(15);
 
     // VSS label
     #[cfg(target_os = "windows")]
     {
+        use iced::widget::checkbox;
+
         let vss_row = row![
             icon_small(icons::HARD_DRIVE),
             checkbox(app.vss_enabled)
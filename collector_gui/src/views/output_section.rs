@@ -1,4 +1,7 @@
-use iced::widget::{Space, checkbox, column, container, row, scrollable, text, text_input};
+use collector_core::sink::ArchiveFormat;
+use iced::widget::{
+    Space, checkbox, column, container, pick_list, row, scrollable, text, text_input,
+};
 use iced::{Alignment, Element, Length};
 
 use crate::gui::CollectorApp;
@@ -6,7 +9,7 @@ use crate::gui::message::Message;
 use crate::style::icons::{self, icon_small};
 use crate::style::theme::{card_style, section_header_style};
 
-/// View of the checkout section (zip, password)
+/// View of the checkout section (archive format, password)
 pub fn view_output_section(app: &CollectorApp) -> Element<'_, Message> {
     let header = container(text("Output Configuration").size(14))
         .width(Length::Fill)
@@ -16,7 +19,7 @@ pub fn view_output_section(app: &CollectorApp) -> Element<'_, Message> {
     let zip_row = row![
         icon_small(icons::FILE_ARCHIVE),
         checkbox(app.zip_enabled)
-            .label(" Compress output to ZIP")
+            .label(" Archive output")
             .on_toggle(Message::ToggleZip)
             .text_size(13),
     ]
@@ -26,6 +29,60 @@ pub fn view_output_section(app: &CollectorApp) -> Element<'_, Message> {
     let mut content = column![zip_row].spacing(12);
 
     if app.zip_enabled {
+        let format_row = row![
+            text("Format").size(13).width(Length::Fixed(60.0)),
+            pick_list(
+                ArchiveFormat::ALL,
+                Some(app.archive_format),
+                Message::ArchiveFormatSelected,
+            )
+            .padding([4, 8]),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        content = content.push(format_row);
+
+        let volume_row = row![
+            text("Volumes").size(13).width(Length::Fixed(60.0)),
+            text_input("No split (e.g. 700M, 4G)", &app.volume_size)
+                .on_input(Message::VolumeSizeChanged)
+                .width(Length::Fill)
+                .padding([4, 8]),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        content = content.push(volume_row);
+
+        let recipients = app.config.recipients.as_ref().map_or(0, |r| r.len());
+        if recipients > 0 {
+            let recipients_row = row![
+                icon_small(icons::LOCK),
+                text(format!(
+                    " Encrypted to {} recipient(s) from config{}",
+                    recipients,
+                    if app.direct_enabled {
+                        ""
+                    } else {
+                        ", staged in plaintext first"
+                    }
+                ))
+                .size(13),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center);
+            content = content.push(recipients_row);
+        }
+
+        if app.config.signing_key.is_some() {
+            let signing_row = row![
+                icon_small(icons::LOCK),
+                text(" Manifest signed with key from config").size(13),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center);
+            content = content.push(signing_row);
+        }
+
         let mut pass_toggle_row = row![
             icon_small(icons::LOCK),
             checkbox(app.zip_password_enabled)
@@ -36,6 +93,17 @@ pub fn view_output_section(app: &CollectorApp) -> Element<'_, Message> {
         .spacing(8)
         .align_y(Alignment::Center);
 
+        let direct_row = row![
+            icon_small(icons::FILE_ARCHIVE),
+            checkbox(app.direct_enabled)
+                .label(" Write archive directly (no staging copy)")
+                .on_toggle(Message::ToggleDirect)
+                .text_size(13),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        content = content.push(direct_row);
+
         if app.zip_password_enabled {
             let pass_input = text_input("Enter ZIP password...", &app.zip_password)
                 .on_input(Message::ZipPasswordChanged)
@@ -46,7 +114,8 @@ pub fn view_output_section(app: &CollectorApp) -> Element<'_, Message> {
 
             pass_toggle_row = pass_toggle_row.push(pass_row);
             content = content.push(pass_toggle_row);
-        } else {
+        } else if app.archive_format == ArchiveFormat::Zip {
+            // Tar formats have no password protection
             content = content.push(pass_toggle_row);
         }
     }